@@ -1,35 +1,122 @@
+use crate::credentials::CredentialStore;
+use crate::library::LibraryWatcher;
+use crate::media::{MediaRoots, StreamProxy};
+use crate::staging::Staging;
+use crate::subsonic::OpenSubsonicExtension;
+use crate::transfer::Scheduler;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Mutex;
 
-/// 应用级的共享状态，后续可替换为 SQLite/Store 等持久化方案。
+/// 应用运行期的共享状态。服务器配置与传输队列启动时从 SQLite 载入、修改后写回，
+/// 其余为凭据、传输调度、播放代理、音乐库监听与上传暂存等运行期服务。
 #[derive(Default)]
 pub struct AppState {
     pub servers: Mutex<Vec<ServerConfig>>,                    // 服务器配置列表
     pub queues: Mutex<Vec<QueueTask>>,                      // 上传/下载队列状态
+    pub http: reqwest::Client,                              // 共享 HTTP 客户端，复用连接池
+    pub credentials: CredentialStore,                       // 以 key_ref 索引的凭据（系统钥匙串或加密文件）
+    pub scheduler: Scheduler,                               // 上传/下载任务调度器
+    pub syncing: Mutex<HashSet<String>>,                    // 正在同步元数据缓存的服务器
+    pub media_roots: Mutex<MediaRoots>,                     // musicboom:// 协议允许访问的目录
+    pub streams: StreamProxy,                               // 在线播放的边下边存代理
+    pub library: LibraryWatcher,                            // 本地音乐文件夹的监听与扫描
+    pub staging: Staging,                                   // 拖拽上传的暂存文件
 }
 
 /// Navidrome/OpenList 服务器配置模型。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub id: String,
+    #[serde(default)]
+    pub kind: ServerKind,
     pub name: String,
     pub base_url: String,
+    #[serde(default)]
+    pub username: String,
     pub key_ref: String,
+    #[serde(default)]
+    pub is_default: bool,                                   // 同类服务器中的默认项
+    #[serde(default)]
+    pub info: ServerInfo,                                   // 添加时探测到的版本信息
 }
 
-/// 队列任务的简单占位模型，后续可接入 SQLite 与真实任务调度。
+/// 探测服务器得到的类型与版本信息。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub server_type: Option<String>,                        // navidrome、gonic、openlist 等
+    pub api_version: Option<String>,                        // Subsonic 协议版本
+    pub server_version: Option<String>,
+    #[serde(default)]
+    pub open_subsonic: bool,
+    #[serde(default)]
+    pub extensions: Vec<OpenSubsonicExtension>,
+}
+
+/// 服务器类型。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerKind {
+    #[default]
+    Navidrome,
+    Openlist,
+}
+
+/// 队列任务模型，持久化在本地 SQLite 中，重启后可继续。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueueTask {
     pub id: String,
     pub kind: QueueKind,
     pub filename: String,
-    pub progress: u8,
-    pub status: String,
+    pub local_path: String,
+    pub remote_path: String,
+    pub server: String,                                     // OpenList 服务器地址，Navidrome 任务为服务器 ID
+    #[serde(default)]
+    pub source: QueueSource,                                // 任务来源，Navidrome 任务的 remote_path 为歌曲 ID
+    pub transferred: u64,                                   // 已传输字节数（下载即断点偏移）
+    pub total: u64,
+    pub retries: u32,                                       // 自动重试次数
+    #[serde(default)]
+    pub attempts: Vec<QueueAttempt>,                        // 每次失败的尝试记录
+    pub status: QueueStatus,
+    pub error: Option<String>,                              // 最近一次错误
+    #[serde(default)]
+    pub as_task: bool,                                      // 上传时是否交给服务端后台转存
+    #[serde(skip)]
+    pub password: Option<String>,                           // 加密目录密码，保存在凭据存储中，不回传前端
 }
 
-/// 队列任务类型。
+/// 传输任务单次失败的尝试记录。
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueAttempt {
+    pub attempt: u32,                                       // 第几次尝试，从 1 开始
+    pub error: String,
+    pub at: i64,                                            // 失败时间（毫秒时间戳）
+    pub retry_in: Option<u64>,                              // 下次重试前等待的毫秒数，放弃时为空
+}
+
+/// 队列任务类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QueueKind {
     Upload,
     Download,
 }
+
+/// 队列任务来源。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueSource {
+    #[default]
+    Openlist,
+    Navidrome,
+}
+
+/// 队列任务状态。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueStatus {
+    Queued,
+    Running,
+    Paused,
+    Failed,
+    Done,
+    Cancelled,
+}
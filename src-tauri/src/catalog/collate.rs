@@ -0,0 +1,63 @@
+use pinyin::ToPinyin;
+use serde::Serialize;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// 排序时忽略的前置冠词（需后接空格）。
+const ARTICLES: &[&str] = &["the ", "a ", "an "];
+/// 不以字母开头的排序键加上此前缀，统一排在 Z 之后并归入 `#` 分组。
+const OTHER_PREFIX: char = '~';
+
+/// A–Z 分组索引：分组字母、在列表中的起始位置与条数。
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionIndex {
+    /// `A`–`Z`，数字、符号及无法转成拼音的文字归入 `#`。
+    pub letter: String,
+    pub offset: u32,
+    pub count: u32,
+}
+
+/// 生成排序键：优先使用排序名标签，去掉前置冠词与变音符号，汉字转为带空格分隔的全拼，
+/// 使中文名与拉丁字母名按同一字母表交错排列，如“周杰伦” → `zhou jie lun`。
+pub fn sort_key(name: &str, sort_name: Option<&str>) -> String {
+    let source = sort_name
+        .map(str::trim)
+        .filter(|sort_name| !sort_name.is_empty())
+        .unwrap_or(name)
+        .trim();
+    let lower = source.to_lowercase();
+    let stripped = ARTICLES
+        .iter()
+        .find_map(|article| lower.strip_prefix(article))
+        .map(str::trim_start)
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(&lower);
+
+    let mut key = String::with_capacity(stripped.len() * 2);
+    for c in stripped.nfd().filter(|c| !is_combining_mark(*c)) {
+        match c.to_pinyin() {
+            Some(pinyin) => {
+                if !key.is_empty() && !key.ends_with(' ') {
+                    key.push(' ');
+                }
+                key.push_str(pinyin.plain());
+                key.push(' ');
+            }
+            None => key.push(c),
+        }
+    }
+    let key = key.trim_end();
+    if key.starts_with(|c: char| c.is_ascii_lowercase()) {
+        key.to_string()
+    } else {
+        format!("{}{}", OTHER_PREFIX, key)
+    }
+}
+
+/// 排序键所属的分组字母。
+pub fn section(key: &str) -> char {
+    match key.chars().next() {
+        Some(c) if c.is_ascii_lowercase() => c.to_ascii_uppercase(),
+        _ => '#',
+    }
+}
@@ -0,0 +1,73 @@
+use pinyin::ToPinyin;
+use zhconv::{zhconv, Variant};
+
+/// 一段文本的各种检索形式。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Folded {
+    /// 转为简体、小写，只保留字母数字与汉字。
+    pub text: String,
+    /// 全拼，不带声调与分隔，如“周杰伦” → `zhoujielun`。
+    pub pinyin: String,
+    /// 拼音首字母，如“周杰伦” → `zjl`；连续的字母数字只取第一个。
+    pub initials: String,
+}
+
+impl Folded {
+    pub fn new(text: &str) -> Self {
+        let simplified = to_simplified(text);
+        let mut folded = Self::default();
+        let mut in_word = false;
+        for c in simplified.chars().flat_map(char::to_lowercase) {
+            if let Some(pinyin) = c.to_pinyin() {
+                folded.text.push(c);
+                folded.pinyin.push_str(pinyin.plain());
+                folded.initials.push_str(pinyin.first_letter());
+                in_word = false;
+            } else if c.is_alphanumeric() {
+                folded.text.push(c);
+                folded.pinyin.push(c);
+                if !in_word {
+                    folded.initials.push(c);
+                }
+                in_word = true;
+            } else {
+                in_word = false;
+            }
+        }
+        folded
+    }
+}
+
+/// 统一为简体小写并去掉空白与标点，用于比较用户输入与缓存内容。
+pub fn fold(text: &str) -> String {
+    to_simplified(text)
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// 繁体转简体；纯 ASCII 文本直接返回，避免无谓的转换开销。
+fn to_simplified(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        zhconv(text, Variant::ZhHans)
+    }
+}
+
+/// 去掉 LRC 歌词中的时间轴与标签（如 `[00:12.34]`、`[ar:...]`），只保留歌词文本。
+pub fn strip_lrc(lyrics: &str) -> String {
+    lyrics
+        .lines()
+        .map(|line| {
+            let mut rest = line.trim();
+            while let Some(end) = rest.strip_prefix('[').and_then(|tail| tail.find(']')) {
+                rest = rest[end + 2..].trim_start();
+            }
+            rest
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,239 @@
+mod collate;
+mod fold;
+mod search;
+mod store;
+mod sync;
+
+pub use collate::SectionIndex;
+pub use search::{search, SearchMatches};
+pub use store::{
+    album_sections, artist_sections, backfill_sort_keys, load_albums, load_artists, load_genres,
+    load_playlists, load_songs,
+};
+
+use crate::db::Database;
+use crate::subsonic::{Song, SubsonicClient};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePool;
+use tauri::AppHandle;
+
+/// 缓存的实体类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheEntity {
+    Artists,
+    Albums,
+    Songs,
+    Playlists,
+    Genres,
+}
+
+impl CacheEntity {
+    pub const ALL: [Self; 5] = [
+        Self::Artists,
+        Self::Albums,
+        Self::Songs,
+        Self::Playlists,
+        Self::Genres,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Artists => "artists",
+            Self::Albums => "albums",
+            Self::Songs => "songs",
+            Self::Playlists => "playlists",
+            Self::Genres => "genres",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            Self::Artists => "cache_artists",
+            Self::Albums => "cache_albums",
+            Self::Songs => "cache_songs",
+            Self::Playlists => "cache_playlists",
+            Self::Genres => "cache_genres",
+        }
+    }
+
+    /// 主键列，流派以名称为键。
+    fn key_column(self) -> &'static str {
+        match self {
+            Self::Genres => "name",
+            _ => "id",
+        }
+    }
+
+    /// 用于错误提示的中文名称。
+    fn label(self) -> &'static str {
+        match self {
+            Self::Artists => "艺人",
+            Self::Albums => "专辑",
+            Self::Songs => "歌曲",
+            Self::Playlists => "歌单",
+            Self::Genres => "流派",
+        }
+    }
+}
+
+/// 读取缓存列表时的分页参数。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CachePaging {
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl Default for CachePaging {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 500,
+        }
+    }
+}
+
+/// 单类实体的缓存状态。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityStatus {
+    pub entity: CacheEntity,
+    pub count: u64,
+    /// 内容每变化一次加一，前端据此判断是否需要重新读取。
+    pub version: i64,
+    /// 最近一次成功同步的时间（毫秒时间戳）。
+    pub synced_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// 某个服务器的元数据缓存状态。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStatus {
+    pub server_id: String,
+    /// 是否已有可用的缓存（至少成功同步过一次）。
+    pub cache_ready: bool,
+    pub syncing: bool,
+    /// 各类实体中最早的一次成功同步时间。
+    pub last_sync: Option<i64>,
+    /// 缓存表占用的磁盘空间（字节）。
+    pub size_on_disk: u64,
+    /// 最近一次同步中出现的错误。
+    pub error: Option<String>,
+    pub entities: Vec<EntityStatus>,
+}
+
+/// 汇总缓存状态。
+pub async fn status(
+    database: &Database,
+    server_id: &str,
+    syncing: bool,
+) -> Result<CacheStatus, String> {
+    let entities = store::load_status(database.pool(), server_id).await?;
+    let size_on_disk = match store::cache_size(database.pool()).await {
+        Some(size) => size,
+        None => database_size(database),
+    };
+    let last_sync = entities
+        .iter()
+        .map(|entity| entity.synced_at)
+        .collect::<Option<Vec<i64>>>()
+        .and_then(|times| times.into_iter().min());
+    Ok(CacheStatus {
+        server_id: server_id.to_string(),
+        cache_ready: entities.iter().any(|entity| entity.synced_at.is_some()),
+        syncing,
+        last_sync,
+        size_on_disk,
+        error: entities.iter().find_map(|entity| entity.error.clone()),
+        entities,
+    })
+}
+
+/// 从服务器同步元数据到本地缓存，默认只拉取变化的部分，`full` 为真时重新拉取全部歌曲。
+/// 艺人、专辑或歌曲有变化时重建搜索索引。
+pub async fn refresh(
+    app: &AppHandle,
+    client: &SubsonicClient,
+    database: &Database,
+    server_id: &str,
+    full: bool,
+) -> Result<(), String> {
+    let pool = database.pool();
+    let before = indexed_versions(pool, server_id).await?;
+    let result = sync::sync_all(app, client, pool, server_id, full).await;
+    if indexed_versions(pool, server_id).await? != before
+        || search::is_empty(pool, server_id).await?
+    {
+        search::rebuild(pool, server_id).await?;
+    }
+    result
+}
+
+/// 是否已为该服务器建立离线搜索索引。
+pub async fn search_ready(pool: &SqlitePool, server_id: &str) -> Result<bool, String> {
+    Ok(!search::is_empty(pool, server_id).await?)
+}
+
+/// 搜索索引所依赖实体的版本号。
+async fn indexed_versions(pool: &SqlitePool, server_id: &str) -> Result<Vec<i64>, String> {
+    Ok(store::load_status(pool, server_id)
+        .await?
+        .into_iter()
+        .filter(|status| {
+            matches!(
+                status.entity,
+                CacheEntity::Artists | CacheEntity::Albums | CacheEntity::Songs
+            )
+        })
+        .map(|status| status.version)
+        .collect())
+}
+
+/// 读取歌词：优先使用缓存，没有时从服务器获取并写入缓存与搜索索引。
+///
+/// `structured` 表示服务器支持 OpenSubsonic `songLyrics` 扩展，可按歌曲 ID 获取；
+/// 否则按缓存中的艺人与标题调用 getLyrics。
+pub async fn lyrics(
+    client: Option<&SubsonicClient>,
+    pool: &SqlitePool,
+    server_id: &str,
+    song_id: &str,
+    structured: bool,
+) -> Result<Option<String>, String> {
+    if let Some(lyrics) = store::load_lyrics(pool, server_id, song_id).await? {
+        return Ok(Some(lyrics));
+    }
+    let Some(client) = client else {
+        return Ok(None);
+    };
+
+    let fetched = if structured {
+        client.get_lyrics_by_song_id(song_id).await?
+    } else {
+        let songs: Vec<Song> = store::load_by_ids(pool, server_id, &[song_id.to_string()]).await?;
+        match songs.first() {
+            Some(song) => {
+                let artist = song.artist.as_deref().unwrap_or_default();
+                client.get_lyrics(artist, &song.title).await?
+            }
+            None => None,
+        }
+    };
+    if let Some(lyrics) = &fetched {
+        store::save_lyrics(pool, server_id, song_id, lyrics).await?;
+        search::index_lyrics(pool, server_id, song_id, lyrics).await?;
+    }
+    Ok(fetched)
+}
+
+/// 无法按表统计时，以数据库文件（含 WAL）的大小近似。
+fn database_size(database: &Database) -> u64 {
+    let path = database.path();
+    let mut wal = path.as_os_str().to_os_string();
+    wal.push("-wal");
+    [path.to_path_buf(), wal.into()]
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
@@ -0,0 +1,360 @@
+use super::fold::{self, Folded};
+use super::store;
+use crate::subsonic::{Album, Artist, SearchPaging, Song};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+/// 单个关键词参与匹配的列。
+const MATCH_COLUMNS: [&str; 10] = [
+    "title",
+    "title_pinyin",
+    "title_initials",
+    "artist",
+    "artist_pinyin",
+    "artist_initials",
+    "album",
+    "album_pinyin",
+    "album_initials",
+    "lyrics",
+];
+
+/// 各字段的权重：标题 > 艺人 > 专辑 > 歌词。
+const TITLE_WEIGHT: u32 = 10;
+const ARTIST_WEIGHT: u32 = 6;
+const ALBUM_WEIGHT: u32 = 4;
+const LYRICS_WEIGHT: u32 = 1;
+
+/// 索引条目的类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Artist,
+    Album,
+    Song,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Artist => "artist",
+            Self::Album => "album",
+            Self::Song => "song",
+        }
+    }
+}
+
+/// 离线搜索结果，各类型内按相关度排列。
+#[derive(Debug, Clone, Default)]
+pub struct SearchMatches {
+    pub artists: Vec<Artist>,
+    pub albums: Vec<Album>,
+    pub songs: Vec<Song>,
+    /// 索引对应的缓存同步时间（各类实体中最早的一次），用于提示结果的新旧。
+    pub indexed_at: Option<i64>,
+}
+
+/// 在本地索引中搜索，多个关键词以空白分隔且需同时命中。
+///
+/// 关键词与索引一样转为简体小写，因此繁体、简体输入都能命中；
+/// 同时匹配全拼（`zhoujielun`）与首字母（`zjl`）。相关度在 SQL 中计算，
+/// 排序后再按 search3 相同的分页参数截取，短关键词也不会漏掉高分结果。
+pub async fn search(
+    pool: &SqlitePool,
+    server_id: &str,
+    keyword: &str,
+    paging: &SearchPaging,
+) -> Result<SearchMatches, String> {
+    let terms: Vec<String> = keyword
+        .split_whitespace()
+        .map(fold::fold)
+        .filter(|term| !term.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return Ok(SearchMatches::default());
+    }
+
+    let artists = ranked_ids(
+        pool,
+        server_id,
+        Kind::Artist,
+        &terms,
+        paging.artist_offset,
+        paging.artist_count,
+    )
+    .await?;
+    let albums = ranked_ids(
+        pool,
+        server_id,
+        Kind::Album,
+        &terms,
+        paging.album_offset,
+        paging.album_count,
+    )
+    .await?;
+    let songs = ranked_ids(
+        pool,
+        server_id,
+        Kind::Song,
+        &terms,
+        paging.song_offset,
+        paging.song_count,
+    )
+    .await?;
+    Ok(SearchMatches {
+        artists: store::load_by_ids(pool, server_id, &artists).await?,
+        albums: store::load_by_ids(pool, server_id, &albums).await?,
+        songs: store::load_by_ids(pool, server_id, &songs).await?,
+        indexed_at: indexed_at(pool, server_id).await?,
+    })
+}
+
+/// 取某类条目按相关度排序后的一页 ID；分数相同时标题越短越接近关键词。
+///
+/// 参数按编号绑定：`?1` 为服务器 ID，第 i 个关键词依次占用完全相同、前缀、包含三个模式。
+async fn ranked_ids(
+    pool: &SqlitePool,
+    server_id: &str,
+    kind: Kind,
+    terms: &[String],
+    offset: u32,
+    count: u32,
+) -> Result<Vec<String>, String> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut filters = Vec::with_capacity(terms.len());
+    let mut scores = Vec::with_capacity(terms.len());
+    for index in 0..terms.len() {
+        let params = Params(2 + index * 3);
+        let any_column = MATCH_COLUMNS
+            .iter()
+            .map(|column| format!("{} LIKE ?{} ESCAPE '\\'", column, params.contains()))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        filters.push(format!("({})", any_column));
+        scores.push(format!(
+            "{} * {} + {} * {} + {} * {} + {} * {}",
+            field_score("title", params),
+            TITLE_WEIGHT,
+            field_score("artist", params),
+            ARTIST_WEIGHT,
+            field_score("album", params),
+            ALBUM_WEIGHT,
+            match_quality("lyrics", params),
+            LYRICS_WEIGHT,
+        ));
+    }
+    let sql = format!(
+        "SELECT item_id, ({score}) AS score FROM search_index
+         WHERE server_id = ?1 AND kind = '{kind}' AND {filters}
+         ORDER BY score DESC, length(title), item_id
+         LIMIT {count} OFFSET {offset}",
+        score = scores.join(" + "),
+        kind = kind.as_str(),
+        filters = filters.join(" AND "),
+    );
+
+    let mut query = sqlx::query(&sql).bind(server_id);
+    for term in terms {
+        let escaped = escape_like(term);
+        query = query
+            .bind(term.clone())
+            .bind(format!("{}%", escaped))
+            .bind(format!("%{}%", escaped));
+    }
+    let rows = query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("搜索本地缓存失败: {}", e))?;
+    Ok(rows.iter().map(|row| row.get("item_id")).collect())
+}
+
+/// 一个关键词占用的三个参数编号。
+#[derive(Clone, Copy)]
+struct Params(usize);
+
+impl Params {
+    fn exact(self) -> usize {
+        self.0
+    }
+
+    fn prefix(self) -> usize {
+        self.0 + 1
+    }
+
+    fn contains(self) -> usize {
+        self.0 + 2
+    }
+}
+
+/// 一个字段的得分：原文命中优先于拼音、首字母命中。
+fn field_score(column: &str, params: Params) -> String {
+    format!(
+        "MAX({} * 3, {} * 2, {} * 2)",
+        match_quality(column, params),
+        match_quality(&format!("{}_pinyin", column), params),
+        match_quality(&format!("{}_initials", column), params),
+    )
+}
+
+/// 完全相同 > 前缀 > 包含。
+fn match_quality(column: &str, params: Params) -> String {
+    format!(
+        "(CASE WHEN {c} = '' THEN 0 WHEN {c} = ?{} THEN 3 WHEN {c} LIKE ?{} ESCAPE '\\' THEN 2 \
+         WHEN {c} LIKE ?{} ESCAPE '\\' THEN 1 ELSE 0 END)",
+        params.exact(),
+        params.prefix(),
+        params.contains(),
+        c = column,
+    )
+}
+
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// 索引对应的同步时间，取艺人、专辑、歌曲中最早的一次；尚未同步完整时为空。
+async fn indexed_at(pool: &SqlitePool, server_id: &str) -> Result<Option<i64>, String> {
+    let row = sqlx::query(
+        "SELECT COUNT(synced_at) = 3 AS complete, MIN(synced_at) AS synced_at FROM cache_sync
+         WHERE server_id = ? AND entity IN ('artists', 'albums', 'songs')",
+    )
+    .bind(server_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("读取缓存状态失败: {}", e))?;
+    Ok(row
+        .get::<bool, _>("complete")
+        .then(|| row.get("synced_at"))
+        .flatten())
+}
+
+/// 某个服务器是否还没有建立索引。
+pub async fn is_empty(pool: &SqlitePool, server_id: &str) -> Result<bool, String> {
+    let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM search_index WHERE server_id = ?)")
+        .bind(server_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("读取搜索索引失败: {}", e))?;
+    Ok(!row.get::<bool, _>(0))
+}
+
+/// 根据缓存的艺人、专辑、歌曲与歌词重建某个服务器的搜索索引。
+pub async fn rebuild(pool: &SqlitePool, server_id: &str) -> Result<(), String> {
+    let artists = store::load_artists(pool, server_id).await?;
+    let albums = store::load_albums(pool, server_id, None, None).await?;
+    let songs = store::load_songs(pool, server_id, None, None).await?;
+    let mut lyrics = store::load_all_lyrics(pool, server_id).await?;
+
+    let error = |e: sqlx::Error| format!("写入搜索索引失败: {}", e);
+    let mut tx = pool.begin().await.map_err(error)?;
+    sqlx::query("DELETE FROM search_index WHERE server_id = ?")
+        .bind(server_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+
+    let entries = artists
+        .iter()
+        .map(|artist| Entry::new(Kind::Artist, &artist.id, &artist.name, &artist.name, ""))
+        .chain(albums.iter().map(|album| {
+            let artist = album.artist.as_deref().unwrap_or_default();
+            Entry::new(Kind::Album, &album.id, &album.name, artist, &album.name)
+        }))
+        .chain(songs.iter().map(|song| {
+            let artist = song.artist.as_deref().unwrap_or_default();
+            let album = song.album.as_deref().unwrap_or_default();
+            let mut entry = Entry::new(Kind::Song, &song.id, &song.title, artist, album);
+            if let Some(text) = lyrics.remove(&song.id) {
+                entry.lyrics = fold_lyrics(&text);
+            }
+            entry
+        }));
+    for entry in entries {
+        entry.insert(server_id, &mut tx).await.map_err(error)?;
+    }
+    tx.commit().await.map_err(error)
+}
+
+/// 更新一首歌曲在索引中的歌词。
+pub async fn index_lyrics(
+    pool: &SqlitePool,
+    server_id: &str,
+    song_id: &str,
+    lyrics: &str,
+) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE search_index SET lyrics = ? WHERE server_id = ? AND kind = 'song' AND item_id = ?",
+    )
+    .bind(fold_lyrics(lyrics))
+    .bind(server_id)
+    .bind(song_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("写入搜索索引失败: {}", e))?;
+    Ok(())
+}
+
+/// 去掉时间轴后折叠歌词，逐行折叠以免跨行拼接出不存在的词。
+fn fold_lyrics(lyrics: &str) -> String {
+    fold::strip_lrc(lyrics)
+        .lines()
+        .map(fold::fold)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 一条待写入的索引记录。
+struct Entry<'a> {
+    kind: Kind,
+    id: &'a str,
+    title: Folded,
+    artist: Folded,
+    album: Folded,
+    lyrics: String,
+}
+
+impl<'a> Entry<'a> {
+    fn new(kind: Kind, id: &'a str, title: &str, artist: &str, album: &str) -> Self {
+        Self {
+            kind,
+            id,
+            title: Folded::new(title),
+            artist: Folded::new(artist),
+            album: Folded::new(album),
+            lyrics: String::new(),
+        }
+    }
+
+    async fn insert(
+        &self,
+        server_id: &str,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO search_index
+                (server_id, kind, item_id, title, title_pinyin, title_initials,
+                 artist, artist_pinyin, artist_initials, album, album_pinyin, album_initials,
+                 lyrics)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(server_id)
+        .bind(self.kind.as_str())
+        .bind(self.id)
+        .bind(&self.title.text)
+        .bind(&self.title.pinyin)
+        .bind(&self.title.initials)
+        .bind(&self.artist.text)
+        .bind(&self.artist.pinyin)
+        .bind(&self.artist.initials)
+        .bind(&self.album.text)
+        .bind(&self.album.pinyin)
+        .bind(&self.album.initials)
+        .bind(&self.lyrics)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,730 @@
+use super::collate::{self, SectionIndex};
+use super::{CacheEntity, CachePaging, EntityStatus};
+use crate::db::now_millis;
+use crate::subsonic::{Album, Artist, Genre, Playlist, Song};
+use serde::Serialize;
+use sqlx::query::Query;
+use sqlx::sqlite::{Sqlite, SqliteArguments, SqlitePool, SqliteRow};
+use sqlx::Row;
+use std::collections::HashMap;
+
+type SqliteQuery<'q> = Query<'q, Sqlite, SqliteArguments<'q>>;
+
+/// 可写入缓存表的实体。
+pub trait Cached: Serialize + Sized {
+    const ENTITY: CacheEntity;
+    /// 整行写入的语句，前两个参数固定为 `server_id` 与 `fingerprint`。
+    const INSERT: &'static str;
+
+    /// 主键（流派为名称）。
+    fn key(&self) -> &str;
+    /// 依次绑定 `INSERT` 中剩余的参数。
+    fn bind<'q>(&'q self, query: SqliteQuery<'q>) -> SqliteQuery<'q>;
+    fn from_row(row: &SqliteRow) -> Self;
+}
+
+impl Cached for Artist {
+    const ENTITY: CacheEntity = CacheEntity::Artists;
+    const INSERT: &'static str = "INSERT OR REPLACE INTO cache_artists
+        (server_id, fingerprint, id, name, sort_name, sort_key, album_count, cover_art)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn bind<'q>(&'q self, query: SqliteQuery<'q>) -> SqliteQuery<'q> {
+        query
+            .bind(&self.id)
+            .bind(&self.name)
+            .bind(&self.sort_name)
+            .bind(collate::sort_key(&self.name, self.sort_name.as_deref()))
+            .bind(self.album_count)
+            .bind(&self.cover_art)
+    }
+
+    fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            id: row.get("id"),
+            name: row.get("name"),
+            sort_name: row.get("sort_name"),
+            album_count: row.get("album_count"),
+            cover_art: row.get("cover_art"),
+        }
+    }
+}
+
+impl Cached for Album {
+    const ENTITY: CacheEntity = CacheEntity::Albums;
+    const INSERT: &'static str = "INSERT OR REPLACE INTO cache_albums
+        (server_id, fingerprint, id, name, sort_name, sort_key, artist, artist_id, year, genre,
+         song_count, duration, cover_art, created, changed)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn bind<'q>(&'q self, query: SqliteQuery<'q>) -> SqliteQuery<'q> {
+        query
+            .bind(&self.id)
+            .bind(&self.name)
+            .bind(&self.sort_name)
+            .bind(collate::sort_key(&self.name, self.sort_name.as_deref()))
+            .bind(&self.artist)
+            .bind(&self.artist_id)
+            .bind(self.year)
+            .bind(&self.genre)
+            .bind(self.song_count)
+            .bind(self.duration)
+            .bind(&self.cover_art)
+            .bind(&self.created)
+            .bind(&self.changed)
+    }
+
+    fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            id: row.get("id"),
+            name: row.get("name"),
+            sort_name: row.get("sort_name"),
+            artist: row.get("artist"),
+            artist_id: row.get("artist_id"),
+            year: row.get("year"),
+            genre: row.get("genre"),
+            song_count: row.get("song_count"),
+            duration: row.get("duration"),
+            cover_art: row.get("cover_art"),
+            created: row.get("created"),
+            changed: row.get("changed"),
+        }
+    }
+}
+
+impl Cached for Song {
+    const ENTITY: CacheEntity = CacheEntity::Songs;
+    const INSERT: &'static str = "INSERT OR REPLACE INTO cache_songs
+        (server_id, fingerprint, id, title, sort_name, sort_key, artist, artist_id, album,
+         album_id, track, disc_number, year, genre, duration, size, suffix, bit_rate, cover_art)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn bind<'q>(&'q self, query: SqliteQuery<'q>) -> SqliteQuery<'q> {
+        query
+            .bind(&self.id)
+            .bind(&self.title)
+            .bind(&self.sort_name)
+            .bind(collate::sort_key(&self.title, self.sort_name.as_deref()))
+            .bind(&self.artist)
+            .bind(&self.artist_id)
+            .bind(&self.album)
+            .bind(&self.album_id)
+            .bind(self.track)
+            .bind(self.disc_number)
+            .bind(self.year)
+            .bind(&self.genre)
+            .bind(self.duration)
+            .bind(self.size.map(|size| size as i64))
+            .bind(&self.suffix)
+            .bind(self.bit_rate)
+            .bind(&self.cover_art)
+    }
+
+    fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            id: row.get("id"),
+            title: row.get("title"),
+            sort_name: row.get("sort_name"),
+            artist: row.get("artist"),
+            artist_id: row.get("artist_id"),
+            album: row.get("album"),
+            album_id: row.get("album_id"),
+            track: row.get("track"),
+            disc_number: row.get("disc_number"),
+            year: row.get("year"),
+            genre: row.get("genre"),
+            duration: row.get("duration"),
+            size: row.get::<Option<i64>, _>("size").map(|size| size as u64),
+            suffix: row.get("suffix"),
+            bit_rate: row.get("bit_rate"),
+            cover_art: row.get("cover_art"),
+        }
+    }
+}
+
+impl Cached for Playlist {
+    const ENTITY: CacheEntity = CacheEntity::Playlists;
+    const INSERT: &'static str = "INSERT OR REPLACE INTO cache_playlists
+        (server_id, fingerprint, id, name, comment, owner, public, song_count, duration,
+         cover_art, changed)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn bind<'q>(&'q self, query: SqliteQuery<'q>) -> SqliteQuery<'q> {
+        query
+            .bind(&self.id)
+            .bind(&self.name)
+            .bind(&self.comment)
+            .bind(&self.owner)
+            .bind(self.public)
+            .bind(self.song_count)
+            .bind(self.duration)
+            .bind(&self.cover_art)
+            .bind(&self.changed)
+    }
+
+    fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            id: row.get("id"),
+            name: row.get("name"),
+            comment: row.get("comment"),
+            owner: row.get("owner"),
+            public: row.get("public"),
+            song_count: row.get("song_count"),
+            duration: row.get("duration"),
+            cover_art: row.get("cover_art"),
+            changed: row.get("changed"),
+            entry: Vec::new(),
+        }
+    }
+}
+
+impl Cached for Genre {
+    const ENTITY: CacheEntity = CacheEntity::Genres;
+    const INSERT: &'static str = "INSERT OR REPLACE INTO cache_genres
+        (server_id, fingerprint, name, song_count, album_count)
+        VALUES (?, ?, ?, ?, ?)";
+
+    fn key(&self) -> &str {
+        &self.value
+    }
+
+    fn bind<'q>(&'q self, query: SqliteQuery<'q>) -> SqliteQuery<'q> {
+        query
+            .bind(&self.value)
+            .bind(self.song_count)
+            .bind(self.album_count)
+    }
+
+    fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            value: row.get("name"),
+            song_count: row.get("song_count"),
+            album_count: row.get("album_count"),
+        }
+    }
+}
+
+/// 以整行内容的摘要判断条目是否变化。
+pub fn fingerprint<T: Serialize>(item: &T) -> String {
+    let encoded = serde_json::to_vec(item).unwrap_or_default();
+    format!("{:x}", md5::compute(encoded))
+}
+
+/// 用服务器返回的完整列表替换缓存：只写入有变化的行并删除已不存在的行，
+/// 返回变化的行数。
+pub async fn replace_all<T: Cached>(
+    pool: &SqlitePool,
+    server_id: &str,
+    items: &[T],
+) -> Result<u64, String> {
+    replace_where(pool, server_id, None, items).await
+}
+
+/// 替换某张专辑下的全部歌曲，返回变化的行数。
+pub async fn replace_album_songs(
+    pool: &SqlitePool,
+    server_id: &str,
+    album_id: &str,
+    songs: &[Song],
+) -> Result<u64, String> {
+    replace_where(pool, server_id, Some(("album_id", album_id)), songs).await
+}
+
+/// 在 `scope`（列名与取值）限定的范围内替换缓存。
+async fn replace_where<T: Cached>(
+    pool: &SqlitePool,
+    server_id: &str,
+    scope: Option<(&str, &str)>,
+    items: &[T],
+) -> Result<u64, String> {
+    let entity = T::ENTITY;
+    let error = |e: sqlx::Error| format!("写入{}缓存失败: {}", entity.label(), e);
+    let mut tx = pool.begin().await.map_err(error)?;
+
+    let filter = scope
+        .map(|(column, _)| format!(" AND {} = ?", column))
+        .unwrap_or_default();
+    let select = format!(
+        "SELECT {key} AS key, fingerprint FROM {table} WHERE server_id = ?{filter}",
+        key = entity.key_column(),
+        table = entity.table(),
+    );
+    let mut query = sqlx::query(&select).bind(server_id);
+    if let Some((_, value)) = scope {
+        query = query.bind(value);
+    }
+    let rows = query.fetch_all(&mut *tx).await.map_err(error)?;
+    let mut existing: HashMap<String, String> = rows
+        .into_iter()
+        .map(|row| (row.get("key"), row.get("fingerprint")))
+        .collect();
+
+    let mut changed = 0;
+    for item in items {
+        let print = fingerprint(item);
+        if existing.remove(item.key()).as_deref() == Some(print.as_str()) {
+            continue;
+        }
+        item.bind(sqlx::query(T::INSERT).bind(server_id).bind(&print))
+            .execute(&mut *tx)
+            .await
+            .map_err(error)?;
+        changed += 1;
+    }
+
+    let delete = format!(
+        "DELETE FROM {} WHERE server_id = ? AND {} = ?",
+        entity.table(),
+        entity.key_column()
+    );
+    for key in existing.keys() {
+        sqlx::query(&delete)
+            .bind(server_id)
+            .bind(key)
+            .execute(&mut *tx)
+            .await
+            .map_err(error)?;
+        changed += 1;
+    }
+
+    tx.commit().await.map_err(error)?;
+    Ok(changed)
+}
+
+/// 删除已不存在的专辑下的歌曲，返回删除的行数。
+pub async fn delete_album_songs(
+    pool: &SqlitePool,
+    server_id: &str,
+    album_ids: &[&str],
+) -> Result<u64, String> {
+    let mut deleted = 0;
+    for album_id in album_ids {
+        deleted += sqlx::query("DELETE FROM cache_songs WHERE server_id = ? AND album_id = ?")
+            .bind(server_id)
+            .bind(album_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("写入歌曲缓存失败: {}", e))?
+            .rows_affected();
+    }
+    Ok(deleted)
+}
+
+/// 已缓存专辑的内容摘要，用于找出需要重新拉取歌曲的专辑。
+pub async fn album_fingerprints(
+    pool: &SqlitePool,
+    server_id: &str,
+) -> Result<HashMap<String, String>, String> {
+    let rows = sqlx::query("SELECT id, fingerprint FROM cache_albums WHERE server_id = ?")
+        .bind(server_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取专辑缓存失败: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.get("id"), row.get("fingerprint")))
+        .collect())
+}
+
+/// 某类实体的缓存条数。
+pub async fn count(pool: &SqlitePool, server_id: &str, entity: CacheEntity) -> Result<u64, String> {
+    let count: i64 = sqlx::query(&format!(
+        "SELECT COUNT(*) FROM {} WHERE server_id = ?",
+        entity.table()
+    ))
+    .bind(server_id)
+    .fetch_one(pool)
+    .await
+    .and_then(|row| row.try_get(0))
+    .map_err(|e| format!("读取{}缓存失败: {}", entity.label(), e))?;
+    Ok(count.max(0) as u64)
+}
+
+/// 上次完整同步时服务器报告的曲库修改时间（记录在艺人的同步状态上）。
+pub async fn last_modified(pool: &SqlitePool, server_id: &str) -> Result<Option<i64>, String> {
+    let row = sqlx::query(
+        "SELECT last_modified FROM cache_sync WHERE server_id = ? AND entity = 'artists'",
+    )
+    .bind(server_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("读取同步状态失败: {}", e))?;
+    Ok(row.and_then(|row| row.get("last_modified")))
+}
+
+/// 保存曲库修改时间，供下次同步时比较。
+pub async fn set_last_modified(
+    pool: &SqlitePool,
+    server_id: &str,
+    last_modified: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE cache_sync SET last_modified = ? WHERE server_id = ? AND entity = 'artists'",
+    )
+    .bind(last_modified)
+    .bind(server_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("记录同步状态失败: {}", e))?;
+    Ok(())
+}
+
+/// 记录一次成功的同步：内容有变化时版本号加一，并清除上次的错误。
+pub async fn record_sync(
+    pool: &SqlitePool,
+    server_id: &str,
+    entity: CacheEntity,
+    changed: bool,
+) -> Result<(), String> {
+    sqlx::query(&format!(
+        "INSERT INTO cache_sync (server_id, entity, version, item_count, synced_at, error)
+         VALUES (?1, ?2, ?3, (SELECT COUNT(*) FROM {table} WHERE server_id = ?1), ?4, NULL)
+         ON CONFLICT(server_id, entity) DO UPDATE SET
+            version = cache_sync.version + excluded.version,
+            item_count = excluded.item_count,
+            synced_at = excluded.synced_at,
+            error = NULL",
+        table = entity.table(),
+    ))
+    .bind(server_id)
+    .bind(entity.as_str())
+    .bind(changed as i64)
+    .bind(now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("记录同步状态失败: {}", e))?;
+    Ok(())
+}
+
+/// 记录同步失败的原因，已缓存的数据保持不变。
+pub async fn record_error(
+    pool: &SqlitePool,
+    server_id: &str,
+    entity: CacheEntity,
+    error: &str,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO cache_sync (server_id, entity, error) VALUES (?, ?, ?)
+         ON CONFLICT(server_id, entity) DO UPDATE SET error = excluded.error",
+    )
+    .bind(server_id)
+    .bind(entity.as_str())
+    .bind(error)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("记录同步状态失败: {}", e))?;
+    Ok(())
+}
+
+/// 读取各类实体的缓存条数、版本与同步时间。
+pub async fn load_status(pool: &SqlitePool, server_id: &str) -> Result<Vec<EntityStatus>, String> {
+    let mut statuses = Vec::with_capacity(CacheEntity::ALL.len());
+    for entity in CacheEntity::ALL {
+        let row = sqlx::query(&format!(
+            "SELECT (SELECT COUNT(*) FROM {table} WHERE server_id = ?1) AS count,
+                    s.version, s.synced_at, s.error
+             FROM (SELECT 1) LEFT JOIN cache_sync s ON s.server_id = ?1 AND s.entity = ?2",
+            table = entity.table(),
+        ))
+        .bind(server_id)
+        .bind(entity.as_str())
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("读取缓存状态失败: {}", e))?;
+        statuses.push(EntityStatus {
+            entity,
+            count: row.get::<i64, _>("count").max(0) as u64,
+            version: row.get::<Option<i64>, _>("version").unwrap_or_default(),
+            synced_at: row.get("synced_at"),
+            error: row.get("error"),
+        });
+    }
+    Ok(statuses)
+}
+
+/// 缓存表占用的磁盘空间；SQLite 未启用 `dbstat` 时返回 `None`。
+pub async fn cache_size(pool: &SqlitePool) -> Option<u64> {
+    sqlx::query(
+        "SELECT COALESCE(SUM(pgsize), 0) FROM dbstat WHERE name LIKE 'cache\\_%' ESCAPE '\\'",
+    )
+    .fetch_one(pool)
+    .await
+    .ok()
+    .map(|row| row.get::<i64, _>(0).max(0) as u64)
+}
+
+/// 读取缓存的艺人，按排序键排列。
+pub async fn load_artists(pool: &SqlitePool, server_id: &str) -> Result<Vec<Artist>, String> {
+    load(
+        pool,
+        "SELECT * FROM cache_artists WHERE server_id = ?
+         ORDER BY COALESCE(sort_key, lower(name)), name COLLATE NOCASE",
+        &[Some(server_id)],
+        None,
+    )
+    .await
+}
+
+/// 读取缓存的专辑，可按艺人过滤。
+pub async fn load_albums(
+    pool: &SqlitePool,
+    server_id: &str,
+    artist_id: Option<&str>,
+    paging: Option<&CachePaging>,
+) -> Result<Vec<Album>, String> {
+    load(
+        pool,
+        "SELECT * FROM cache_albums WHERE server_id = ? AND (?2 IS NULL OR artist_id = ?2)
+         ORDER BY COALESCE(sort_key, lower(name)), name COLLATE NOCASE",
+        &[Some(server_id), artist_id],
+        paging,
+    )
+    .await
+}
+
+/// 读取缓存的歌曲；按专辑过滤时按碟号、音轨排列。
+pub async fn load_songs(
+    pool: &SqlitePool,
+    server_id: &str,
+    album_id: Option<&str>,
+    paging: Option<&CachePaging>,
+) -> Result<Vec<Song>, String> {
+    load(
+        pool,
+        "SELECT * FROM cache_songs WHERE server_id = ? AND (?2 IS NULL OR album_id = ?2)
+         ORDER BY CASE WHEN ?2 IS NULL THEN COALESCE(sort_key, lower(title)) END,
+                  disc_number, track, title COLLATE NOCASE",
+        &[Some(server_id), album_id],
+        paging,
+    )
+    .await
+}
+
+/// 艺人列表的 A–Z 分组索引，与 [`load_artists`] 的顺序一致。
+pub async fn artist_sections(
+    pool: &SqlitePool,
+    server_id: &str,
+) -> Result<Vec<SectionIndex>, String> {
+    sections(
+        pool,
+        "SELECT COALESCE(sort_key, lower(name)) AS sort_key FROM cache_artists
+         WHERE server_id = ? ORDER BY 1",
+        &[Some(server_id)],
+    )
+    .await
+}
+
+/// 专辑列表的 A–Z 分组索引，与 [`load_albums`] 的顺序一致。
+pub async fn album_sections(
+    pool: &SqlitePool,
+    server_id: &str,
+    artist_id: Option<&str>,
+) -> Result<Vec<SectionIndex>, String> {
+    sections(
+        pool,
+        "SELECT COALESCE(sort_key, lower(name)) AS sort_key FROM cache_albums
+         WHERE server_id = ? AND (?2 IS NULL OR artist_id = ?2) ORDER BY 1",
+        &[Some(server_id), artist_id],
+    )
+    .await
+}
+
+/// 按排好序的排序键统计每个分组的起始位置与条数。
+async fn sections(
+    pool: &SqlitePool,
+    sql: &str,
+    params: &[Option<&str>],
+) -> Result<Vec<SectionIndex>, String> {
+    let query = params
+        .iter()
+        .fold(sqlx::query(sql), |query, param| query.bind(*param));
+    let rows = query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取分组索引失败: {}", e))?;
+
+    let mut sections: Vec<SectionIndex> = Vec::new();
+    for (offset, row) in rows.iter().enumerate() {
+        let letter = collate::section(row.get("sort_key")).to_string();
+        match sections.last_mut() {
+            Some(section) if section.letter == letter => section.count += 1,
+            _ => sections.push(SectionIndex {
+                letter,
+                offset: offset as u32,
+                count: 1,
+            }),
+        }
+    }
+    Ok(sections)
+}
+
+/// 读取缓存的歌单（不含曲目）。
+pub async fn load_playlists(pool: &SqlitePool, server_id: &str) -> Result<Vec<Playlist>, String> {
+    load(
+        pool,
+        "SELECT * FROM cache_playlists WHERE server_id = ? ORDER BY name COLLATE NOCASE",
+        &[Some(server_id)],
+        None,
+    )
+    .await
+}
+
+/// 读取缓存的流派。
+pub async fn load_genres(pool: &SqlitePool, server_id: &str) -> Result<Vec<Genre>, String> {
+    load(
+        pool,
+        "SELECT * FROM cache_genres WHERE server_id = ? ORDER BY name COLLATE NOCASE",
+        &[Some(server_id)],
+        None,
+    )
+    .await
+}
+
+/// 按 ID 读取缓存条目，保持 `ids` 的顺序，缓存中不存在的 ID 被忽略。
+pub async fn load_by_ids<T: Cached>(
+    pool: &SqlitePool,
+    server_id: &str,
+    ids: &[String],
+) -> Result<Vec<T>, String> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let entity = T::ENTITY;
+    let encoded = serde_json::to_string(ids).unwrap_or_default();
+    let sql = format!(
+        "SELECT * FROM {table} WHERE server_id = ? AND {key} IN (SELECT value FROM json_each(?))",
+        table = entity.table(),
+        key = entity.key_column(),
+    );
+    let items: Vec<T> = load(pool, &sql, &[Some(server_id), Some(&encoded)], None).await?;
+    let mut by_key: HashMap<String, T> = items
+        .into_iter()
+        .map(|item| (item.key().to_string(), item))
+        .collect();
+    Ok(ids.iter().filter_map(|id| by_key.remove(id)).collect())
+}
+
+/// 读取已缓存的歌词。
+pub async fn load_lyrics(
+    pool: &SqlitePool,
+    server_id: &str,
+    song_id: &str,
+) -> Result<Option<String>, String> {
+    let row = sqlx::query("SELECT lyrics FROM cache_lyrics WHERE server_id = ? AND song_id = ?")
+        .bind(server_id)
+        .bind(song_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("读取歌词缓存失败: {}", e))?;
+    Ok(row.map(|row| row.get("lyrics")))
+}
+
+/// 读取某个服务器的全部歌词，以歌曲 ID 为键。
+pub async fn load_all_lyrics(
+    pool: &SqlitePool,
+    server_id: &str,
+) -> Result<HashMap<String, String>, String> {
+    let rows = sqlx::query("SELECT song_id, lyrics FROM cache_lyrics WHERE server_id = ?")
+        .bind(server_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取歌词缓存失败: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.get("song_id"), row.get("lyrics")))
+        .collect())
+}
+
+/// 保存或覆盖歌词。
+pub async fn save_lyrics(
+    pool: &SqlitePool,
+    server_id: &str,
+    song_id: &str,
+    lyrics: &str,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO cache_lyrics (server_id, song_id, lyrics, fetched_at)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(server_id)
+    .bind(song_id)
+    .bind(lyrics)
+    .bind(now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("写入歌词缓存失败: {}", e))?;
+    Ok(())
+}
+
+/// 依次绑定参数执行读取语句，末尾追加分页。
+async fn load<T: Cached>(
+    pool: &SqlitePool,
+    sql: &str,
+    params: &[Option<&str>],
+    paging: Option<&CachePaging>,
+) -> Result<Vec<T>, String> {
+    let sql = match paging {
+        Some(paging) => format!("{} LIMIT {} OFFSET {}", sql, paging.limit, paging.offset),
+        None => sql.to_string(),
+    };
+    let query = params
+        .iter()
+        .fold(sqlx::query(&sql), |query, param| query.bind(*param));
+    let rows = query
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取{}缓存失败: {}", T::ENTITY.label(), e))?;
+    Ok(rows.iter().map(T::from_row).collect())
+}
+
+/// 为升级前缓存的行补全排序键，只处理排序键为空的行，不影响同步摘要与修改时间。
+pub async fn backfill_sort_keys(pool: &SqlitePool) -> Result<(), String> {
+    let error = |e: sqlx::Error| format!("补全排序键失败: {}", e);
+    for (table, name) in [
+        ("cache_artists", "name"),
+        ("cache_albums", "name"),
+        ("cache_songs", "title"),
+    ] {
+        let rows = sqlx::query(&format!(
+            "SELECT server_id, id, {name} AS name, sort_name FROM {table} WHERE sort_key IS NULL"
+        ))
+        .fetch_all(pool)
+        .await
+        .map_err(error)?;
+        if rows.is_empty() {
+            continue;
+        }
+
+        let update = format!("UPDATE {table} SET sort_key = ? WHERE server_id = ? AND id = ?");
+        let mut tx = pool.begin().await.map_err(error)?;
+        for row in &rows {
+            let name: String = row.get("name");
+            let sort_name: Option<String> = row.get("sort_name");
+            sqlx::query(&update)
+                .bind(collate::sort_key(&name, sort_name.as_deref()))
+                .bind(row.get::<String, _>("server_id"))
+                .bind(row.get::<String, _>("id"))
+                .execute(&mut *tx)
+                .await
+                .map_err(error)?;
+        }
+        tx.commit().await.map_err(error)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use super::store::{self, Cached};
+use super::CacheEntity;
+use crate::subsonic::{Album, SearchPaging, Song, SubsonicClient};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// 同步进度事件名，载荷为 [`SyncProgress`]。
+pub const PROGRESS_EVENT: &str = "catalog://progress";
+
+/// 分页拉取时每页的条数（getAlbumList2 与 search3 的上限均为 500）。
+const PAGE_SIZE: u32 = 500;
+/// 同时进行的请求数，避免压垮小型服务器。
+const PARALLELISM: usize = 4;
+/// 两次进度推送之间的最小间隔。
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 推送给前端的同步进度。
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncProgress {
+    pub server_id: String,
+    pub entity: CacheEntity,
+    /// 已处理的条数（增量同步歌曲时为已处理的专辑数）。
+    pub done: u64,
+    /// 总数未知时为空。
+    pub total: Option<u64>,
+}
+
+/// 同步一个服务器的元数据。
+///
+/// 先用 `getIndexes?ifModifiedSince` 判断曲库自上次同步后是否有变化，没有变化时只刷新歌单；
+/// 有变化时并行分页拉取专辑，仅对内容（含 `changed` 时间戳）变化的专辑重新拉取歌曲。
+/// 首次同步或 `full` 为真时以空关键字分页调用 search3 拉取全部歌曲。
+/// 某类实体失败时继续同步其余类型，最后返回第一个错误。
+pub async fn sync_all(
+    app: &AppHandle,
+    client: &SubsonicClient,
+    pool: &SqlitePool,
+    server_id: &str,
+    full: bool,
+) -> Result<(), String> {
+    let progress = Progress::new(app, server_id);
+    let since = if full {
+        None
+    } else {
+        store::last_modified(pool, server_id).await?
+    };
+    // 服务器不支持时视为已变化
+    let modified = client.get_indexes_last_modified(since).await.ok();
+    let unchanged = matches!((since, modified), (Some(since), Some(modified)) if modified <= since);
+
+    let mut results = Vec::new();
+    if unchanged {
+        for entity in [
+            CacheEntity::Artists,
+            CacheEntity::Albums,
+            CacheEntity::Songs,
+            CacheEntity::Genres,
+        ] {
+            results.push(store::record_sync(pool, server_id, entity, false).await);
+        }
+    } else {
+        results.push(save(pool, server_id, client.get_artists().await).await);
+        results.push(sync_albums(client, pool, server_id, full, &progress).await);
+        results.push(save(pool, server_id, client.get_genres().await).await);
+        if let (true, Some(modified)) = (results.iter().all(Result::is_ok), modified) {
+            results.push(store::set_last_modified(pool, server_id, modified).await);
+        }
+    }
+    results.push(save(pool, server_id, client.get_playlists().await).await);
+    results.into_iter().collect()
+}
+
+/// 写入一类实体的拉取结果，失败时记录错误并保留旧缓存。
+async fn save<T: Cached>(
+    pool: &SqlitePool,
+    server_id: &str,
+    fetched: Result<Vec<T>, String>,
+) -> Result<(), String> {
+    let result = match fetched {
+        Ok(items) => store::replace_all(pool, server_id, &items).await,
+        Err(e) => Err(e),
+    };
+    record(pool, server_id, T::ENTITY, result).await
+}
+
+/// 按结果记录同步状态。
+async fn record(
+    pool: &SqlitePool,
+    server_id: &str,
+    entity: CacheEntity,
+    result: Result<u64, String>,
+) -> Result<(), String> {
+    match result {
+        Ok(changed) => store::record_sync(pool, server_id, entity, changed > 0).await,
+        Err(e) => {
+            store::record_error(pool, server_id, entity, &e).await?;
+            Err(e)
+        }
+    }
+}
+
+/// 同步专辑及其歌曲；歌曲写入成功后才更新专辑，保证失败的专辑下次会被重新拉取。
+async fn sync_albums(
+    client: &SubsonicClient,
+    pool: &SqlitePool,
+    server_id: &str,
+    full: bool,
+    progress: &Progress<'_>,
+) -> Result<(), String> {
+    let albums = match fetch_albums(client, progress).await {
+        Ok(albums) => albums,
+        Err(e) => {
+            store::record_error(pool, server_id, CacheEntity::Songs, &e).await?;
+            return record(pool, server_id, CacheEntity::Albums, Err(e)).await;
+        }
+    };
+
+    let songs = if full || store::count(pool, server_id, CacheEntity::Songs).await? == 0 {
+        match fetch_songs(client, progress).await {
+            Ok(songs) => store::replace_all(pool, server_id, &songs).await,
+            Err(e) => Err(e),
+        }
+    } else {
+        sync_changed_albums(client, pool, server_id, &albums, progress).await
+    };
+    let songs_ok = songs.is_ok();
+    let songs = record(pool, server_id, CacheEntity::Songs, songs).await;
+
+    if songs_ok {
+        let saved = store::replace_all(pool, server_id, &albums).await;
+        record(pool, server_id, CacheEntity::Albums, saved).await?;
+    }
+    songs
+}
+
+/// 只对新增或内容变化的专辑并行拉取歌曲，并清理已删除专辑的歌曲，返回变化的行数。
+async fn sync_changed_albums(
+    client: &SubsonicClient,
+    pool: &SqlitePool,
+    server_id: &str,
+    albums: &[Album],
+    progress: &Progress<'_>,
+) -> Result<u64, String> {
+    let previous = store::album_fingerprints(pool, server_id).await?;
+    let stale: Vec<&Album> = albums
+        .iter()
+        .filter(|album| previous.get(&album.id) != Some(&store::fingerprint(*album)))
+        .collect();
+    let current: HashSet<&str> = albums.iter().map(|album| album.id.as_str()).collect();
+    let removed: Vec<&str> = previous
+        .keys()
+        .map(String::as_str)
+        .filter(|id| !current.contains(id))
+        .collect();
+
+    let total = stale.len() as u64;
+    let mut changed = store::delete_album_songs(pool, server_id, &removed).await?;
+    let mut fetched = stream::iter(stale)
+        .map(|album| async move {
+            let songs = client.get_album_songs(&album.id).await?;
+            Ok::<_, String>((album, songs))
+        })
+        .buffer_unordered(PARALLELISM);
+    let mut done = 0;
+    while let Some(result) = fetched.next().await {
+        let (album, songs) = result?;
+        changed += store::replace_album_songs(pool, server_id, &album.id, &songs).await?;
+        done += 1;
+        progress.report(CacheEntity::Songs, done, Some(total));
+    }
+    progress.finish(CacheEntity::Songs, done, Some(total));
+    Ok(changed)
+}
+
+/// 按名称并行分页拉取全部专辑。
+async fn fetch_albums(
+    client: &SubsonicClient,
+    progress: &Progress<'_>,
+) -> Result<Vec<Album>, String> {
+    fetch_pages(CacheEntity::Albums, progress, |offset| {
+        client.get_album_list2("alphabeticalByName", PAGE_SIZE, offset)
+    })
+    .await
+}
+
+/// 以空关键字并行分页调用 search3 拉取全部歌曲。
+async fn fetch_songs(
+    client: &SubsonicClient,
+    progress: &Progress<'_>,
+) -> Result<Vec<Song>, String> {
+    fetch_pages(CacheEntity::Songs, progress, |offset| async move {
+        let paging = SearchPaging {
+            artist_count: 0,
+            album_count: 0,
+            song_count: PAGE_SIZE,
+            song_offset: offset,
+            ..SearchPaging::default()
+        };
+        Ok(client.search3("", &paging).await?.song)
+    })
+    .await
+}
+
+/// 同时请求 [`PARALLELISM`] 页，按顺序收集，遇到不满一页时结束。
+async fn fetch_pages<T, F, Fut>(
+    entity: CacheEntity,
+    progress: &Progress<'_>,
+    fetch: F,
+) -> Result<Vec<T>, String>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, String>>,
+{
+    let mut pages = stream::iter(0u32..)
+        .map(|page| fetch(page * PAGE_SIZE))
+        .buffered(PARALLELISM);
+    let mut items = Vec::new();
+    while let Some(page) = pages.next().await {
+        let page = page?;
+        let last = page.len() < PAGE_SIZE as usize;
+        items.extend(page);
+        progress.report(entity, items.len() as u64, None);
+        if last {
+            break;
+        }
+    }
+    progress.finish(entity, items.len() as u64, Some(items.len() as u64));
+    Ok(items)
+}
+
+/// 按固定间隔推送同步进度。
+struct Progress<'a> {
+    app: &'a AppHandle,
+    server_id: &'a str,
+    last: Mutex<Option<Instant>>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(app: &'a AppHandle, server_id: &'a str) -> Self {
+        Self {
+            app,
+            server_id,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// 汇报进度；未到推送间隔时忽略。
+    fn report(&self, entity: CacheEntity, done: u64, total: Option<u64>) {
+        {
+            let mut last = self.last.lock().expect("进度锁定失败");
+            let now = Instant::now();
+            if last.is_some_and(|at| now - at < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last = Some(now);
+        }
+        self.emit(entity, done, total);
+    }
+
+    /// 某个阶段结束时总会推送一次。
+    fn finish(&self, entity: CacheEntity, done: u64, total: Option<u64>) {
+        self.emit(entity, done, total);
+    }
+
+    fn emit(&self, entity: CacheEntity, done: u64, total: Option<u64>) {
+        let _ = self.app.emit(
+            PROGRESS_EVENT,
+            SyncProgress {
+                server_id: self.server_id.to_string(),
+                entity,
+                done,
+                total,
+            },
+        );
+    }
+}
@@ -1,29 +1,88 @@
-use crate::app_state::{AppState, QueueKind, QueueTask, ServerConfig};
+use crate::app_state::{
+    AppState, QueueKind, QueueSource, QueueStatus, QueueTask, ServerConfig, ServerKind,
+};
+use crate::catalog::{self, CachePaging, CacheStatus, SectionIndex};
+use crate::credentials::{Credential, CredentialKind};
+use crate::db::Database;
+use crate::library::{self, LibraryChanges, LibraryTrack};
+use crate::media::cache::{self, CacheUsage, EvictReport};
+use crate::media::{self, MediaRoots};
+use crate::mysql::{self, MysqlTarget};
+use crate::openlist::{self, ListOptions};
+use crate::probe::{self, ServerError};
+use crate::servers;
+use crate::staging::StagedFile;
+use crate::subsonic::{
+    self, PlaylistUpdate, SearchPaging, StreamOptions, SubsonicAuth, SubsonicClient,
+};
+use crate::tags::anchor::{AnchorInfo, TagOptions};
+use crate::tags::{self, AudioMetadata, MetadataResult};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use tauri::State;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
 use uuid::Uuid;
 
-/// 新增或更新服务器配置。
+/// 新增或更新（传入 `id` 时）服务器配置。保存前会探测服务器类型与版本并校验
+/// `key_ref` 对应的凭据，同类中的第一个服务器自动成为默认项。
 #[tauri::command]
-pub fn add_server(
+#[allow(clippy::too_many_arguments)]
+pub async fn add_server(
     app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    id: Option<String>,
+    kind: Option<ServerKind>,
     name: String,
     base_url: String,
+    username: Option<String>,
     key_ref: String,
-) -> Vec<ServerConfig> {
-    let mut servers = app_state.servers.lock().expect("服务器配置锁定失败");
-    let id = Uuid::new_v4().to_string();
-    servers.push(ServerConfig {
-        id,
-        name,
-        base_url,
-        key_ref,
-    });
-    servers.clone()
+) -> Result<Vec<ServerConfig>, ServerError> {
+    let username = username.unwrap_or_default();
+    let credential = app_state
+        .credentials
+        .load(&key_ref)?
+        .ok_or_else(|| ServerError::Unauthorized("请先保存该服务器的密码或 API Key".to_string()))?;
+    let probed =
+        probe::probe_server(&app_state.http, kind, &base_url, &username, credential).await?;
+    let name = match name.trim() {
+        "" => probed.base_url.host_str().unwrap_or_default().to_string(),
+        trimmed => trimmed.to_string(),
+    };
+
+    let snapshot = {
+        let mut servers = app_state.servers.lock().expect("服务器配置锁定失败");
+        let config = ServerConfig {
+            id: id.clone().unwrap_or_else(|| Uuid::new_v4().to_string()),
+            kind: probed.kind,
+            name,
+            base_url: probed.base_url.to_string(),
+            username,
+            key_ref,
+            is_default: false,
+            info: probed.info,
+        };
+        match id {
+            Some(id) => {
+                let existing = servers
+                    .iter_mut()
+                    .find(|server| server.id == id)
+                    .ok_or_else(|| ServerError::NotFound(format!("未找到服务器配置: {}", id)))?;
+                // 类型不变时保留默认标记
+                let is_default = existing.is_default && existing.kind == config.kind;
+                *existing = ServerConfig {
+                    is_default,
+                    ..config
+                };
+            }
+            None => servers.push(config),
+        }
+        servers::ensure_default(&mut servers);
+        servers.clone()
+    };
+    servers::save_servers(database.pool(), &snapshot).await?;
+    Ok(snapshot)
 }
 
-/// 列出当前已注册的服务器。
+/// 按添加顺序列出已注册的服务器。
 #[tauri::command]
 pub fn list_servers(app_state: State<'_, AppState>) -> Vec<ServerConfig> {
     app_state
@@ -33,95 +92,478 @@ pub fn list_servers(app_state: State<'_, AppState>) -> Vec<ServerConfig> {
         .clone()
 }
 
-/// 移除服务器。
+/// 移除服务器及其不再被引用的凭据；移除的是默认项时由同类的下一个服务器接替。
+#[tauri::command]
+pub async fn remove_server(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    id: String,
+) -> Result<Vec<ServerConfig>, String> {
+    let (snapshot, orphaned) = {
+        let mut servers = app_state.servers.lock().expect("服务器配置锁定失败");
+        let removed = servers.iter().position(|server| server.id == id);
+        let removed = removed.map(|index| servers.remove(index));
+        servers::ensure_default(&mut servers);
+        // 没有其他服务器引用时一并清理凭据
+        let orphaned = removed
+            .map(|server| server.key_ref)
+            .filter(|key_ref| servers.iter().all(|server| &server.key_ref != key_ref));
+        (servers.clone(), orphaned)
+    };
+    servers::save_servers(database.pool(), &snapshot).await?;
+    if let Some(key_ref) = orphaned {
+        app_state.credentials.remove(&key_ref)?;
+    }
+    Ok(snapshot)
+}
+
+/// 将服务器设为同类中的默认项。
 #[tauri::command]
-pub fn remove_server(app_state: State<'_, AppState>, id: String) -> Vec<ServerConfig> {
-    let mut servers = app_state.servers.lock().expect("服务器配置锁定失败");
-    servers.retain(|server| server.id != id);
-    servers.clone()
+pub async fn set_default_server(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    id: String,
+) -> Result<Vec<ServerConfig>, String> {
+    let snapshot = {
+        let mut servers = app_state.servers.lock().expect("服务器配置锁定失败");
+        servers::set_default(&mut servers, &id)?;
+        servers.clone()
+    };
+    servers::save_servers(database.pool(), &snapshot).await?;
+    Ok(snapshot)
 }
 
-/// Navidrome 搜索占位实现，后续接入真实 HTTP 请求与缓存。
+/// 保存 `key_ref` 对应的凭据，之后的请求均在 Rust 侧完成鉴权。
 #[tauri::command]
-pub fn navidrome_search(keyword: String) -> Vec<TrackSummary> {
-    vec![TrackSummary {
-        id: Uuid::new_v4().to_string(),
-        title: format!("示例歌曲：{}", keyword),
-        artist: "示例歌手".to_string(),
-        album: "示例专辑".to_string(),
-    }]
+pub fn set_credential(
+    app_state: State<'_, AppState>,
+    key_ref: String,
+    kind: CredentialKind,
+    secret: String,
+) -> Result<CredentialInfo, String> {
+    app_state
+        .credentials
+        .store(&key_ref, Credential { kind, secret })?;
+    credential_info(app_state, key_ref)
 }
 
-/// Navidrome 歌单占位实现。
+/// 查询凭据是否已保存及其类型，不返回明文；凭据存储不可用时返回原因。
 #[tauri::command]
-pub fn navidrome_playlist(name: String) -> PlaylistSummary {
-    PlaylistSummary {
-        id: Uuid::new_v4().to_string(),
-        name: format!("{}（占位歌单）", name),
-        tracks: vec![TrackSummary {
-            id: Uuid::new_v4().to_string(),
-            title: "占位曲目".to_string(),
-            artist: "示例歌手".to_string(),
-            album: "示例专辑".to_string(),
-        }],
+pub fn credential_info(
+    app_state: State<'_, AppState>,
+    key_ref: String,
+) -> Result<CredentialInfo, String> {
+    let backend_error = app_state.credentials.backend_error().map(str::to_string);
+    let credential = match backend_error {
+        Some(_) => None,
+        None => app_state.credentials.load(&key_ref)?,
+    };
+    Ok(CredentialInfo {
+        key_ref,
+        stored: credential.is_some(),
+        kind: credential.map(|credential| credential.kind),
+        backend: app_state.credentials.backend_name().to_string(),
+        backend_error,
+    })
+}
+
+/// 轮换已有凭据；未指定类型时沿用原类型。
+#[tauri::command]
+pub fn rotate_credential(
+    app_state: State<'_, AppState>,
+    key_ref: String,
+    kind: Option<CredentialKind>,
+    secret: String,
+) -> Result<CredentialInfo, String> {
+    let current = app_state.credentials.resolve(&key_ref)?;
+    let kind = kind.unwrap_or(current.kind);
+    app_state
+        .credentials
+        .store(&key_ref, Credential { kind, secret })?;
+    credential_info(app_state, key_ref)
+}
+
+/// 删除凭据，返回删除前是否存在。
+#[tauri::command]
+pub fn delete_credential(app_state: State<'_, AppState>, key_ref: String) -> Result<bool, String> {
+    app_state.credentials.remove(&key_ref)
+}
+
+/// 保存 MySQL 密码到凭据存储，配置文件中不再写入密码。
+#[tauri::command]
+pub fn mysql_store_password(
+    app_state: State<'_, AppState>,
+    config: MysqlTarget,
+    password: String,
+) -> Result<(), String> {
+    let credential = Credential {
+        kind: CredentialKind::Password,
+        secret: password,
+    };
+    app_state.credentials.store(&config.key_ref(), credential)
+}
+
+/// 测试 MySQL 连接；未传入密码时使用凭据存储中的密码。
+#[tauri::command]
+pub async fn mysql_test(
+    app_state: State<'_, AppState>,
+    config: MysqlTarget,
+    password: Option<String>,
+) -> Result<(), String> {
+    let password = match password {
+        Some(password) => password,
+        None => mysql_password(&app_state, &config)?,
+    };
+    config.connect(&password).await?.close().await;
+    Ok(())
+}
+
+/// 用凭据存储中的密码建立 MySQL 连接并交给 SQL 插件，返回供 `Database.get` 使用的连接名。
+#[tauri::command]
+pub async fn mysql_connect(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    config: MysqlTarget,
+) -> Result<String, String> {
+    let password = mysql_password(&app_state, &config)?;
+    let pool = config.connect(&password).await?;
+    let name = config.db_name();
+    mysql::register(&app, name.clone(), pool).await;
+    Ok(name)
+}
+
+/// 读取 MySQL 密码，未保存时视为空密码。
+fn mysql_password(app_state: &AppState, config: &MysqlTarget) -> Result<String, String> {
+    let credential = app_state.credentials.load(&config.key_ref())?;
+    Ok(credential
+        .map(|credential| credential.secret)
+        .unwrap_or_default())
+}
+
+/// 按 id 查找服务器配置。
+pub(crate) fn find_server(app_state: &AppState, server_id: &str) -> Result<ServerConfig, String> {
+    app_state
+        .servers
+        .lock()
+        .expect("服务器配置锁定失败")
+        .iter()
+        .find(|server| server.id == server_id)
+        .cloned()
+        .ok_or_else(|| format!("未找到服务器配置: {}", server_id))
+}
+
+/// 根据服务器配置与 `key_ref` 凭据构造 Subsonic 客户端。
+pub(crate) fn subsonic_client(
+    app_state: &AppState,
+    server_id: &str,
+) -> Result<SubsonicClient, String> {
+    let server = find_server(app_state, server_id)?;
+    let credential = app_state.credentials.resolve(&server.key_ref)?;
+    let auth = SubsonicAuth::from_credential(&server.username, credential);
+    SubsonicClient::new(app_state.http.clone(), &server.base_url, auth)
+}
+
+/// Navidrome 搜索：在线调用 search3，按艺人、专辑、歌曲分页返回；
+/// 连不上服务器（连接失败或超时）且已建立离线索引时改为搜索本地缓存，并在结果中注明索引的同步时间。
+#[tauri::command]
+pub async fn navidrome_search(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    server_id: String,
+    keyword: String,
+    paging: Option<SearchPaging>,
+) -> Result<SearchResults, String> {
+    let paging = paging.unwrap_or_default();
+    let client = subsonic_client(&app_state, &server_id)?;
+    let result = match client.search3(keyword.trim(), &paging).await {
+        Ok(result) => result,
+        // 只有连不上服务器时才改用离线索引，鉴权失败与接口错误直接返回
+        Err(e) if e.unreachable && catalog::search_ready(database.pool(), &server_id).await? => {
+            let matches = catalog::search(database.pool(), &server_id, &keyword, &paging).await?;
+            return Ok(SearchResults::from(matches));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(SearchResults {
+        artists: result.artist.into_iter().map(ArtistSummary::from).collect(),
+        albums: result.album.into_iter().map(AlbumSummary::from).collect(),
+        songs: result.song.into_iter().map(TrackSummary::from).collect(),
+        indexed_at: None,
+    })
+}
+
+/// 获取服务器上的歌单列表（不含曲目）。
+#[tauri::command]
+pub async fn navidrome_playlists(
+    app_state: State<'_, AppState>,
+    server_id: String,
+) -> Result<Vec<PlaylistSummary>, String> {
+    let client = subsonic_client(&app_state, &server_id)?;
+    let playlists = client.get_playlists().await?;
+    Ok(playlists.into_iter().map(PlaylistSummary::from).collect())
+}
+
+/// 获取歌单详情及曲目。
+#[tauri::command]
+pub async fn navidrome_playlist(
+    app_state: State<'_, AppState>,
+    server_id: String,
+    id: String,
+) -> Result<PlaylistSummary, String> {
+    let client = subsonic_client(&app_state, &server_id)?;
+    Ok(client.get_playlist(&id).await?.into())
+}
+
+/// 在服务器上创建歌单，可附带初始曲目。
+#[tauri::command]
+pub async fn navidrome_playlist_create(
+    app_state: State<'_, AppState>,
+    server_id: String,
+    name: String,
+    song_ids: Option<Vec<String>>,
+) -> Result<PlaylistSummary, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("歌单名称不能为空".to_string());
     }
+
+    let client = subsonic_client(&app_state, &server_id)?;
+    let playlist = client
+        .create_playlist(name, &song_ids.unwrap_or_default())
+        .await?;
+    Ok(playlist.into())
 }
 
-/// 生成占位播放地址。
+/// 更新歌单（改名、公开状态、按 id 追加或按下标移除曲目），返回更新后的详情。
 #[tauri::command]
-pub fn navidrome_stream_url(base_url: String, track_id: String) -> String {
-    format!(
-        "{}/rest/stream.view?id={}&token=stub",
-        base_url.trim_end_matches('/'),
-        track_id
-    )
+pub async fn navidrome_playlist_update(
+    app_state: State<'_, AppState>,
+    server_id: String,
+    id: String,
+    update: PlaylistUpdate,
+) -> Result<PlaylistSummary, String> {
+    let client = subsonic_client(&app_state, &server_id)?;
+    client.update_playlist(&id, &update).await?;
+    Ok(client.get_playlist(&id).await?.into())
 }
 
-/// OpenList 目录浏览占位实现。
+/// 删除服务器上的歌单。
 #[tauri::command]
-pub fn openlist_browse(path: Option<String>) -> Vec<FileEntry> {
-    vec![
-        FileEntry {
-            name: path.unwrap_or_else(|| "/".to_string()),
-            kind: "directory".to_string(),
-            size: 0,
-        },
-        FileEntry {
-            name: "示例音频.flac".to_string(),
-            kind: "file".to_string(),
-            size: 1234,
-        },
-    ]
+pub async fn navidrome_playlist_delete(
+    app_state: State<'_, AppState>,
+    server_id: String,
+    id: String,
+) -> Result<(), String> {
+    let client = subsonic_client(&app_state, &server_id)?;
+    client.delete_playlist(&id).await
+}
+
+/// 生成带新 salt/token 的播放地址，支持码率、格式与起播偏移。
+#[tauri::command]
+pub fn navidrome_stream_url(
+    app_state: State<'_, AppState>,
+    server_id: String,
+    track_id: String,
+    options: Option<StreamOptions>,
+) -> Result<String, String> {
+    let client = subsonic_client(&app_state, &server_id)?;
+    let url = client.stream_url(&track_id, &options.unwrap_or_default())?;
+    Ok(url.to_string())
+}
+
+/// OpenList 目录浏览：调用 /api/fs/list，支持分页、刷新与加密目录。
+#[tauri::command]
+pub async fn openlist_browse(
+    app: AppHandle,
+    path: Option<String>,
+    options: Option<ListOptions>,
+) -> Result<DirectoryListing, String> {
+    let client = openlist::session_client(&app)?;
+    let dir = path.unwrap_or_else(|| "/".to_string());
+    let list = client.list(&dir, &options.unwrap_or_default()).await?;
+
+    let entries = list
+        .content
+        .into_iter()
+        .map(|item| FileEntry {
+            path: join_remote_path(&dir, &item.name),
+            name: item.name,
+            is_dir: item.is_dir,
+            size: item.size,
+            modified: item.modified,
+            sign: item.sign,
+            thumb: item.thumb,
+            provider: list.provider.clone(),
+        })
+        .collect();
+
+    Ok(DirectoryListing {
+        entries,
+        total: list.total,
+        write: list.write,
+    })
 }
 
-/// OpenList 上传占位实现。
+/// OpenList 登录：Token 写入凭据存储，只把 `key_ref` 交给前端保存。
 #[tauri::command]
-pub fn openlist_upload(app_state: State<'_, AppState>, filename: String) -> QueueTask {
-    let mut queues = app_state.queues.lock().expect("队列锁定失败");
+pub async fn openlist_login(
+    app_state: State<'_, AppState>,
+    base_url: String,
+    username: String,
+    password: String,
+) -> Result<OpenlistLogin, String> {
+    if username.is_empty() || password.is_empty() {
+        return Err("请填写用户名和密码".to_string());
+    }
+    let base_url = openlist::normalize_base_url(&base_url)?;
+    let token = openlist::login(&app_state.http, &base_url, &username, &password).await?;
+    let key_ref = openlist::session_key_ref(&base_url);
+    let credential = Credential {
+        kind: CredentialKind::ApiKey,
+        secret: token,
+    };
+    app_state.credentials.store(&key_ref, credential)?;
+    Ok(OpenlistLogin {
+        base_url: base_url.as_str().trim_end_matches('/').to_string(),
+        username,
+        key_ref,
+    })
+}
+
+/// OpenList 删除：批量删除目录下的文件或文件夹。
+#[tauri::command]
+pub async fn openlist_remove(
+    app: AppHandle,
+    dir: String,
+    names: Vec<String>,
+) -> Result<(), String> {
+    openlist::session_client(&app)?.remove(&dir, &names).await
+}
+
+/// 拼接网盘路径，逻辑对应前端 `joinPath`。
+fn join_remote_path(parent: &str, name: &str) -> String {
+    let parent = parent.trim_end_matches('/');
+    format!("{}/{}", parent, name)
+}
+
+/// OpenList 上传：加入调度队列，在 Rust 侧流式读取本地文件并推送进度事件。
+#[tauri::command]
+pub fn openlist_upload(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    local_path: String,
+    remote_dir: String,
+    as_task: Option<bool>,
+) -> Result<QueueTask, String> {
+    let path = Path::new(&local_path);
+    if !path.is_file() {
+        return Err(format!("文件不存在: {}", local_path));
+    }
+    let filename = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| "无效的本地文件路径".to_string())?
+        .to_string();
+
+    let server = openlist::session_client(&app)?.base_url().to_string();
     let task = QueueTask {
         id: Uuid::new_v4().to_string(),
         kind: QueueKind::Upload,
+        remote_path: join_remote_path(&remote_dir, &filename),
+        filename,
+        local_path,
+        server,
+        source: QueueSource::Openlist,
+        transferred: 0,
+        total: 0,
+        retries: 0,
+        attempts: Vec::new(),
+        status: QueueStatus::Queued,
+        error: None,
+        as_task: as_task.unwrap_or(false),
+        password: None,
+    };
+    app_state.scheduler.enqueue(&app, task.clone());
+    Ok(task)
+}
+
+/// OpenList 下载：加入调度队列，解析直链后断点续传到 `.part` 文件，完成后原子重命名。
+#[tauri::command]
+pub fn openlist_download(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    remote_path: String,
+    local_dir: String,
+    password: Option<String>,
+) -> Result<QueueTask, String> {
+    let filename = remote_path
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| "无效的网盘文件路径".to_string())?
+        .to_string();
+
+    let server = openlist::session_client(&app)?.base_url().to_string();
+    let target = Path::new(&local_dir).join(&filename);
+    let task = QueueTask {
+        id: Uuid::new_v4().to_string(),
+        kind: QueueKind::Download,
         filename,
-        progress: 12,
-        status: "等待上传".to_string(),
+        local_path: target.to_string_lossy().into_owned(),
+        remote_path,
+        server,
+        source: QueueSource::Openlist,
+        transferred: 0,
+        total: 0,
+        retries: 0,
+        attempts: Vec::new(),
+        status: QueueStatus::Queued,
+        error: None,
+        as_task: false,
+        password,
     };
-    queues.push(task.clone());
-    task
+    app_state.scheduler.enqueue(&app, task.clone());
+    Ok(task)
 }
 
-/// OpenList 下载占位实现。
+/// Navidrome 歌曲下载：加入调度队列，优先复制在线播放缓存，否则从 stream 接口断点续传到 `local_path`。
 #[tauri::command]
-pub fn openlist_download(app_state: State<'_, AppState>, filename: String) -> QueueTask {
-    let mut queues = app_state.queues.lock().expect("队列锁定失败");
+pub fn navidrome_download(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    server_id: String,
+    song_id: String,
+    local_path: String,
+) -> Result<QueueTask, String> {
+    find_server(&app_state, &server_id)?;
+    if song_id.is_empty() {
+        return Err("歌曲 ID 不能为空".to_string());
+    }
+    let filename = Path::new(&local_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| "无效的下载路径".to_string())?;
+
     let task = QueueTask {
         id: Uuid::new_v4().to_string(),
         kind: QueueKind::Download,
         filename,
-        progress: 0,
-        status: "等待下载".to_string(),
+        local_path,
+        remote_path: song_id,
+        server: server_id,
+        source: QueueSource::Navidrome,
+        transferred: 0,
+        total: 0,
+        retries: 0,
+        attempts: Vec::new(),
+        status: QueueStatus::Queued,
+        error: None,
+        as_task: false,
+        password: None,
     };
-    queues.push(task.clone());
-    task
+    app_state.scheduler.enqueue(&app, task.clone());
+    Ok(task)
 }
 
 /// 查询队列状态。
@@ -130,54 +572,391 @@ pub fn queue_status(app_state: State<'_, AppState>) -> Vec<QueueTask> {
     app_state.queues.lock().expect("队列锁定失败").clone()
 }
 
-/// 将任务标记为暂停。
+/// 暂停任务并中断正在进行的传输。
 #[tauri::command]
-pub fn queue_pause(app_state: State<'_, AppState>, id: String) -> Vec<QueueTask> {
-    let mut queues = app_state.queues.lock().expect("队列锁定失败");
-    for task in queues.iter_mut() {
-        if task.id == id {
-            task.status = "已暂停".to_string();
-        }
+pub fn queue_pause(app: AppHandle, app_state: State<'_, AppState>, id: String) -> Vec<QueueTask> {
+    app_state.scheduler.pause(&app, &id);
+    queue_status(app_state)
+}
+
+/// 恢复暂停或失败的任务。
+#[tauri::command]
+pub fn queue_resume(app: AppHandle, app_state: State<'_, AppState>, id: String) -> Vec<QueueTask> {
+    app_state.scheduler.resume(&app, &id);
+    queue_status(app_state)
+}
+
+/// 取消任务并中断传输。
+#[tauri::command]
+pub fn queue_cancel(app: AppHandle, app_state: State<'_, AppState>, id: String) -> Vec<QueueTask> {
+    app_state.scheduler.cancel(&app, &id);
+    queue_status(app_state)
+}
+
+/// 清理已完成或已取消的任务记录。
+#[tauri::command]
+pub fn queue_clear_finished(app: AppHandle, app_state: State<'_, AppState>) -> Vec<QueueTask> {
+    app_state.scheduler.clear_finished(&app);
+    queue_status(app_state)
+}
+
+/// 读取服务器的元数据缓存状态。
+#[tauri::command]
+pub async fn cache_fetch(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    server_id: String,
+) -> Result<CacheStatus, String> {
+    let syncing = app_state
+        .syncing
+        .lock()
+        .expect("同步状态锁定失败")
+        .contains(&server_id);
+    catalog::status(&database, &server_id, syncing).await
+}
+
+/// 从服务器同步元数据到本地缓存并返回最新状态，同步过程中推送进度事件；
+/// 同一服务器已在同步时直接返回当前状态。
+#[tauri::command]
+pub async fn cache_refresh(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    server_id: String,
+    full: Option<bool>,
+) -> Result<CacheStatus, String> {
+    let client = subsonic_client(&app_state, &server_id)?;
+    let started = app_state
+        .syncing
+        .lock()
+        .expect("同步状态锁定失败")
+        .insert(server_id.clone());
+    if !started {
+        return catalog::status(&database, &server_id, true).await;
+    }
+
+    let full = full.unwrap_or(false);
+    let result = catalog::refresh(&app, &client, &database, &server_id, full).await;
+    app_state
+        .syncing
+        .lock()
+        .expect("同步状态锁定失败")
+        .remove(&server_id);
+
+    let mut status = catalog::status(&database, &server_id, false).await?;
+    if let Err(e) = result {
+        status.error.get_or_insert(e);
     }
-    queues.clone()
+    Ok(status)
 }
 
-/// 将任务标记为继续。
+/// 从本地缓存读取艺人列表，离线时同样可用。
 #[tauri::command]
-pub fn queue_resume(app_state: State<'_, AppState>, id: String) -> Vec<QueueTask> {
-    let mut queues = app_state.queues.lock().expect("队列锁定失败");
-    for task in queues.iter_mut() {
-        if task.id == id {
-            task.status = "运行中".to_string();
-        }
+pub async fn cache_artists(
+    database: State<'_, Database>,
+    server_id: String,
+) -> Result<Vec<ArtistSummary>, String> {
+    let artists = catalog::load_artists(database.pool(), &server_id).await?;
+    Ok(artists.into_iter().map(ArtistSummary::from).collect())
+}
+
+/// 从本地缓存读取专辑，可按艺人过滤。
+#[tauri::command]
+pub async fn cache_albums(
+    database: State<'_, Database>,
+    server_id: String,
+    artist_id: Option<String>,
+    paging: Option<CachePaging>,
+) -> Result<Vec<AlbumSummary>, String> {
+    let albums = catalog::load_albums(
+        database.pool(),
+        &server_id,
+        artist_id.as_deref(),
+        paging.as_ref(),
+    )
+    .await?;
+    Ok(albums.into_iter().map(AlbumSummary::from).collect())
+}
+
+/// 艺人列表的 A–Z 分组索引，顺序与 `cache_artists` 一致。
+#[tauri::command]
+pub async fn cache_artist_sections(
+    database: State<'_, Database>,
+    server_id: String,
+) -> Result<Vec<SectionIndex>, String> {
+    catalog::artist_sections(database.pool(), &server_id).await
+}
+
+/// 专辑列表的 A–Z 分组索引，顺序与相同过滤条件下的 `cache_albums` 一致。
+#[tauri::command]
+pub async fn cache_album_sections(
+    database: State<'_, Database>,
+    server_id: String,
+    artist_id: Option<String>,
+) -> Result<Vec<SectionIndex>, String> {
+    catalog::album_sections(database.pool(), &server_id, artist_id.as_deref()).await
+}
+
+/// 从本地缓存读取歌曲，可按专辑过滤。
+#[tauri::command]
+pub async fn cache_songs(
+    database: State<'_, Database>,
+    server_id: String,
+    album_id: Option<String>,
+    paging: Option<CachePaging>,
+) -> Result<Vec<TrackSummary>, String> {
+    let songs = catalog::load_songs(
+        database.pool(),
+        &server_id,
+        album_id.as_deref(),
+        paging.as_ref(),
+    )
+    .await?;
+    Ok(songs.into_iter().map(TrackSummary::from).collect())
+}
+
+/// 从本地缓存读取歌单（不含曲目）。
+#[tauri::command]
+pub async fn cache_playlists(
+    database: State<'_, Database>,
+    server_id: String,
+) -> Result<Vec<PlaylistSummary>, String> {
+    let playlists = catalog::load_playlists(database.pool(), &server_id).await?;
+    Ok(playlists.into_iter().map(PlaylistSummary::from).collect())
+}
+
+/// 从本地缓存读取流派。
+#[tauri::command]
+pub async fn cache_genres(
+    database: State<'_, Database>,
+    server_id: String,
+) -> Result<Vec<GenreSummary>, String> {
+    let genres = catalog::load_genres(database.pool(), &server_id).await?;
+    Ok(genres.into_iter().map(GenreSummary::from).collect())
+}
+
+/// 离线搜索本地缓存的标题、艺人、专辑与歌词，支持全拼、拼音首字母与繁简混合输入。
+#[tauri::command]
+pub async fn cache_search(
+    database: State<'_, Database>,
+    server_id: String,
+    keyword: String,
+    paging: Option<SearchPaging>,
+) -> Result<SearchResults, String> {
+    let matches = catalog::search(
+        database.pool(),
+        &server_id,
+        &keyword,
+        &paging.unwrap_or_default(),
+    )
+    .await?;
+    Ok(SearchResults::from(matches))
+}
+
+/// 读取歌曲歌词：优先使用缓存，没有时在线获取并加入搜索索引；离线且未缓存时返回空。
+#[tauri::command]
+pub async fn cache_lyrics(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    server_id: String,
+    song_id: String,
+) -> Result<Option<String>, String> {
+    let structured = find_server(&app_state, &server_id)?
+        .info
+        .extensions
+        .iter()
+        .any(|extension| extension.name == "songLyrics");
+    let client = subsonic_client(&app_state, &server_id).ok();
+    catalog::lyrics(
+        client.as_ref(),
+        database.pool(),
+        &server_id,
+        &song_id,
+        structured,
+    )
+    .await
+}
+
+/// 设置 `musicboom://` 协议允许访问的本地音乐库、下载与缓存目录。
+#[tauri::command]
+pub fn set_media_roots(app_state: State<'_, AppState>, roots: MediaRoots) {
+    *app_state.media_roots.lock().expect("媒体目录锁定失败") = roots.canonicalize();
+}
+
+/// 用服务器凭据生成上游播放地址并登记，返回交给 `convertFileSrc(path, "musicboom")` 的路径；
+/// 播放时边转发边写入缓存目录，完整且大小与 `size` 一致时保存为缓存文件。
+/// 同时记录播放时间，并在后台按容量上限淘汰旧缓存。
+#[tauri::command]
+pub async fn stream_proxy(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    server_id: String,
+    song_id: String,
+    options: Option<StreamOptions>,
+    size: Option<u64>,
+) -> Result<String, String> {
+    if !cache::valid_id(&song_id) {
+        return Err("歌曲 ID 无效".to_string());
     }
-    queues.clone()
+    let url = subsonic_client(&app_state, &server_id)?
+        .stream_url(&song_id, &options.unwrap_or_default())?;
+    app_state.streams.register(&song_id, url.to_string(), size);
+    cache::touch(database.pool(), &song_id).await?;
+
+    let cache_dir = app_state
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .cache
+        .clone();
+    if let Some(cache_dir) = cache_dir {
+        let pool = database.pool().clone();
+        let keep = song_id.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = cache::evict(&pool, &cache_dir, Some(&keep)).await;
+        });
+    }
+    Ok(media::stream_path(&song_id))
 }
 
-/// 删除队列任务。
+/// 直接播放已缓存的文件时记录播放时间。
 #[tauri::command]
-pub fn queue_cancel(app_state: State<'_, AppState>, id: String) -> Vec<QueueTask> {
-    let mut queues = app_state.queues.lock().expect("队列锁定失败");
-    queues.retain(|task| task.id != id);
-    queues.clone()
+pub async fn cache_touch(database: State<'_, Database>, song_id: String) -> Result<(), String> {
+    cache::touch(database.pool(), &song_id).await
 }
 
-/// 返回缓存占位结果。
+/// 设置在线播放缓存的容量上限（字节，0 表示不限制），并立即按新上限淘汰。
 #[tauri::command]
-pub fn cache_fetch() -> CacheStatus {
-    CacheStatus {
-        cache_ready: false,
-        last_refresh: "尚未同步".to_string(),
+pub async fn cache_set_budget(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    bytes: u64,
+) -> Result<EvictReport, String> {
+    cache::set_budget(database.pool(), bytes).await?;
+    let cache_dir = app_state
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .cache
+        .clone();
+    match cache_dir {
+        Some(cache_dir) => cache::evict(database.pool(), &cache_dir, None).await,
+        None => Ok(EvictReport::default()),
     }
 }
 
-/// 返回缓存刷新占位结果。
+/// 整体替换一组固定的歌曲（如 `favorites`、`playlist:{id}`），固定的缓存不会被淘汰。
+#[tauri::command]
+pub async fn cache_set_pins(
+    database: State<'_, Database>,
+    group: String,
+    song_ids: Vec<String>,
+) -> Result<(), String> {
+    cache::set_pins(database.pool(), &group, &song_ids).await
+}
+
+/// 按类别统计在线播放缓存、下载、封面与歌词的磁盘占用。
+#[tauri::command]
+pub async fn cache_usage(
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+) -> Result<CacheUsage, String> {
+    let roots = app_state
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .clone();
+    cache::usage(database.pool(), roots.cache, roots.downloads).await
+}
+
+/// 读取本地音频文件的标签、技术参数与内嵌封面。
+#[tauri::command]
+pub async fn read_audio_metadata(
+    app_state: State<'_, AppState>,
+    path: String,
+) -> Result<AudioMetadata, String> {
+    tags::read(path.into(), covers_dir(&app_state)).await
+}
+
+/// 批量读取本地音频文件的元数据，每个文件对应一条结果。
+#[tauri::command]
+pub async fn read_audio_metadata_batch(
+    app_state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<Vec<MetadataResult>, String> {
+    Ok(tags::read_many(paths, covers_dir(&app_state)).await)
+}
+
+/// 内嵌封面的导出目录，位于缓存目录下。
+fn covers_dir(app_state: &AppState) -> Option<PathBuf> {
+    app_state
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .cache
+        .as_ref()
+        .map(|cache| cache.join(cache::COVERS_DIR))
+}
+
+/// 已保存的本地音乐文件夹。
+#[tauri::command]
+pub async fn library_folders(database: State<'_, Database>) -> Result<Vec<String>, String> {
+    let folders = library::load_folders(database.pool()).await?;
+    Ok(folders
+        .into_iter()
+        .map(|folder| folder.to_string_lossy().to_string())
+        .collect())
+}
+
+/// 设置本地音乐文件夹，重新开始监听并立即增量扫描；移除的文件夹中的歌曲会被删除。
 #[tauri::command]
-pub fn cache_refresh() -> CacheStatus {
-    CacheStatus {
-        cache_ready: true,
-        last_refresh: "已触发刷新".to_string(),
+pub async fn library_set_folders(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+    folders: Vec<String>,
+) -> Result<LibraryChanges, String> {
+    let folders: Vec<PathBuf> = folders
+        .iter()
+        .map(|folder| folder.trim())
+        .filter(|folder| !folder.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    library::save_folders(database.pool(), &folders).await?;
+    if folders.is_empty() {
+        app_state.library.stop();
+    } else {
+        app_state.library.watch(&app, &folders)?;
     }
+    app_state.library.rescan(&app, database.pool()).await
+}
+
+/// 手动增量扫描全部音乐文件夹，进度通过 `library://progress` 事件推送。
+#[tauri::command]
+pub async fn library_scan(
+    app: AppHandle,
+    app_state: State<'_, AppState>,
+    database: State<'_, Database>,
+) -> Result<LibraryChanges, String> {
+    app_state.library.rescan(&app, database.pool()).await
+}
+
+/// 扫描得到的全部本地歌曲。
+#[tauri::command]
+pub async fn library_tracks(database: State<'_, Database>) -> Result<Vec<LibraryTrack>, String> {
+    library::load_tracks(database.pool()).await
+}
+
+/// 凭据概况，供前端判断是否需要重新输入密码。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialInfo {
+    pub key_ref: String,
+    pub stored: bool,
+    pub kind: Option<CredentialKind>,
+    /// 存储方式：`keyring`、`encrypted_file`，两者都无法使用时为 `unavailable`。
+    pub backend: String,
+    /// 凭据存储不可用的原因。
+    pub backend_error: Option<String>,
 }
 
 /// 简单的曲目摘要。
@@ -187,6 +966,112 @@ pub struct TrackSummary {
     pub title: String,
     pub artist: String,
     pub album: String,
+    pub duration: u32,
+    pub cover_id: Option<String>,
+}
+
+impl From<subsonic::Song> for TrackSummary {
+    fn from(song: subsonic::Song) -> Self {
+        Self {
+            id: song.id,
+            title: song.title,
+            artist: song.artist.unwrap_or_default(),
+            album: song.album.unwrap_or_default(),
+            duration: song.duration.unwrap_or_default(),
+            cover_id: song.cover_art,
+        }
+    }
+}
+
+/// 艺人摘要。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtistSummary {
+    pub id: String,
+    pub name: String,
+    pub album_count: u32,
+    pub cover_id: Option<String>,
+}
+
+impl From<subsonic::Artist> for ArtistSummary {
+    fn from(artist: subsonic::Artist) -> Self {
+        Self {
+            id: artist.id,
+            name: artist.name,
+            album_count: artist.album_count.unwrap_or_default(),
+            cover_id: artist.cover_art,
+        }
+    }
+}
+
+/// 专辑摘要。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumSummary {
+    pub id: String,
+    pub name: String,
+    pub artist: String,
+    pub artist_id: Option<String>,
+    pub song_count: u32,
+    pub duration: u32,
+    pub year: Option<i32>,
+    pub cover_id: Option<String>,
+}
+
+impl From<subsonic::Album> for AlbumSummary {
+    fn from(album: subsonic::Album) -> Self {
+        Self {
+            id: album.id,
+            name: album.name,
+            artist: album.artist.unwrap_or_default(),
+            artist_id: album.artist_id,
+            song_count: album.song_count.unwrap_or_default(),
+            duration: album.duration.unwrap_or_default(),
+            year: album.year,
+            cover_id: album.cover_art,
+        }
+    }
+}
+
+/// 流派摘要。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreSummary {
+    pub name: String,
+    pub song_count: u32,
+    pub album_count: u32,
+}
+
+impl From<subsonic::Genre> for GenreSummary {
+    fn from(genre: subsonic::Genre) -> Self {
+        Self {
+            name: genre.value,
+            song_count: genre.song_count.unwrap_or_default(),
+            album_count: genre.album_count.unwrap_or_default(),
+        }
+    }
+}
+
+/// 搜索结果，按类型分组。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub artists: Vec<ArtistSummary>,
+    pub albums: Vec<AlbumSummary>,
+    pub songs: Vec<TrackSummary>,
+    /// 结果来自本地索引时为索引的同步时间（毫秒），在线搜索时为空。
+    pub indexed_at: Option<i64>,
+}
+
+impl From<catalog::SearchMatches> for SearchResults {
+    fn from(matches: catalog::SearchMatches) -> Self {
+        Self {
+            artists: matches
+                .artists
+                .into_iter()
+                .map(ArtistSummary::from)
+                .collect(),
+            albums: matches.albums.into_iter().map(AlbumSummary::from).collect(),
+            songs: matches.songs.into_iter().map(TrackSummary::from).collect(),
+            indexed_at: matches.indexed_at,
+        }
+    }
 }
 
 /// 歌单摘要。
@@ -194,222 +1079,166 @@ pub struct TrackSummary {
 pub struct PlaylistSummary {
     pub id: String,
     pub name: String,
+    pub comment: Option<String>,
+    pub owner: Option<String>,
+    pub public: bool,
+    pub song_count: u32,
+    pub duration: u32,
+    pub cover_id: Option<String>,
     pub tracks: Vec<TrackSummary>,
 }
 
+impl From<subsonic::Playlist> for PlaylistSummary {
+    fn from(playlist: subsonic::Playlist) -> Self {
+        Self {
+            id: playlist.id,
+            name: playlist.name,
+            comment: playlist.comment,
+            owner: playlist.owner,
+            public: playlist.public.unwrap_or_default(),
+            song_count: playlist.song_count.unwrap_or_default(),
+            duration: playlist.duration.unwrap_or_default(),
+            cover_id: playlist.cover_art,
+            tracks: playlist.entry.into_iter().map(TrackSummary::from).collect(),
+        }
+    }
+}
+
 /// OpenList 目录条目。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
-    pub kind: String,
-    pub size: usize,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: String,
+    pub sign: String,
+    pub thumb: String,
+    pub provider: String,
+}
+
+/// OpenList 登录结果，Token 只保存在凭据存储中。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenlistLogin {
+    pub base_url: String,
+    pub username: String,
+    pub key_ref: String,
 }
 
-/// 缓存状态。
+/// OpenList 目录分页结果。
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CacheStatus {
-    pub cache_ready: bool,
-    pub last_refresh: String,
+pub struct DirectoryListing {
+    pub entries: Vec<FileEntry>,
+    pub total: u64,
+    pub write: bool,
 }
 
-/// 音频标签处理结果。
+/// 音频标签处理结果，文件在原处修改，只返回修改后的大小。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TagProcessResult {
     pub success: bool,
     pub error_message: Option<String>,
     pub app_anchor_id: Option<String>,
-    pub modified_data: Option<Vec<u8>>,
+    pub size: Option<u64>,
 }
 
-/// 为音频文件添加APP_ANCHOR_ID标签
+/// 为拖入的文件创建暂存文件，之后用 `stage_append` 分块写入内容。
 #[tauri::command]
-pub async fn add_app_anchor_tag(
+pub fn stage_create(
+    app_state: State<'_, AppState>,
     file_name: String,
-    file_data: Vec<u8>,
-    app_anchor_id: Option<String>,
-) -> Result<TagProcessResult, String> {
-    // 如果没有提供app_anchor_id，则生成一个新的
-    let anchor_id = app_anchor_id.unwrap_or_else(|| Uuid::new_v4().to_string());
-
-    // 从文件名检查扩展名
-    let path = Path::new(&file_name);
-    let extension = path.extension().and_then(|ext| ext.to_str());
-
-    match extension {
-        Some("flac") | Some("mp3") | Some("m4a") | Some("ogg") => {
-            // 处理支持的音频格式
-            match add_tag_to_file(&file_data, &anchor_id, extension.unwrap()) {
-                Ok(modified_data) => Ok(TagProcessResult {
-                    success: true,
-                    error_message: None,
-                    app_anchor_id: Some(anchor_id),
-                    modified_data: Some(modified_data),
-                }),
-                Err(e) => Ok(TagProcessResult {
-                    success: false,
-                    error_message: Some(format!("{}文件标签写入失败: {}", extension.unwrap(), e)),
-                    app_anchor_id: Some(anchor_id),
-                    modified_data: None,
-                }),
-            }
-        }
-        Some("wav") | Some("aac") => Ok(TagProcessResult {
-            success: false,
-            error_message: Some(format!(
-                "{}格式暂不支持标签写入，请转换为FLAC/MP3/M4A/OGG格式",
-                extension.unwrap()
-            )),
-            app_anchor_id: Some(anchor_id),
-            modified_data: None,
-        }),
-        _ => Ok(TagProcessResult {
-            success: false,
-            error_message: Some("不支持的音频格式，目前支持FLAC、MP3、M4A、OGG".to_string()),
-            app_anchor_id: Some(anchor_id),
-            modified_data: None,
-        }),
-    }
+) -> Result<StagedFile, String> {
+    app_state.staging.create(&file_name)
 }
 
-/// 为音频文件添加标签的通用函数
-fn add_tag_to_file(file_data: &[u8], anchor_id: &str, format: &str) -> Result<Vec<u8>, String> {
-    use audiotags::Tag;
-
-    // 创建临时文件
-    let temp_file = format!("temp_{}.{}", Uuid::new_v4(), format);
-    let temp_path = std::env::temp_dir().join(&temp_file);
-    let temp_path_str = temp_path.to_string_lossy();
-
-    // 写入原始数据
-    if let Err(e) = std::fs::write(&temp_path, file_data) {
-        return Err(format!("写入临时文件失败: {}", e));
-    }
-
-    // 读取并修改标签
-    let mut tag = match Tag::new().read_from_path(temp_path_str.as_ref()) {
-        Ok(t) => t,
-        Err(e) => {
-            let _ = std::fs::remove_file(&temp_path);
-            return Err(format!("读取音频标签失败: {}", e));
-        }
-    };
-
-    // 设置评论字段为APP_ANCHOR_ID
-    let comment_with_anchor = format!("APP_ANCHOR_ID:{}", anchor_id);
-    tag.set_comment(comment_with_anchor);
-
-    // 写回标签
-    if let Err(e) = tag.write_to_path(&temp_path_str) {
-        let _ = std::fs::remove_file(&temp_path);
-        return Err(format!("写入音频标签失败: {}", e));
-    }
-
-    // 读取修改后的数据
-    let modified_data = match std::fs::read(&temp_path) {
-        Ok(data) => data,
-        Err(e) => {
-            let _ = std::fs::remove_file(&temp_path);
-            return Err(format!("读取修改后的文件失败: {}", e));
-        }
+/// 追加一块暂存数据。请求体为原始二进制，`stage-id` 请求头指定暂存文件，返回当前长度。
+#[tauri::command]
+pub async fn stage_append(
+    app_state: State<'_, AppState>,
+    request: tauri::ipc::Request<'_>,
+) -> Result<u64, String> {
+    let id = request
+        .headers()
+        .get("stage-id")
+        .and_then(|value| value.to_str().ok())
+        .ok_or("缺少 stage-id 请求头")?;
+    let tauri::ipc::InvokeBody::Raw(data) = request.body() else {
+        return Err("暂存数据必须以二进制发送".to_string());
     };
+    app_state.staging.append(id, data).await
+}
 
-    // 清理临时文件
-    let _ = std::fs::remove_file(&temp_path);
-
-    Ok(modified_data)
+/// 删除暂存文件。
+#[tauri::command]
+pub fn stage_discard(app_state: State<'_, AppState>, id: String) {
+    app_state.staging.discard(&id);
 }
 
-/// 直接修改原始文件并添加标签
+/// 为本地文件或暂存文件添加APP_ANCHOR_ID标签
+///
+/// 文件在原处修改并整体替换，`tags` 为设置中的标签类型开关，未传时只写 ID3v2。
 #[tauri::command]
 pub async fn add_app_anchor_tag_to_file(
     file_path: String,
     app_anchor_id: Option<String>,
+    tags: Option<TagOptions>,
 ) -> Result<TagProcessResult, String> {
     // 如果没有提供app_anchor_id，则生成一个新的
     let anchor_id = app_anchor_id.unwrap_or_else(|| Uuid::new_v4().to_string());
 
     // 检查文件是否存在
-    let path = Path::new(&file_path);
+    let path = PathBuf::from(&file_path);
     if !path.exists() {
         return Ok(TagProcessResult {
             success: false,
             error_message: Some(format!("文件不存在: {}", file_path)),
             app_anchor_id: Some(anchor_id),
-            modified_data: None,
+            size: None,
         });
     }
 
-    // 从文件名检查扩展名
-    let extension = path.extension().and_then(|ext| ext.to_str());
-
-    match extension {
-        Some("flac") | Some("mp3") | Some("m4a") | Some("ogg") => {
-            // 处理支持的音频格式 - 直接修改原始文件
-            use audiotags::Tag;
-
-            // 读取并修改标签
-            let mut tag = match Tag::new().read_from_path(path) {
-                Ok(t) => t,
-                Err(e) => {
-                    return Ok(TagProcessResult {
-                        success: false,
-                        error_message: Some(format!("读取音频标签失败: {}", e)),
-                        app_anchor_id: Some(anchor_id),
-                        modified_data: None,
-                    });
-                }
-            };
-
-            // 设置评论字段为APP_ANCHOR_ID
-            let comment_with_anchor = format!("APP_ANCHOR_ID:{}", anchor_id);
-            tag.set_comment(comment_with_anchor);
-
-            // 写回标签到原始文件
-            if let Err(e) = tag.write_to_path(path.to_str().unwrap_or("")) {
-                return Ok(TagProcessResult {
-                    success: false,
-                    error_message: Some(format!("写入音频标签失败: {}", e)),
-                    app_anchor_id: Some(anchor_id),
-                    modified_data: None,
-                });
-            }
+    // 锚点写入专用字段，在后台线程中修改临时副本后替换原文件
+    let id = anchor_id.clone();
+    let options = tags.unwrap_or_default();
+    let written = tokio::task::spawn_blocking(move || {
+        tags::anchor::write(&path, &id, &options)?;
+        std::fs::metadata(&path)
+            .map(|metadata| metadata.len())
+            .map_err(|e| format!("读取修改后的文件失败: {}", e))
+    })
+    .await
+    .map_err(|e| format!("写入标签失败: {}", e))?;
 
-            // 读取修改后的文件数据
-            let modified_data = match std::fs::read(path) {
-                Ok(data) => data,
-                Err(e) => {
-                    return Ok(TagProcessResult {
-                        success: false,
-                        error_message: Some(format!("读取修改后的文件失败: {}", e)),
-                        app_anchor_id: Some(anchor_id),
-                        modified_data: None,
-                    });
-                }
-            };
-
-            Ok(TagProcessResult {
-                success: true,
-                error_message: None,
-                app_anchor_id: Some(anchor_id),
-                modified_data: Some(modified_data),
-            })
-        }
-        Some("wav") | Some("aac") => Ok(TagProcessResult {
-            success: false,
-            error_message: Some(format!(
-                "{}格式暂不支持标签写入，请转换为FLAC/MP3/M4A/OGG格式",
-                extension.unwrap()
-            )),
+    Ok(match written {
+        Ok(size) => TagProcessResult {
+            success: true,
+            error_message: None,
             app_anchor_id: Some(anchor_id),
-            modified_data: None,
-        }),
-        _ => Ok(TagProcessResult {
+            size: Some(size),
+        },
+        Err(e) => TagProcessResult {
             success: false,
-            error_message: Some("不支持的音频格式，目前支持FLAC、MP3、M4A、OGG".to_string()),
+            error_message: Some(e),
             app_anchor_id: Some(anchor_id),
-            modified_data: None,
-        }),
-    }
+            size: None,
+        },
+    })
+}
+
+/// 读取本地音频文件中的锚点，兼容旧版写在注释中的锚点。
+#[tauri::command]
+pub async fn read_app_anchor(path: String) -> Result<AnchorInfo, String> {
+    tokio::task::spawn_blocking(move || tags::anchor::read(Path::new(&path)))
+        .await
+        .map_err(|e| format!("读取锚点失败: {}", e))?
+}
+
+/// 把旧版写在注释中的锚点迁移到专用字段。
+#[tauri::command]
+pub async fn migrate_app_anchor(path: String) -> Result<AnchorInfo, String> {
+    tokio::task::spawn_blocking(move || tags::anchor::migrate(Path::new(&path)))
+        .await
+        .map_err(|e| format!("迁移锚点失败: {}", e))?
 }
 
 /// 清除指定目录下所有文件和文件夹
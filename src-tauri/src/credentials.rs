@@ -0,0 +1,367 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// 系统钥匙串中使用的服务名，与应用标识一致。
+const KEYRING_SERVICE: &str = "com.alex.music-boom";
+/// 探测钥匙串是否可用时读取的条目。
+const KEYRING_PROBE: &str = "__musicboom_probe__";
+/// 无钥匙串环境下的加密凭据文件，位于应用数据目录。
+const VAULT_FILE: &str = "credentials.vault";
+/// 加密文件的格式标记。
+const VAULT_MAGIC: &[u8; 4] = b"MBV1";
+/// 密钥派生使用的随机盐长度，盐随文件保存，本身不是秘密。
+const SALT_LEN: usize = 16;
+/// ChaCha20-Poly1305 的随机数长度。
+const NONCE_LEN: usize = 12;
+/// 可选的口令环境变量，设置后参与密钥派生。
+const PASSPHRASE_ENV: &str = "MUSICBOOM_VAULT_PASSPHRASE";
+
+/// 凭据类型：Subsonic 密码或 OpenSubsonic API Key。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    Password,
+    ApiKey,
+}
+
+/// 通过 `key_ref` 引用的服务器凭据，明文只保留在 Rust 侧，不回传前端。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credential {
+    pub kind: CredentialKind,
+    pub secret: String,
+}
+
+/// 凭据实际存放的位置。
+enum Backend {
+    /// 系统钥匙串（Secret Service / macOS Keychain / Windows 凭据管理器）。
+    Keyring,
+    /// 没有钥匙串守护进程的无界面 Linux 上使用的加密文件。
+    Vault(VaultFile),
+}
+
+/// 以 `key_ref` 为键的凭据存储：优先写入系统钥匙串，不可用时退回加密文件，
+/// 读取过的凭据缓存在内存中。
+#[derive(Default)]
+pub struct CredentialStore {
+    /// 钥匙串与加密文件都无法使用时（如缺少本机标识或凭据文件损坏）保存原因。
+    backend: OnceLock<Result<Backend, String>>,
+    cache: Mutex<HashMap<String, Credential>>,
+}
+
+impl CredentialStore {
+    /// 探测系统钥匙串，不可用时改用 `data_dir` 下的加密文件。
+    ///
+    /// 加密文件也无法打开时只记录原因，不影响应用启动，之后的读写返回该原因。
+    pub fn init(&self, data_dir: &Path) {
+        let backend = if keyring_available() {
+            Ok(Backend::Keyring)
+        } else {
+            VaultFile::open(data_dir).map(Backend::Vault)
+        };
+        if let Err(e) = &backend {
+            eprintln!("凭据存储不可用: {}", e);
+        }
+        let _ = self.backend.set(backend);
+    }
+
+    /// 当前使用的存储方式，供设置页展示。
+    pub fn backend_name(&self) -> &'static str {
+        match self.backend.get() {
+            Some(Ok(Backend::Keyring)) => "keyring",
+            Some(Ok(Backend::Vault(_))) => "encrypted_file",
+            Some(Err(_)) => "unavailable",
+            None => "uninitialized",
+        }
+    }
+
+    /// 凭据存储不可用的原因。
+    pub fn backend_error(&self) -> Option<&str> {
+        match self.backend.get() {
+            Some(Err(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// 保存或覆盖凭据。
+    pub fn store(&self, key_ref: &str, credential: Credential) -> Result<(), String> {
+        if key_ref.trim().is_empty() {
+            return Err("缺少凭据引用 key_ref".to_string());
+        }
+        if credential.secret.is_empty() {
+            return Err("凭据内容不能为空".to_string());
+        }
+        match self.backend()? {
+            Backend::Keyring => {
+                let payload = serde_json::to_string(&credential)
+                    .map_err(|e| format!("序列化凭据失败: {}", e))?;
+                keyring_entry(key_ref)?
+                    .set_password(&payload)
+                    .map_err(|e| format!("写入系统钥匙串失败: {}", e))?;
+            }
+            Backend::Vault(vault) => vault.update(|entries| {
+                entries.insert(key_ref.to_string(), credential.clone());
+            })?,
+        }
+        self.cache
+            .lock()
+            .expect("凭据存储锁定失败")
+            .insert(key_ref.to_string(), credential);
+        Ok(())
+    }
+
+    /// 按 `key_ref` 取出凭据，不存在时返回 `None`。
+    pub fn load(&self, key_ref: &str) -> Result<Option<Credential>, String> {
+        if let Some(credential) = self.cache.lock().expect("凭据存储锁定失败").get(key_ref)
+        {
+            return Ok(Some(credential.clone()));
+        }
+
+        let credential = match self.backend()? {
+            Backend::Keyring => match keyring_entry(key_ref)?.get_password() {
+                Ok(payload) => Some(
+                    serde_json::from_str(&payload)
+                        .map_err(|e| format!("解析钥匙串中的凭据失败: {}", e))?,
+                ),
+                Err(keyring::Error::NoEntry) => None,
+                Err(e) => return Err(format!("读取系统钥匙串失败: {}", e)),
+            },
+            Backend::Vault(vault) => vault.read()?.remove(key_ref),
+        };
+        if let Some(credential) = &credential {
+            self.cache
+                .lock()
+                .expect("凭据存储锁定失败")
+                .insert(key_ref.to_string(), credential.clone());
+        }
+        Ok(credential)
+    }
+
+    /// 按 `key_ref` 取出凭据，不存在时提示重新登录。
+    pub fn resolve(&self, key_ref: &str) -> Result<Credential, String> {
+        self.load(key_ref)?
+            .ok_or_else(|| format!("未找到凭据 {}，请重新登录", key_ref))
+    }
+
+    /// 删除凭据，返回删除前是否存在。
+    pub fn remove(&self, key_ref: &str) -> Result<bool, String> {
+        let existed = match self.backend()? {
+            Backend::Keyring => match keyring_entry(key_ref)?.delete_credential() {
+                Ok(()) => true,
+                Err(keyring::Error::NoEntry) => false,
+                Err(e) => return Err(format!("删除系统钥匙串条目失败: {}", e)),
+            },
+            Backend::Vault(vault) => {
+                let mut existed = false;
+                vault.update(|entries| existed = entries.remove(key_ref).is_some())?;
+                existed
+            }
+        };
+        self.cache.lock().expect("凭据存储锁定失败").remove(key_ref);
+        Ok(existed)
+    }
+
+    fn backend(&self) -> Result<&Backend, String> {
+        match self.backend.get() {
+            Some(Ok(backend)) => Ok(backend),
+            Some(Err(e)) => Err(format!("凭据存储不可用: {}", e)),
+            None => Err("凭据存储尚未初始化".to_string()),
+        }
+    }
+}
+
+fn keyring_entry(key_ref: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, key_ref).map_err(|e| format!("打开系统钥匙串失败: {}", e))
+}
+
+/// 读取一个不存在的条目：能正常返回“未找到”即说明钥匙串服务可用。
+fn keyring_available() -> bool {
+    match keyring::Entry::new(KEYRING_SERVICE, KEYRING_PROBE) {
+        Ok(entry) => matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)),
+        Err(_) => false,
+    }
+}
+
+/// 加密凭据文件：内容为格式标记 + 盐 + 随机数 + ChaCha20-Poly1305 密文。
+///
+/// 密钥不落盘，由本机标识、当前用户名与可选口令（`MUSICBOOM_VAULT_PASSPHRASE`）经 HKDF 派生。
+/// 威胁模型：数据目录被单独拷走（备份、同步盘、换机）后无法解密；同一台机器上以当前用户身份
+/// 运行的程序仍能重新派生密钥，未设置口令时无法防御，这也是优先使用系统钥匙串的原因。
+struct VaultFile {
+    path: PathBuf,
+    salt: [u8; SALT_LEN],
+    cipher: ChaCha20Poly1305,
+    /// 串行化读改写，避免并发保存互相覆盖。
+    lock: Mutex<()>,
+}
+
+impl VaultFile {
+    fn open(data_dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(data_dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+        let path = data_dir.join(VAULT_FILE);
+        let mut salt = [0u8; SALT_LEN];
+        match std::fs::read(&path) {
+            Ok(data) => salt.copy_from_slice(header(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => OsRng.fill_bytes(&mut salt),
+            Err(e) => return Err(format!("读取凭据文件失败: {}", e)),
+        }
+        let key = derive_key(&salt)?;
+        Ok(Self {
+            path,
+            salt,
+            cipher: ChaCha20Poly1305::new(&key),
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// 解密全部凭据，文件不存在时视为空。
+    fn read(&self) -> Result<HashMap<String, Credential>, String> {
+        let _guard = self.lock.lock().expect("凭据文件锁定失败");
+        self.decrypt()
+    }
+
+    /// 在锁内读取、修改并重新加密写回。
+    fn update(&self, apply: impl FnOnce(&mut HashMap<String, Credential>)) -> Result<(), String> {
+        let _guard = self.lock.lock().expect("凭据文件锁定失败");
+        let mut entries = self.decrypt()?;
+        apply(&mut entries);
+        self.encrypt(&entries)
+    }
+
+    fn decrypt(&self) -> Result<HashMap<String, Credential>, String> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(format!("读取凭据文件失败: {}", e)),
+        };
+        header(&data)?;
+        let body = &data[VAULT_MAGIC.len() + SALT_LEN..];
+        if body.len() < NONCE_LEN {
+            return Err("凭据文件已损坏".to_string());
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "凭据文件解密失败，可能已换机、换用户或口令不同".to_string())?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("解析凭据文件失败: {}", e))
+    }
+
+    /// 每次写入使用新的随机数，先写临时文件再替换，避免写到一半损坏。
+    fn encrypt(&self, entries: &HashMap<String, Credential>) -> Result<(), String> {
+        let plaintext =
+            serde_json::to_vec(entries).map_err(|e| format!("序列化凭据失败: {}", e))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "加密凭据失败".to_string())?;
+
+        let mut data = VAULT_MAGIC.to_vec();
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        let temp = self.path.with_extension("tmp");
+        write_private(&temp, &data)?;
+        std::fs::rename(&temp, &self.path).map_err(|e| format!("保存凭据文件失败: {}", e))
+    }
+}
+
+/// 校验格式标记并取出盐。
+fn header(data: &[u8]) -> Result<&[u8], String> {
+    let salt_end = VAULT_MAGIC.len() + SALT_LEN;
+    if data.len() < salt_end || !data.starts_with(VAULT_MAGIC) {
+        return Err("凭据文件格式无法识别".to_string());
+    }
+    Ok(&data[VAULT_MAGIC.len()..salt_end])
+}
+
+/// 由本机标识、当前用户名与可选口令派生文件密钥。
+fn derive_key(salt: &[u8]) -> Result<Key, String> {
+    let mut material = machine_id()?.into_bytes();
+    material.push(0);
+    material.extend_from_slice(current_user().as_bytes());
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        material.push(0);
+        material.extend_from_slice(passphrase.as_bytes());
+    }
+    let mut key = Key::default();
+    Hkdf::<Sha256>::new(Some(salt), &material)
+        .expand(b"musicboom credential vault", &mut key)
+        .map_err(|_| "派生凭据密钥失败".to_string())?;
+    Ok(key)
+}
+
+/// 当前登录的系统用户名。
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+/// 本机标识：systemd/dbus 的 machine-id。
+#[cfg(all(unix, not(target_os = "macos")))]
+fn machine_id() -> Result<String, String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+        .ok_or_else(|| "读取本机标识失败".to_string())
+}
+
+/// 本机标识：IOPlatformUUID。
+#[cfg(target_os = "macos")]
+fn machine_id() -> Result<String, String> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .map_err(|e| format!("读取本机标识失败: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("IOPlatformUUID"))
+        .and_then(|line| line.rsplit('"').nth(1))
+        .map(str::to_string)
+        .ok_or_else(|| "读取本机标识失败".to_string())
+}
+
+/// 本机标识：注册表中的 MachineGuid。
+#[cfg(windows)]
+fn machine_id() -> Result<String, String> {
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .output()
+        .map_err(|e| format!("读取本机标识失败: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+        .ok_or_else(|| "读取本机标识失败".to_string())
+}
+
+/// 写入仅当前用户可读写的文件。
+fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("写入凭据文件失败: {}", e))?;
+    std::io::Write::write_all(&mut file, data).map_err(|e| format!("写入凭据文件失败: {}", e))
+}
@@ -0,0 +1,274 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Rust 侧本地数据库文件名，位于应用数据目录。
+pub const DB_FILE: &str = "musicboom.db";
+
+/// 按顺序执行的建表脚本，下标 + 1 即为 `user_version`，只允许在末尾追加。
+const MIGRATIONS: &[&str] = &[
+    // 1: 传输队列
+    "CREATE TABLE IF NOT EXISTS queue_tasks (
+        id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        filename TEXT NOT NULL,
+        local_path TEXT NOT NULL,
+        remote_path TEXT NOT NULL,
+        server TEXT NOT NULL DEFAULT '',
+        source TEXT NOT NULL DEFAULT 'Openlist',
+        status TEXT NOT NULL,
+        transferred INTEGER NOT NULL DEFAULT 0,
+        total INTEGER NOT NULL DEFAULT 0,
+        retries INTEGER NOT NULL DEFAULT 0,
+        error TEXT,
+        as_task INTEGER NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL
+    )",
+    // 2: 记录每次失败的尝试
+    "ALTER TABLE queue_tasks ADD COLUMN attempts TEXT NOT NULL DEFAULT '[]'",
+    // 3: 服务器注册表
+    "CREATE TABLE IF NOT EXISTS servers (
+        id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL,
+        base_url TEXT NOT NULL,
+        username TEXT NOT NULL DEFAULT '',
+        key_ref TEXT NOT NULL,
+        is_default INTEGER NOT NULL DEFAULT 0,
+        position INTEGER NOT NULL
+    )",
+    // 4: 服务器探测信息
+    "ALTER TABLE servers ADD COLUMN info TEXT NOT NULL DEFAULT '{}'",
+    // 5: Navidrome 元数据缓存
+    "CREATE TABLE IF NOT EXISTS cache_artists (
+        server_id TEXT NOT NULL,
+        id TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        name TEXT NOT NULL,
+        sort_name TEXT,
+        album_count INTEGER,
+        cover_art TEXT,
+        PRIMARY KEY (server_id, id)
+    );
+    CREATE TABLE IF NOT EXISTS cache_albums (
+        server_id TEXT NOT NULL,
+        id TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        name TEXT NOT NULL,
+        sort_name TEXT,
+        artist TEXT,
+        artist_id TEXT,
+        year INTEGER,
+        genre TEXT,
+        song_count INTEGER,
+        duration INTEGER,
+        cover_art TEXT,
+        created TEXT,
+        changed TEXT,
+        PRIMARY KEY (server_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS cache_albums_artist ON cache_albums (server_id, artist_id);
+    CREATE TABLE IF NOT EXISTS cache_songs (
+        server_id TEXT NOT NULL,
+        id TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        title TEXT NOT NULL,
+        sort_name TEXT,
+        artist TEXT,
+        artist_id TEXT,
+        album TEXT,
+        album_id TEXT,
+        track INTEGER,
+        disc_number INTEGER,
+        year INTEGER,
+        genre TEXT,
+        duration INTEGER,
+        size INTEGER,
+        suffix TEXT,
+        bit_rate INTEGER,
+        cover_art TEXT,
+        PRIMARY KEY (server_id, id)
+    );
+    CREATE INDEX IF NOT EXISTS cache_songs_album ON cache_songs (server_id, album_id);
+    CREATE TABLE IF NOT EXISTS cache_playlists (
+        server_id TEXT NOT NULL,
+        id TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        name TEXT NOT NULL,
+        comment TEXT,
+        owner TEXT,
+        public INTEGER,
+        song_count INTEGER,
+        duration INTEGER,
+        cover_art TEXT,
+        changed TEXT,
+        PRIMARY KEY (server_id, id)
+    );
+    CREATE TABLE IF NOT EXISTS cache_genres (
+        server_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        song_count INTEGER,
+        album_count INTEGER,
+        PRIMARY KEY (server_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS cache_sync (
+        server_id TEXT NOT NULL,
+        entity TEXT NOT NULL,
+        version INTEGER NOT NULL DEFAULT 0,
+        item_count INTEGER NOT NULL DEFAULT 0,
+        synced_at INTEGER,
+        error TEXT,
+        PRIMARY KEY (server_id, entity)
+    );",
+    // 6: 增量同步使用的曲库修改时间
+    "ALTER TABLE cache_sync ADD COLUMN last_modified INTEGER",
+    // 7: 歌词缓存与离线搜索索引（文本均已转为简体小写，另存全拼与首字母）
+    "CREATE TABLE IF NOT EXISTS cache_lyrics (
+        server_id TEXT NOT NULL,
+        song_id TEXT NOT NULL,
+        lyrics TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        PRIMARY KEY (server_id, song_id)
+    );
+    CREATE TABLE IF NOT EXISTS search_index (
+        server_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        item_id TEXT NOT NULL,
+        title TEXT NOT NULL,
+        title_pinyin TEXT NOT NULL,
+        title_initials TEXT NOT NULL,
+        artist TEXT NOT NULL,
+        artist_pinyin TEXT NOT NULL,
+        artist_initials TEXT NOT NULL,
+        album TEXT NOT NULL,
+        album_pinyin TEXT NOT NULL,
+        album_initials TEXT NOT NULL,
+        lyrics TEXT NOT NULL DEFAULT '',
+        PRIMARY KEY (server_id, kind, item_id)
+    );",
+    // 8: 按拼音与字母统一排序的排序键，已缓存的行在启动时由 `catalog::backfill_sort_keys` 补全
+    "ALTER TABLE cache_artists ADD COLUMN sort_key TEXT;
+    ALTER TABLE cache_albums ADD COLUMN sort_key TEXT;
+    ALTER TABLE cache_songs ADD COLUMN sort_key TEXT;
+    CREATE INDEX IF NOT EXISTS cache_artists_sort ON cache_artists (server_id, sort_key);
+    CREATE INDEX IF NOT EXISTS cache_albums_sort ON cache_albums (server_id, sort_key);",
+    // 9: 在线播放缓存的最近播放时间、固定项与通用设置
+    "CREATE TABLE IF NOT EXISTS media_cache (
+        song_id TEXT PRIMARY KEY,
+        last_played INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS media_cache_pins (
+        song_id TEXT NOT NULL,
+        pin_group TEXT NOT NULL,
+        PRIMARY KEY (song_id, pin_group)
+    );
+    CREATE TABLE IF NOT EXISTS app_settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // 10: 扫描得到的本地音乐库与要扫描的文件夹
+    "CREATE TABLE IF NOT EXISTS library_tracks (
+        id TEXT PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE,
+        fingerprint TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        artist TEXT,
+        album TEXT,
+        album_artist TEXT,
+        track_number INTEGER,
+        disc_number INTEGER,
+        year INTEGER,
+        genre TEXT,
+        duration REAL,
+        bitrate INTEGER,
+        sample_rate INTEGER,
+        bit_depth INTEGER,
+        channels INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS library_tracks_fingerprint ON library_tracks (fingerprint);
+    CREATE TABLE IF NOT EXISTS library_folders (
+        path TEXT PRIMARY KEY
+    );",
+];
+
+/// 本地 SQLite 数据库，保存服务器配置、传输队列、元数据缓存等需要跨重启保留的数据。
+pub struct Database {
+    pool: SqlitePool,
+    path: PathBuf,
+}
+
+impl Database {
+    /// 打开（必要时创建）数据库并执行未应用的迁移。
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建数据目录失败: {}", e))?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("打开本地数据库失败: {}", e))?;
+
+        let database = Self {
+            pool,
+            path: path.to_path_buf(),
+        };
+        database.migrate().await?;
+        Ok(database)
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// 数据库文件路径。
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 根据 `user_version` 执行尚未应用的迁移。
+    async fn migrate(&self) -> Result<(), String> {
+        let version: i64 = sqlx::query("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await
+            .and_then(|row| row.try_get(0))
+            .map_err(|e| format!("读取数据库版本失败: {}", e))?;
+
+        for (index, script) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let mut tx = self
+                .pool
+                .begin()
+                .await
+                .map_err(|e| format!("开启迁移事务失败: {}", e))?;
+            sqlx::raw_sql(script)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("执行第 {} 个迁移失败: {}", index + 1, e))?;
+            sqlx::raw_sql(&format!("PRAGMA user_version = {}", index + 1))
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("更新数据库版本失败: {}", e))?;
+            tx.commit()
+                .await
+                .map_err(|e| format!("提交迁移失败: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// 当前时间的毫秒时间戳，各表的时间列统一使用。
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
@@ -1,8 +1,24 @@
 mod app_state;
+mod catalog;
 mod commands;
+mod credentials;
+mod db;
+mod library;
+mod media;
+mod mysql;
+mod openlist;
+mod probe;
+mod retry;
+mod servers;
+mod staging;
+mod subsonic;
+mod tags;
+mod transfer;
 
-use app_state::AppState;
+use app_state::{AppState, QueueKind, QueueStatus};
 use commands::*;
+use std::collections::HashSet;
+use std::path::PathBuf;
 use tauri::{
     menu::{MenuBuilder, MenuItemBuilder},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
@@ -82,30 +98,112 @@ pub fn run() {
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_sql::Builder::new().build())
         .manage(AppState::default())
+        .register_asynchronous_uri_scheme_protocol(media::SCHEME, |ctx, request, responder| {
+            // 在后台读取文件，避免阻塞 webview 线程
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(media::serve(&app, &request).await);
+            });
+        })
         .setup(|app| {
             setup_tray(app)?;
+
+            // 初始化凭据存储（迁移旧版明文 Token）与暂存区，打开本地数据库（补全旧缓存的排序键），载入服务器配置并恢复上次未完成的传输队列，清理队列不再引用的暂存文件
+            let data_dir = app.path().app_data_dir()?;
+            app.state::<AppState>().credentials.init(&data_dir);
+            let _ = openlist::migrate_session(app.handle());
+            app.state::<AppState>().staging.init(&app.path().app_cache_dir()?)?;
+            *app.state::<AppState>().media_roots.lock().expect("媒体目录锁定失败") =
+                media::MediaRoots::from_path_config(&data_dir);
+            let db_path = data_dir.join(db::DB_FILE);
+            let database = tauri::async_runtime::block_on(db::Database::open(&db_path))?;
+            let _ = tauri::async_runtime::block_on(catalog::backfill_sort_keys(database.pool()));
+            let saved = tauri::async_runtime::block_on(servers::load_servers(database.pool()))?;
+            *app.state::<AppState>().servers.lock().expect("服务器配置锁定失败") = saved;
+            let handle = app.handle().clone();
+            tauri::async_runtime::block_on(
+                app.state::<AppState>()
+                    .scheduler
+                    .restore(&handle, database.pool().clone()),
+            )?;
+            let staged: HashSet<PathBuf> = app
+                .state::<AppState>()
+                .queues
+                .lock()
+                .expect("队列锁定失败")
+                .iter()
+                .filter(|task| {
+                    task.kind == QueueKind::Upload
+                        && !matches!(task.status, QueueStatus::Done | QueueStatus::Cancelled)
+                })
+                .map(|task| PathBuf::from(&task.local_path))
+                .collect();
+            app.state::<AppState>().staging.retain(&staged);
+            app.manage(database);
+            app.state::<AppState>().library.start(&handle);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             add_server,
             list_servers,
             remove_server,
+            set_default_server,
+            set_credential,
+            credential_info,
+            rotate_credential,
+            delete_credential,
+            mysql_store_password,
+            mysql_test,
+            mysql_connect,
             navidrome_search,
+            navidrome_playlists,
             navidrome_playlist,
+            navidrome_playlist_create,
+            navidrome_playlist_update,
+            navidrome_playlist_delete,
             navidrome_stream_url,
+            openlist_login,
             openlist_browse,
+            openlist_remove,
             openlist_upload,
             openlist_download,
+            navidrome_download,
             queue_status,
             queue_pause,
             queue_resume,
             queue_cancel,
+            queue_clear_finished,
             cache_fetch,
             cache_refresh,
-            add_app_anchor_tag,
+            cache_artists,
+            cache_albums,
+            cache_artist_sections,
+            cache_album_sections,
+            cache_songs,
+            cache_playlists,
+            cache_genres,
+            cache_search,
+            cache_lyrics,
+            stage_create,
+            stage_append,
+            stage_discard,
             add_app_anchor_tag_to_file,
+            read_app_anchor,
+            migrate_app_anchor,
             clear_directory,
             clear_downloaded_songs,
+            set_media_roots,
+            stream_proxy,
+            cache_touch,
+            cache_set_budget,
+            cache_set_pins,
+            cache_usage,
+            read_audio_metadata,
+            read_audio_metadata_batch,
+            library_folders,
+            library_set_folders,
+            library_scan,
+            library_tracks,
             resolve_hostname
         ])
         .run(tauri::generate_context!())
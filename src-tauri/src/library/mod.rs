@@ -0,0 +1,484 @@
+mod store;
+mod watch;
+
+pub use store::{load_folders, load_tracks, save_folders};
+pub use watch::LibraryWatcher;
+
+use crate::tags::{self, AudioMetadata};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+/// 扫描进度事件名，载荷为 [`ScanProgress`]。
+pub const PROGRESS_EVENT: &str = "library://progress";
+/// 音乐库变化事件名，载荷为 [`LibraryChanges`]。
+pub const CHANGED_EVENT: &str = "library://changed";
+
+/// 识别为音频文件的扩展名。
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "m4a", "mp4", "alac", "aac", "ogg", "oga", "opus", "wav", "aif", "aiff", "ape",
+    "wv", "dsf",
+];
+/// 同时读取标签的文件数。
+const PARALLELISM: usize = 8;
+/// 内容指纹在音频数据头、中、尾各取的字节数。
+const FINGERPRINT_SAMPLE: u64 = 64 * 1024;
+/// 两次进度推送之间的最小间隔。
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 扫描得到的本地歌曲。
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryTrack {
+    /// 首次发现时生成，改名或移动后保持不变。
+    pub id: String,
+    pub path: String,
+    /// 音频数据的内容指纹（不含标签），用于识别改名与移动，改标签后仍能匹配。
+    pub fingerprint: String,
+    pub size: u64,
+    /// 文件修改时间（毫秒），与大小一起判断是否需要重新读取标签。
+    pub modified: i64,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u16>,
+    pub disc_number: Option<u16>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    pub duration: Option<f64>,
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u8>,
+    pub channels: Option<u8>,
+}
+
+/// 扫描阶段。
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanPhase {
+    /// 遍历文件夹。
+    Walking,
+    /// 读取新增或变化文件的标签。
+    Reading,
+    Done,
+}
+
+/// 推送给前端的扫描进度。
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanProgress {
+    pub phase: ScanPhase,
+    pub done: u64,
+    /// 遍历阶段总数未知，为空。
+    pub total: Option<u64>,
+}
+
+/// 一次扫描的结果，前端据此同步自己的本地音乐列表。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LibraryChanges {
+    pub added: Vec<LibraryTrack>,
+    pub updated: Vec<LibraryTrack>,
+    /// 按内容指纹识别出的改名或移动，ID 不变、路径为新路径。
+    pub moved: Vec<LibraryTrack>,
+    /// 已删除文件对应的歌曲 ID。
+    pub removed: Vec<String>,
+    /// 本次无法读取或保存的文件，原有记录保持不变，下次扫描时重试。
+    pub errors: Vec<ScanError>,
+}
+
+/// 扫描中单个文件的错误。
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanError {
+    pub path: String,
+    pub error: String,
+}
+
+impl LibraryChanges {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.moved.is_empty()
+            && self.removed.is_empty()
+    }
+}
+
+/// 遍历得到的音频文件。
+struct FoundFile {
+    path: PathBuf,
+    size: u64,
+    modified: i64,
+}
+
+/// 一次遍历的结果。
+#[derive(Default)]
+struct Walked {
+    files: Vec<FoundFile>,
+    /// 无法完整读取的文件夹或文件，其下的旧记录本次不能判定为已删除。
+    unreadable: Vec<PathBuf>,
+    errors: Vec<ScanError>,
+}
+
+impl Walked {
+    fn mark_unreadable(&mut self, path: &Path, error: String) {
+        self.errors.push(ScanError {
+            path: path.to_string_lossy().to_string(),
+            error,
+        });
+        self.unreadable.push(path.to_path_buf());
+    }
+}
+
+/// 增量扫描音乐文件夹并更新本地音乐库。
+///
+/// 大小与修改时间都没变的文件直接跳过；新增或变化的文件并行计算内容指纹并读取标签。
+/// 新文件的指纹与已消失的文件相同时视为改名或移动，沿用原来的 ID；同一路径上换成了
+/// 内容不同的文件时，原记录按已消失处理。不在任何文件夹中的旧记录（文件被删除或文件夹
+/// 被移除）会被删除，但无法读取的文件夹（如未挂载的移动硬盘）中的记录保持不变。
+/// 单个文件夹或文件读取、保存失败只记入 `errors`，不影响其他文件。
+pub async fn scan(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    folders: &[PathBuf],
+) -> Result<LibraryChanges, String> {
+    let progress = Progress::new(app);
+    progress.finish(ScanPhase::Walking, 0, None);
+    let roots = folders.to_vec();
+    let walked = tokio::task::spawn_blocking(move || walk(&roots))
+        .await
+        .map_err(|e| format!("遍历音乐文件夹失败: {}", e))?;
+
+    let mut known: HashMap<String, LibraryTrack> = load_tracks(pool)
+        .await?
+        .into_iter()
+        .map(|track| (track.path.clone(), track))
+        .collect();
+    let mut pending = Vec::new();
+    for file in walked.files {
+        let key = file.path.to_string_lossy().to_string();
+        match known.remove(&key) {
+            Some(track) if track.size == file.size && track.modified == file.modified => {}
+            existing => pending.push((file, existing)),
+        }
+    }
+    // 剩下的是本次没有找到的文件，按指纹分组以便匹配改名与移动；位于无法读取的文件夹中的除外
+    let mut missing: HashMap<String, Vec<LibraryTrack>> = HashMap::new();
+    for track in known.into_values() {
+        let path = Path::new(&track.path);
+        if walked.unreadable.iter().any(|dir| path.starts_with(dir)) {
+            continue;
+        }
+        missing
+            .entry(track.fingerprint.clone())
+            .or_default()
+            .push(track);
+    }
+
+    let total = pending.len() as u64;
+    let mut changes = LibraryChanges {
+        errors: walked.errors,
+        ..LibraryChanges::default()
+    };
+    let mut done = 0;
+    progress.finish(ScanPhase::Reading, done, Some(total));
+    let mut reads = stream::iter(pending)
+        .map(|(file, existing)| async move {
+            let read = read_file(&file).await;
+            (file, existing, read)
+        })
+        .buffer_unordered(PARALLELISM);
+    let mut read_files = Vec::new();
+    while let Some((file, existing, read)) = reads.next().await {
+        done += 1;
+        progress.report(ScanPhase::Reading, done, Some(total));
+        // 无法读取的文件（如正在写入）留到下次扫描
+        match read {
+            Ok((fingerprint, metadata)) => {
+                // 同一路径上换成了内容不同的文件，原记录可能已移动到别处
+                let existing = match existing {
+                    Some(track) if track.fingerprint != fingerprint => {
+                        missing
+                            .entry(track.fingerprint.clone())
+                            .or_default()
+                            .push(track);
+                        None
+                    }
+                    existing => existing,
+                };
+                read_files.push((file, existing, fingerprint, metadata));
+            }
+            Err(error) => changes.errors.push(ScanError {
+                path: file.path.to_string_lossy().to_string(),
+                error,
+            }),
+        }
+    }
+
+    // 全部读取完后再匹配改名与移动，确保换了内容的路径上的原记录也能参与匹配
+    for (file, existing, fingerprint, metadata) in read_files {
+        let moved = match existing {
+            Some(_) => None,
+            None => missing.get_mut(&fingerprint).and_then(Vec::pop),
+        };
+        let id = existing
+            .as_ref()
+            .or(moved.as_ref())
+            .map(|track| track.id.clone())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let track = to_track(id, &file, fingerprint, metadata);
+        if let Err(error) = store::save_track(pool, &track).await {
+            // 匹配到的旧记录不会被删除，下次扫描时重新匹配
+            changes.errors.push(ScanError {
+                path: track.path,
+                error,
+            });
+            continue;
+        }
+        if existing.is_some() {
+            changes.updated.push(track);
+        } else if moved.is_some() {
+            changes.moved.push(track);
+        } else {
+            changes.added.push(track);
+        }
+    }
+
+    changes.removed = missing
+        .into_values()
+        .flatten()
+        .map(|track| track.id)
+        .collect();
+    store::delete_tracks(pool, &changes.removed).await?;
+
+    progress.finish(ScanPhase::Done, done, Some(total));
+    if !changes.is_empty() {
+        let _ = app.emit(CHANGED_EVENT, changes.clone());
+    }
+    Ok(changes)
+}
+
+/// 计算内容指纹并读取标签，不导出封面。
+async fn read_file(file: &FoundFile) -> Result<(String, AudioMetadata), String> {
+    let path = file.path.clone();
+    let size = file.size;
+    let fingerprint = tokio::task::spawn_blocking(move || fingerprint(&path, size))
+        .await
+        .map_err(|e| format!("计算内容指纹失败: {}", e))?
+        .map_err(|e| format!("计算内容指纹失败: {}", e))?;
+    let metadata = tags::read(file.path.clone(), None).await?;
+    Ok((fingerprint, metadata))
+}
+
+/// 音频数据长度加上头、中、尾三段内容的 MD5，避免为改名检测读取整个文件。
+/// 开头的 ID3v2 与 FLAC 元数据块、末尾的 APEv2 与 ID3v1 不参与计算，只改标签时指纹不变。
+fn fingerprint(path: &Path, size: u64) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let (start, end) = payload_range(&mut file, size)?;
+    let len = end - start;
+    let mut context = md5::Context::new();
+    context.consume(len.to_le_bytes());
+    let mut buffer = Vec::with_capacity(FINGERPRINT_SAMPLE as usize);
+    for offset in [0, len / 2, len.saturating_sub(FINGERPRINT_SAMPLE)] {
+        buffer.clear();
+        file.seek(SeekFrom::Start(start + offset))?;
+        file.by_ref()
+            .take(FINGERPRINT_SAMPLE.min(len - offset))
+            .read_to_end(&mut buffer)?;
+        context.consume(&buffer);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// 去掉首尾标签后音频数据所在的区间 `[start, end)`，无法识别的部分按音频数据处理。
+fn payload_range(file: &mut std::fs::File, size: u64) -> std::io::Result<(u64, u64)> {
+    let mut read_at = |offset: u64, buffer: &mut [u8]| -> std::io::Result<bool> {
+        if offset + buffer.len() as u64 > size {
+            return Ok(false);
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buffer)?;
+        Ok(true)
+    };
+
+    let mut start = 0;
+    // ID3v2：10 字节头，长度为 syncsafe 整数，带尾部时再加 10 字节
+    let mut header = [0u8; 10];
+    while read_at(start, &mut header)? && header.starts_with(b"ID3") {
+        let len = header[6..10]
+            .iter()
+            .fold(0u64, |len, byte| (len << 7) | u64::from(byte & 0x7F));
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        start += 10 + len + footer;
+    }
+    // FLAC：`fLaC` 后是若干元数据块，每块 4 字节头，最高位标记最后一块
+    let mut magic = [0u8; 4];
+    if read_at(start, &mut magic)? && &magic == b"fLaC" {
+        start += 4;
+        let mut block = [0u8; 4];
+        while read_at(start, &mut block)? {
+            start += 4 + u64::from(u32::from_be_bytes([0, block[1], block[2], block[3]]));
+            if block[0] & 0x80 != 0 {
+                break;
+            }
+        }
+    }
+
+    let mut end = size;
+    loop {
+        // ID3v1：末尾 128 字节，以 `TAG` 开头
+        let mut tag = [0u8; 3];
+        if end >= start + 128 && read_at(end - 128, &mut tag)? && &tag == b"TAG" {
+            end -= 128;
+            continue;
+        }
+        // APEv2：32 字节尾部，长度含尾部不含头部，标志位最高位表示另有 32 字节头部
+        let mut footer = [0u8; 32];
+        if end >= start + 32 && read_at(end - 32, &mut footer)? && footer.starts_with(b"APETAGEX") {
+            let len = u64::from(u32::from_le_bytes([
+                footer[12], footer[13], footer[14], footer[15],
+            ]));
+            let flags = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+            let header = if flags & (1 << 31) != 0 { 32 } else { 0 };
+            if len + header <= end - start {
+                end -= len + header;
+                continue;
+            }
+        }
+        break;
+    }
+    Ok((start.min(end), end))
+}
+
+fn to_track(
+    id: String,
+    file: &FoundFile,
+    fingerprint: String,
+    metadata: AudioMetadata,
+) -> LibraryTrack {
+    let title = metadata.title.unwrap_or_else(|| {
+        file.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    LibraryTrack {
+        id,
+        path: file.path.to_string_lossy().to_string(),
+        fingerprint,
+        size: file.size,
+        modified: file.modified,
+        title,
+        artist: (!metadata.artists.is_empty()).then(|| metadata.artists.join(" / ")),
+        album: metadata.album,
+        album_artist: metadata.album_artist,
+        track_number: metadata.track_number,
+        disc_number: metadata.disc_number,
+        year: metadata.year,
+        genre: metadata.genre,
+        duration: metadata.duration,
+        bitrate: metadata.bitrate,
+        sample_rate: metadata.sample_rate,
+        bit_depth: metadata.bit_depth,
+        channels: metadata.channels,
+    }
+}
+
+/// 递归列出文件夹中的音频文件，不跟随软链接，同一文件只保留一次。
+/// 无法读取的文件夹与文件记入 `unreadable` 与 `errors`。
+fn walk(folders: &[PathBuf]) -> Walked {
+    let mut walked = Walked::default();
+    let mut found: HashMap<PathBuf, FoundFile> = HashMap::new();
+    for folder in folders {
+        for entry in walkdir::WalkDir::new(folder) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // 没有路径的错误无法定位，整个文件夹都不判定删除
+                    let path = e.path().unwrap_or(folder).to_path_buf();
+                    walked.mark_unreadable(&path, format!("读取文件夹失败: {}", e));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() || !is_audio(entry.path()) {
+                continue;
+            }
+            let meta = match entry.metadata() {
+                Ok(meta) => meta,
+                Err(e) => {
+                    walked.mark_unreadable(entry.path(), format!("读取文件信息失败: {}", e));
+                    continue;
+                }
+            };
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_millis() as i64)
+                .unwrap_or_default();
+            found.insert(
+                entry.path().to_path_buf(),
+                FoundFile {
+                    path: entry.into_path(),
+                    size: meta.len(),
+                    modified,
+                },
+            );
+        }
+    }
+    walked.files = found.into_values().collect();
+    walked
+}
+
+fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|audio| audio.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// 按固定间隔推送扫描进度。
+struct Progress<'a> {
+    app: &'a AppHandle,
+    last: Mutex<Option<Instant>>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(app: &'a AppHandle) -> Self {
+        Self {
+            app,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// 汇报进度；未到推送间隔时忽略。
+    fn report(&self, phase: ScanPhase, done: u64, total: Option<u64>) {
+        {
+            let mut last = self.last.lock().expect("进度锁定失败");
+            let now = Instant::now();
+            if last.is_some_and(|at| now - at < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last = Some(now);
+        }
+        self.emit(phase, done, total);
+    }
+
+    /// 阶段切换时总会推送一次。
+    fn finish(&self, phase: ScanPhase, done: u64, total: Option<u64>) {
+        self.emit(phase, done, total);
+    }
+
+    fn emit(&self, phase: ScanPhase, done: u64, total: Option<u64>) {
+        let _ = self
+            .app
+            .emit(PROGRESS_EVENT, ScanProgress { phase, done, total });
+    }
+}
@@ -0,0 +1,136 @@
+use super::LibraryTrack;
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::Row;
+use std::path::PathBuf;
+
+/// 读取扫描得到的全部歌曲。
+pub async fn load_tracks(pool: &SqlitePool) -> Result<Vec<LibraryTrack>, String> {
+    let rows = sqlx::query(
+        "SELECT id, path, fingerprint, size, modified, title, artist, album, album_artist,
+                track_number, disc_number, year, genre, duration, bitrate, sample_rate,
+                bit_depth, channels
+         FROM library_tracks ORDER BY path",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("读取本地音乐库失败: {}", e))?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+/// 写入或更新一首歌曲，路径变化（改名、移动）时沿用原来的 ID。
+pub async fn save_track(pool: &SqlitePool, track: &LibraryTrack) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO library_tracks (id, path, fingerprint, size, modified, title, artist, album,
+                                     album_artist, track_number, disc_number, year, genre,
+                                     duration, bitrate, sample_rate, bit_depth, channels)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+            path = excluded.path,
+            fingerprint = excluded.fingerprint,
+            size = excluded.size,
+            modified = excluded.modified,
+            title = excluded.title,
+            artist = excluded.artist,
+            album = excluded.album,
+            album_artist = excluded.album_artist,
+            track_number = excluded.track_number,
+            disc_number = excluded.disc_number,
+            year = excluded.year,
+            genre = excluded.genre,
+            duration = excluded.duration,
+            bitrate = excluded.bitrate,
+            sample_rate = excluded.sample_rate,
+            bit_depth = excluded.bit_depth,
+            channels = excluded.channels",
+    )
+    .bind(&track.id)
+    .bind(&track.path)
+    .bind(&track.fingerprint)
+    .bind(track.size as i64)
+    .bind(track.modified)
+    .bind(&track.title)
+    .bind(&track.artist)
+    .bind(&track.album)
+    .bind(&track.album_artist)
+    .bind(track.track_number)
+    .bind(track.disc_number)
+    .bind(track.year)
+    .bind(&track.genre)
+    .bind(track.duration)
+    .bind(track.bitrate)
+    .bind(track.sample_rate)
+    .bind(track.bit_depth)
+    .bind(track.channels)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("保存本地歌曲失败: {}", e))?;
+    Ok(())
+}
+
+pub async fn delete_tracks(pool: &SqlitePool, ids: &[String]) -> Result<(), String> {
+    for id in ids {
+        sqlx::query("DELETE FROM library_tracks WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("删除本地歌曲失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 需要扫描与监听的音乐文件夹。
+pub async fn load_folders(pool: &SqlitePool) -> Result<Vec<PathBuf>, String> {
+    let rows = sqlx::query("SELECT path FROM library_folders ORDER BY path")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取音乐文件夹失败: {}", e))?;
+    Ok(rows
+        .iter()
+        .map(|row| PathBuf::from(row.get::<String, _>("path")))
+        .collect())
+}
+
+pub async fn save_folders(pool: &SqlitePool, folders: &[PathBuf]) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    sqlx::query("DELETE FROM library_folders")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("保存音乐文件夹失败: {}", e))?;
+    for folder in folders {
+        sqlx::query("INSERT OR IGNORE INTO library_folders (path) VALUES (?)")
+            .bind(folder.to_string_lossy().as_ref())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("保存音乐文件夹失败: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(())
+}
+
+fn from_row(row: &SqliteRow) -> LibraryTrack {
+    LibraryTrack {
+        id: row.get("id"),
+        path: row.get("path"),
+        fingerprint: row.get("fingerprint"),
+        size: row.get::<i64, _>("size").max(0) as u64,
+        modified: row.get("modified"),
+        title: row.get("title"),
+        artist: row.get("artist"),
+        album: row.get("album"),
+        album_artist: row.get("album_artist"),
+        track_number: row.get("track_number"),
+        disc_number: row.get("disc_number"),
+        year: row.get("year"),
+        genre: row.get("genre"),
+        duration: row.get("duration"),
+        bitrate: row.get("bitrate"),
+        sample_rate: row.get("sample_rate"),
+        bit_depth: row.get("bit_depth"),
+        channels: row.get("channels"),
+    }
+}
@@ -0,0 +1,104 @@
+use super::{scan, store, LibraryChanges};
+use crate::app_state::AppState;
+use crate::db::Database;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use sqlx::sqlite::SqlitePool;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+/// 文件变化停止这么久之后才重新扫描，避免复制大量文件时反复扫描。
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// 监听音乐文件夹，文件新增、删除、改名后自动增量扫描。
+#[derive(Default)]
+pub struct LibraryWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    /// 串行化扫描，监听触发与手动扫描不会同时进行。
+    scanning: tokio::sync::Mutex<()>,
+}
+
+impl LibraryWatcher {
+    /// 启动时开始监听已保存的文件夹，并补扫应用未运行期间的变化。
+    pub fn start(&self, app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let database = app.state::<Database>();
+            let Ok(folders) = store::load_folders(database.pool()).await else {
+                return;
+            };
+            if folders.is_empty() {
+                return;
+            }
+            let library = &app.state::<AppState>().library;
+            let _ = library.watch(&app, &folders);
+            let _ = library.rescan(&app, database.pool()).await;
+        });
+    }
+
+    /// 替换要监听的文件夹，不存在的文件夹会被跳过。
+    pub fn watch(&self, app: &AppHandle, folders: &[PathBuf]) -> Result<(), String> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if event.is_ok_and(|event| relevant(&event)) {
+                let _ = tx.send(());
+            }
+        })
+        .map_err(|e| format!("监听音乐文件夹失败: {}", e))?;
+        for folder in folders.iter().filter(|folder| folder.is_dir()) {
+            watcher
+                .watch(folder, RecursiveMode::Recursive)
+                .map_err(|e| format!("监听 {} 失败: {}", folder.display(), e))?;
+        }
+        // 旧的监听器被丢弃后，它的后台任务会随通道关闭而退出
+        *self.watcher.lock().expect("监听器锁定失败") = Some(watcher);
+
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while rx.recv().await.is_some() {
+                loop {
+                    match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                        Ok(Some(())) => continue,
+                        Ok(None) => return,
+                        Err(_) => break,
+                    }
+                }
+                let database = app.state::<Database>();
+                let _ = app
+                    .state::<AppState>()
+                    .library
+                    .rescan(&app, database.pool())
+                    .await;
+            }
+        });
+        Ok(())
+    }
+
+    /// 停止监听。
+    pub fn stop(&self) {
+        *self.watcher.lock().expect("监听器锁定失败") = None;
+    }
+
+    /// 扫描已保存的全部文件夹，同一时间只进行一次。
+    pub async fn rescan(
+        &self,
+        app: &AppHandle,
+        pool: &SqlitePool,
+    ) -> Result<LibraryChanges, String> {
+        let _scanning = self.scanning.lock().await;
+        let folders = store::load_folders(pool).await?;
+        scan(app, pool, &folders).await
+    }
+}
+
+/// 只关心新增、删除、改名与内容变化，忽略访问与权限、时间戳等元数据变化。
+fn relevant(event: &Event) -> bool {
+    match event.kind {
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_) | EventKind::Any => true,
+        EventKind::Access(_) | EventKind::Other => false,
+    }
+}
@@ -0,0 +1,318 @@
+use crate::db::now_millis;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// `app_settings` 中保存缓存容量上限的键。
+const BUDGET_KEY: &str = "media_cache_budget";
+/// 未设置时的缓存容量上限：2 GiB。
+pub const DEFAULT_BUDGET: u64 = 2 * 1024 * 1024 * 1024;
+/// 缓存目录下存放封面图片的子目录。
+pub const COVERS_DIR: &str = "covers";
+/// 在线播放缓存文件的扩展名，文件名为 `{songId}.mp3`。
+const CACHE_EXTENSION: &str = "mp3";
+
+/// 文件数量与占用字节数。
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Usage {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        self.files += 1;
+    }
+}
+
+/// 按类别统计的磁盘占用。
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUsage {
+    /// 在线播放缓存（含未完成的临时文件）。
+    pub stream_cache: Usage,
+    /// 其中被固定、不会被淘汰的部分。
+    pub pinned: Usage,
+    pub downloads: Usage,
+    pub covers: Usage,
+    /// 数据库中缓存的歌词，按文本字节数计。
+    pub lyrics: Usage,
+    /// 在线播放缓存的容量上限，0 表示不限制。
+    pub budget: u64,
+}
+
+/// 一次淘汰的结果。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EvictReport {
+    pub removed: u64,
+    pub freed: u64,
+    /// 淘汰后在线播放缓存的占用。
+    pub used: u64,
+}
+
+/// 缓存目录中的一首歌曲。
+struct CachedFile {
+    song_id: String,
+    path: PathBuf,
+    size: u64,
+    modified: i64,
+}
+
+/// 歌曲 ID 能否安全地用作缓存文件名：不能为空，不能含路径分隔符、`..` 或 NUL。
+pub fn valid_id(song_id: &str) -> bool {
+    !song_id.is_empty() && !song_id.contains(['/', '\\', '\0']) && !song_id.contains("..")
+}
+
+/// 歌曲在缓存目录中的文件路径；ID 无效或拼接后不在缓存目录内（如带盘符）时返回空。
+pub fn cache_file(cache_dir: &Path, song_id: &str) -> Option<PathBuf> {
+    if !valid_id(song_id) {
+        return None;
+    }
+    let path = cache_dir.join(format!("{}.{}", song_id, CACHE_EXTENSION));
+    (path.parent() == Some(cache_dir)).then_some(path)
+}
+
+/// 在线播放缓存的容量上限，0 表示不限制。
+pub async fn budget(pool: &SqlitePool) -> Result<u64, String> {
+    let value: Option<String> = sqlx::query("SELECT value FROM app_settings WHERE key = ?")
+        .bind(BUDGET_KEY)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("读取缓存容量设置失败: {}", e))?
+        .map(|row| row.get("value"));
+    Ok(value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_BUDGET))
+}
+
+pub async fn set_budget(pool: &SqlitePool, bytes: u64) -> Result<(), String> {
+    sqlx::query("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?, ?)")
+        .bind(BUDGET_KEY)
+        .bind(bytes.to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("保存缓存容量设置失败: {}", e))?;
+    Ok(())
+}
+
+/// 记录歌曲的最近播放时间，淘汰时最久未播放的先删除。
+pub async fn touch(pool: &SqlitePool, song_id: &str) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO media_cache (song_id, last_played) VALUES (?, ?)
+         ON CONFLICT (song_id) DO UPDATE SET last_played = excluded.last_played",
+    )
+    .bind(song_id)
+    .bind(now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("记录播放时间失败: {}", e))?;
+    Ok(())
+}
+
+/// 整体替换一组固定的歌曲，如 `favorites` 或 `playlist:{id}`，固定的缓存永不淘汰。
+pub async fn set_pins(pool: &SqlitePool, group: &str, song_ids: &[String]) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+    sqlx::query("DELETE FROM media_cache_pins WHERE pin_group = ?")
+        .bind(group)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("更新固定缓存失败: {}", e))?;
+    for song_id in song_ids {
+        sqlx::query("INSERT OR IGNORE INTO media_cache_pins (song_id, pin_group) VALUES (?, ?)")
+            .bind(song_id)
+            .bind(group)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("更新固定缓存失败: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(())
+}
+
+/// 超出容量上限时，按最近播放时间从旧到新删除未固定的缓存，`keep` 为正在播放的歌曲。
+pub async fn evict(
+    pool: &SqlitePool,
+    cache_dir: &Path,
+    keep: Option<&str>,
+) -> Result<EvictReport, String> {
+    let budget = budget(pool).await?;
+    let files = list_cached(cache_dir.to_path_buf()).await?;
+    let mut report = EvictReport {
+        used: files.iter().map(|file| file.size).sum(),
+        ..EvictReport::default()
+    };
+    if budget == 0 || report.used <= budget {
+        return Ok(report);
+    }
+
+    let pinned = pinned(pool).await?;
+    let played: HashMap<String, i64> = sqlx::query("SELECT song_id, last_played FROM media_cache")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取播放时间失败: {}", e))?
+        .iter()
+        .map(|row| (row.get("song_id"), row.get("last_played")))
+        .collect();
+
+    let mut candidates: Vec<(i64, &CachedFile)> = files
+        .iter()
+        .filter(|file| !pinned.contains(&file.song_id) && Some(file.song_id.as_str()) != keep)
+        .map(|file| {
+            let last_played = played.get(&file.song_id).copied().unwrap_or(file.modified);
+            (last_played, file)
+        })
+        .collect();
+    candidates.sort_by_key(|(last_played, _)| *last_played);
+
+    for (_, file) in candidates {
+        if report.used <= budget {
+            break;
+        }
+        if tokio::fs::remove_file(&file.path).await.is_err() {
+            continue;
+        }
+        sqlx::query("DELETE FROM media_cache WHERE song_id = ?")
+            .bind(&file.song_id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("删除播放记录失败: {}", e))?;
+        report.used -= file.size;
+        report.freed += file.size;
+        report.removed += 1;
+    }
+    Ok(report)
+}
+
+/// 统计在线播放缓存、下载目录、封面与歌词的占用。
+pub async fn usage(
+    pool: &SqlitePool,
+    cache_dir: Option<PathBuf>,
+    downloads: Option<PathBuf>,
+) -> Result<CacheUsage, String> {
+    let pinned_ids = pinned(pool).await?;
+    let (stream_cache, pinned, covers, downloads) = tokio::task::spawn_blocking(move || {
+        let mut stream_cache = Usage::default();
+        let mut pinned = Usage::default();
+        let mut covers = Usage::default();
+        if let Some(cache_dir) = &cache_dir {
+            for (path, size) in files_in(cache_dir) {
+                stream_cache.add(size);
+                if song_id(&path).is_some_and(|id| pinned_ids.contains(id)) {
+                    pinned.add(size);
+                }
+            }
+            covers = dir_usage(&cache_dir.join(COVERS_DIR));
+        }
+        let downloads = downloads.map(|dir| dir_usage(&dir)).unwrap_or_default();
+        (stream_cache, pinned, covers, downloads)
+    })
+    .await
+    .map_err(|e| format!("统计缓存占用失败: {}", e))?;
+
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS files, COALESCE(SUM(LENGTH(CAST(lyrics AS BLOB))), 0) AS bytes
+         FROM cache_lyrics",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("统计歌词缓存失败: {}", e))?;
+    let lyrics = Usage {
+        bytes: row.get::<i64, _>("bytes") as u64,
+        files: row.get::<i64, _>("files") as u64,
+    };
+
+    Ok(CacheUsage {
+        stream_cache,
+        pinned,
+        downloads,
+        covers,
+        lyrics,
+        budget: budget(pool).await?,
+    })
+}
+
+async fn pinned(pool: &SqlitePool) -> Result<HashSet<String>, String> {
+    Ok(sqlx::query("SELECT DISTINCT song_id FROM media_cache_pins")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("读取固定缓存失败: {}", e))?
+        .iter()
+        .map(|row| row.get("song_id"))
+        .collect())
+}
+
+/// 列出缓存目录中已完成的缓存文件，忽略正在写入的 `.part` 临时文件。
+async fn list_cached(cache_dir: PathBuf) -> Result<Vec<CachedFile>, String> {
+    tokio::task::spawn_blocking(move || {
+        files_in(&cache_dir)
+            .into_iter()
+            .filter_map(|(path, size)| {
+                let song_id = song_id(&path)?.to_string();
+                let modified = std::fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_millis() as i64)
+                    .unwrap_or_default();
+                Some(CachedFile {
+                    song_id,
+                    path,
+                    size,
+                    modified,
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("读取缓存目录失败: {}", e))
+}
+
+/// `{songId}.mp3` 对应的歌曲 ID。
+fn song_id(path: &Path) -> Option<&str> {
+    if path.extension()? != CACHE_EXTENSION {
+        return None;
+    }
+    path.file_stem()?.to_str()
+}
+
+/// 目录第一层的文件及大小。
+fn files_in(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            meta.is_file().then(|| (entry.path(), meta.len()))
+        })
+        .collect()
+}
+
+/// 递归统计目录，跳过软链接避免循环。
+fn dir_usage(dir: &Path) -> Usage {
+    let mut usage = Usage::default();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return usage;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let nested = dir_usage(&entry.path());
+            usage.bytes += nested.bytes;
+            usage.files += nested.files;
+        } else if file_type.is_file() {
+            usage.add(entry.metadata().map(|meta| meta.len()).unwrap_or_default());
+        }
+    }
+    usage
+}
@@ -0,0 +1,427 @@
+pub mod cache;
+mod proxy;
+
+pub use proxy::StreamProxy;
+
+use crate::app_state::AppState;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tauri::http::{header, Method, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// 自定义协议名，前端用 `convertFileSrc(path, "musicboom")` 生成地址。
+pub const SCHEME: &str = "musicboom";
+/// 在线播放流的地址前缀，后接歌曲 ID。
+const STREAM_PREFIX: &str = "stream/";
+/// 前端保存目录设置的文件，位于应用数据目录。
+const PATH_CONFIG_FILE: &str = "path-config.json";
+/// 单次 Range 响应的最大字节数，避免拖动进度时一次读入整个文件。
+const MAX_CHUNK: u64 = 4 * 1024 * 1024;
+/// 识别文件格式时读取的文件头长度。
+const SNIFF_LEN: usize = 12;
+
+/// 允许通过自定义协议访问的目录，路径之外的请求一律拒绝。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaRoots {
+    /// 本地音乐库目录，也可以是单个文件。
+    pub library: Vec<PathBuf>,
+    /// 下载目录。
+    pub downloads: Option<PathBuf>,
+    /// 在线播放的缓存目录。
+    pub cache: Option<PathBuf>,
+}
+
+impl MediaRoots {
+    /// 从前端的 `path-config.json` 读取下载与缓存目录，作为启动时的默认值。
+    pub fn from_path_config(data_dir: &Path) -> Self {
+        #[derive(Default, Deserialize)]
+        #[serde(default, rename_all = "camelCase")]
+        struct PathConfig {
+            music_dir: String,
+            cache_dir: String,
+        }
+
+        let config: PathConfig = std::fs::read(data_dir.join(PATH_CONFIG_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        let dir = |value: String| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| PathBuf::from(value))
+        };
+        Self {
+            library: Vec::new(),
+            downloads: dir(config.music_dir),
+            cache: dir(config.cache_dir),
+        }
+        .canonicalize()
+    }
+
+    /// 解析符号链接与相对部分，丢弃不存在的路径，之后按前缀比较即可。
+    pub fn canonicalize(self) -> Self {
+        let resolve = |path: PathBuf| std::fs::canonicalize(path).ok();
+        Self {
+            library: self.library.into_iter().filter_map(resolve).collect(),
+            downloads: self.downloads.and_then(resolve),
+            cache: self.cache.and_then(resolve),
+        }
+    }
+
+    /// 已解析的 `path` 是否位于某个根目录内（或正是允许的单个文件）。
+    fn contains(&self, path: &Path) -> bool {
+        self.library
+            .iter()
+            .chain(self.downloads.iter())
+            .chain(self.cache.iter())
+            .any(|root| path.starts_with(root))
+    }
+}
+
+/// 在线播放流在协议中的路径。
+pub fn stream_path(song_id: &str) -> String {
+    format!("{}{}", STREAM_PREFIX, song_id)
+}
+
+/// 处理一次协议请求，出错时返回带状态码的纯文本响应。
+pub async fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    match respond(app, request).await {
+        Ok(response) => response,
+        Err((status, message)) => {
+            let mut response = Response::new(message.into_bytes());
+            *response.status_mut() = status;
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("text/plain; charset=utf-8"),
+            );
+            response
+        }
+    }
+}
+
+type Failure = (StatusCode, String);
+
+/// 协议地址指向的内容。
+enum Target {
+    /// 本地文件的绝对路径。
+    File(PathBuf),
+    /// 经 [`StreamProxy`] 转发的在线播放流。
+    Stream(String),
+}
+
+async fn respond(
+    app: &AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, Failure> {
+    match request_target(request.uri().path())? {
+        Target::File(path) => respond_file(app, &path, request).await,
+        Target::Stream(song_id) => respond_stream(app, &song_id, request).await,
+    }
+}
+
+/// 校验路径位于允许的目录内后返回文件内容。
+async fn respond_file(
+    app: &AppHandle,
+    path: &Path,
+    request: &Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, Failure> {
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "文件不存在".to_string()))?;
+    let allowed = app
+        .state::<AppState>()
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .contains(&path);
+    if !allowed {
+        return Err((StatusCode::FORBIDDEN, "不允许访问该路径".to_string()));
+    }
+    serve_file(&path, request).await
+}
+
+/// 按 Range 返回文件的一段；未带 Range 时小文件整个返回，大文件只返回开头一段并以 206 告知总长度，
+/// 播放器会继续用 Range 请求后续内容，单次响应不会把整个文件读入内存。
+async fn serve_file(path: &Path, request: &Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Failure> {
+    let io_error = |e: std::io::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("读取文件失败: {}", e),
+        )
+    };
+    let mut file = tokio::fs::File::open(path).await.map_err(io_error)?;
+    let len = file.metadata().await.map_err(io_error)?.len();
+    let mime = content_type(&mut file, path).await.map_err(io_error)?;
+    drop(file);
+
+    let (status, start, end) = match range_header(request) {
+        Some(range) => match parse_range(range, len) {
+            Some((start, end)) => (
+                StatusCode::PARTIAL_CONTENT,
+                start,
+                end.min(start + MAX_CHUNK - 1),
+            ),
+            None => return Ok(unsatisfiable(Some(len))),
+        },
+        None if len > MAX_CHUNK && request.method() != Method::HEAD => {
+            (StatusCode::PARTIAL_CONTENT, 0, MAX_CHUNK - 1)
+        }
+        None => (StatusCode::OK, 0, len.saturating_sub(1)),
+    };
+
+    let body = if request.method() == Method::HEAD || len == 0 {
+        Vec::new()
+    } else {
+        read_range(path, start, end).await.map_err(io_error)?
+    };
+    let size = if len == 0 { 0 } else { end - start + 1 };
+    let mut response = media_response(status, body, mime);
+    insert(&mut response, header::CONTENT_LENGTH, size.to_string());
+    if status == StatusCode::PARTIAL_CONTENT {
+        insert(
+            &mut response,
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end, len),
+        );
+    }
+    Ok(response)
+}
+
+/// 在线播放流：已完整缓存时直接读缓存文件，否则边转发边写入缓存目录。
+async fn respond_stream(
+    app: &AppHandle,
+    song_id: &str,
+    request: &Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, Failure> {
+    let state = app.state::<AppState>();
+    let cache_dir = state
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .cache
+        .clone()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "未设置缓存目录".to_string()))?;
+    let cached = cache::cache_file(&cache_dir, song_id)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "歌曲 ID 无效".to_string()))?;
+    if tokio::fs::try_exists(&cached).await.unwrap_or(false) {
+        return serve_file(&cached, request).await;
+    }
+
+    // 总长度未知，不支持从末尾倒数的范围
+    let (start, end) = match range_header(request) {
+        Some(range) => match parse_open_range(range) {
+            Some(range) => range,
+            None => return Ok(unsatisfiable(None)),
+        },
+        None => (0, None),
+    };
+    let chunk = state
+        .streams
+        .read(&state.http, &cache_dir, song_id, start, end, MAX_CHUNK)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+
+    let length = chunk.data.len();
+    let body = if request.method() == Method::HEAD {
+        Vec::new()
+    } else {
+        chunk.data
+    };
+    let total = chunk
+        .total
+        .map(|total| total.to_string())
+        .unwrap_or_else(|| "*".to_string());
+    let mut response = media_response(StatusCode::PARTIAL_CONTENT, body, &chunk.mime);
+    insert(&mut response, header::CONTENT_LENGTH, length.to_string());
+    insert(
+        &mut response,
+        header::CONTENT_RANGE,
+        format!("bytes {}-{}/{}", chunk.start, chunk.end, total),
+    );
+    Ok(response)
+}
+
+/// 带公共响应头的媒体响应。
+fn media_response(status: StatusCode, body: Vec<u8>, mime: &str) -> Response<Vec<u8>> {
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    insert(&mut response, header::CONTENT_TYPE, mime.to_string());
+    insert(&mut response, header::ACCEPT_RANGES, "bytes".to_string());
+    insert(
+        &mut response,
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        "*".to_string(),
+    );
+    response
+}
+
+fn unsatisfiable(len: Option<u64>) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+    let len = len
+        .map(|len| len.to_string())
+        .unwrap_or_else(|| "*".to_string());
+    insert(
+        &mut response,
+        header::CONTENT_RANGE,
+        format!("bytes */{}", len),
+    );
+    response
+}
+
+fn insert(response: &mut Response<Vec<u8>>, name: header::HeaderName, value: String) {
+    if let Ok(value) = header::HeaderValue::from_str(&value) {
+        response.headers_mut().insert(name, value);
+    }
+}
+
+fn range_header(request: &Request<Vec<u8>>) -> Option<&str> {
+    request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// 读取文件的 `[start, end]` 闭区间。
+async fn read_range(path: &Path, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut data = vec![0; (end - start + 1) as usize];
+    file.seek(SeekFrom::Start(start)).await?;
+    file.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+/// 地址路径为百分号编码的绝对路径（`convertFileSrc` 的格式），
+/// 或 `stream/{songId}` 形式的在线播放流。
+fn request_target(raw: &str) -> Result<Target, Failure> {
+    let decoded = percent_decode_str(raw.trim_start_matches('/'))
+        .decode_utf8()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "路径编码无效".to_string()))?;
+    if let Some(song_id) = decoded.strip_prefix(STREAM_PREFIX) {
+        if !cache::valid_id(song_id) {
+            return Err((StatusCode::BAD_REQUEST, "歌曲 ID 无效".to_string()));
+        }
+        return Ok(Target::Stream(song_id.to_string()));
+    }
+    let path = PathBuf::from(decoded.as_ref());
+    if !path.is_absolute() {
+        return Err((StatusCode::BAD_REQUEST, "只接受绝对路径".to_string()));
+    }
+    Ok(Target::File(path))
+}
+
+/// 解析不依赖总长度的范围 `bytes=start-` 或 `bytes=start-end`。
+fn parse_open_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let spec = value.trim().strip_prefix("bytes=")?.split(',').next()?;
+    let (start, end) = spec.trim().split_once('-')?;
+    let start = start.trim().parse().ok()?;
+    let end = match end.trim() {
+        "" => None,
+        end => Some(end.parse().ok()?),
+    };
+    match end {
+        Some(end) if end < start => None,
+        _ => Some((start, end)),
+    }
+}
+
+/// 解析 `Range: bytes=...`，返回闭区间；多段范围只取第一段，无法满足时返回 `None`。
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value
+        .trim()
+        .strip_prefix("bytes=")?
+        .split(',')
+        .next()?
+        .trim();
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.min(len.checked_sub(1)?),
+        ),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+/// 按文件头识别音频格式（缓存文件一律以 `.mp3` 命名，不能只看扩展名），识别不了时再看扩展名。
+async fn content_type(file: &mut tokio::fs::File, path: &Path) -> std::io::Result<&'static str> {
+    let mut head = [0u8; SNIFF_LEN];
+    let mut read = 0;
+    while read < SNIFF_LEN {
+        let n = file.read(&mut head[read..]).await?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    file.seek(SeekFrom::Start(0)).await?;
+    Ok(sniff(&head[..read]).unwrap_or_else(|| mime_from_extension(path)))
+}
+
+/// 读取文件头识别 MIME 类型，无法识别时按扩展名判断。
+pub fn detect(file: &mut std::fs::File, path: &Path) -> std::io::Result<&'static str> {
+    use std::io::{Read, Seek};
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    file.by_ref()
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(sniff(&head).unwrap_or_else(|| mime_from_extension(path)))
+}
+
+fn sniff(head: &[u8]) -> Option<&'static str> {
+    let mime = match head {
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        [b'I', b'D', b'3', ..] => "audio/mpeg",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', ..] => "audio/aiff",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "audio/mp4",
+        [b'M', b'A', b'C', b' ', ..] => "audio/x-ape",
+        [b'w', b'v', b'p', b'k', ..] => "audio/x-wavpack",
+        [b'D', b'S', b'D', b' ', ..] => "audio/x-dsf",
+        // ADTS 同步字（AAC）与 MPEG 音频帧同步字
+        [0xFF, second, ..] if second & 0xF6 == 0xF0 => "audio/aac",
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => "audio/mpeg",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+fn mime_from_extension(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "wav" => "audio/wav",
+        "aif" | "aiff" => "audio/aiff",
+        "m4a" | "mp4" | "alac" => "audio/mp4",
+        "aac" => "audio/aac",
+        "ape" => "audio/x-ape",
+        "wv" => "audio/x-wavpack",
+        "dsf" => "audio/x-dsf",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "lrc" | "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,335 @@
+use super::cache;
+use crate::retry::{self, RetryPolicy};
+use futures_util::StreamExt;
+use reqwest::header::{HeaderName, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{watch, OnceCell};
+
+/// 请求位置超出已下载部分这么多字节时，不再等待边下边存，而是直接向服务器发起 Range 请求。
+const SEEK_AHEAD: u64 = 1024 * 1024;
+/// 上游连续这么久没有数据时放弃缓存，后续请求改为直接转发。
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 已注册的在线播放源。
+#[derive(Debug, Clone)]
+struct Source {
+    url: String,
+    /// 服务器报告的文件大小，下载完成后据此校验。
+    size: Option<u64>,
+}
+
+/// 边下边存的进度。
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Progress {
+    /// 已写入临时文件的字节数。
+    Running(u64),
+    /// 完整且大小一致，已改名为正式缓存文件。
+    Saved(u64),
+    /// 已下载完但大小与服务器不符（如转码），临时文件仅供本次播放。
+    Unsaved(u64),
+    Failed,
+}
+
+/// 一首歌曲的后台下载。
+struct Download {
+    part: PathBuf,
+    target: PathBuf,
+    /// 上游响应的总长度，未知时为空。
+    total: Option<u64>,
+    mime: String,
+    progress: watch::Receiver<Progress>,
+}
+
+/// 在线播放代理：把 Navidrome 的流转给 webview，同时写入缓存目录，
+/// 完整下载且大小与服务器一致时才保存为 `{cacheDir}/{songId}.mp3`。
+#[derive(Default)]
+pub struct StreamProxy {
+    sources: Mutex<HashMap<String, Source>>,
+    /// 每首歌只发起一次下载：同一首歌的并发请求等待同一个单元格，不同歌曲互不阻塞。
+    downloads: Mutex<HashMap<String, Arc<OnceCell<Arc<Download>>>>>,
+}
+
+/// 一段要返回给 webview 的数据。
+pub struct Chunk {
+    pub data: Vec<u8>,
+    pub start: u64,
+    pub end: u64,
+    pub total: Option<u64>,
+    pub mime: String,
+}
+
+impl StreamProxy {
+    /// 登记歌曲的上游地址，并清理已结束的下载：同一首歌只保留进行中或文件仍在的下载，
+    /// 失败或缓存已被淘汰的会在下次请求时重新下载。
+    pub fn register(&self, song_id: &str, url: String, size: Option<u64>) {
+        self.sources
+            .lock()
+            .expect("播放代理锁定失败")
+            .insert(song_id.to_string(), Source { url, size });
+
+        self.downloads
+            .lock()
+            .expect("播放代理锁定失败")
+            .retain(|id, cell| {
+                // 仍在发起请求的下载保留，由发起方写入
+                let Some(download) = cell.get() else {
+                    return true;
+                };
+                let state = download.progress.borrow().clone();
+                let reusable = match state {
+                    Progress::Running(_) => true,
+                    Progress::Saved(_) => id == song_id && download.target.exists(),
+                    Progress::Unsaved(_) => id == song_id && download.part.exists(),
+                    Progress::Failed => false,
+                };
+                if !reusable && !matches!(state, Progress::Saved(_)) {
+                    let _ = std::fs::remove_file(&download.part);
+                }
+                reusable
+            });
+    }
+
+    /// 读取 `[start, end]` 区间：已下载到的部分直接读临时文件，尚未下载到时等待，
+    /// 跳转到远处或下载失败时直接向服务器请求该区间。
+    pub async fn read(
+        &self,
+        http: &reqwest::Client,
+        cache_dir: &Path,
+        song_id: &str,
+        start: u64,
+        end: Option<u64>,
+        max_len: u64,
+    ) -> Result<Chunk, String> {
+        let source = self
+            .sources
+            .lock()
+            .expect("播放代理锁定失败")
+            .get(song_id)
+            .cloned()
+            .ok_or_else(|| format!("未登记的播放流: {}", song_id))?;
+        let target = cache::cache_file(cache_dir, song_id)
+            .ok_or_else(|| format!("歌曲 ID 无效: {}", song_id))?;
+        let download = self.download(http, target, song_id, &source).await?;
+        let total = download.total.or(source.size);
+        let limit = |available: u64| {
+            let last = end
+                .unwrap_or(u64::MAX)
+                .min(start.saturating_add(max_len) - 1);
+            last.min(available.saturating_sub(1))
+        };
+
+        let mut progress = download.progress.clone();
+        loop {
+            let state = progress.borrow_and_update().clone();
+            let (path, available) = match state {
+                Progress::Running(written) if written > start => (&download.part, written),
+                Progress::Running(written) if start < written + SEEK_AHEAD => {
+                    if progress.changed().await.is_err() {
+                        return fetch_range(http, &source, start, end, max_len, total).await;
+                    }
+                    continue;
+                }
+                Progress::Saved(len) => (&download.target, len),
+                Progress::Unsaved(len) => (&download.part, len),
+                Progress::Running(_) | Progress::Failed => {
+                    return fetch_range(http, &source, start, end, max_len, total).await;
+                }
+            };
+            if start >= available {
+                return Err("请求的范围超出文件长度".to_string());
+            }
+            let last = limit(available);
+            let data = match super::read_range(path, start, last).await {
+                Ok(data) => data,
+                // 读取期间临时文件恰好改名为正式缓存，按新状态重读
+                Err(_) if *progress.borrow() != state => continue,
+                Err(e) => return Err(format!("读取缓存失败: {}", e)),
+            };
+            let finished = match state {
+                Progress::Saved(len) | Progress::Unsaved(len) => Some(len),
+                _ => None,
+            };
+            return Ok(Chunk {
+                data,
+                start,
+                end: last,
+                total: total.or(finished),
+                mime: download.mime.clone(),
+            });
+        }
+    }
+
+    /// 取得正在进行的下载，没有时发起请求；发起失败时下次请求会重新尝试。
+    async fn download(
+        &self,
+        http: &reqwest::Client,
+        target: PathBuf,
+        song_id: &str,
+        source: &Source,
+    ) -> Result<Arc<Download>, String> {
+        let cell = self
+            .downloads
+            .lock()
+            .expect("播放代理锁定失败")
+            .entry(song_id.to_string())
+            .or_default()
+            .clone();
+        cell.get_or_try_init(|| start_download(http, target, source))
+            .await
+            .cloned()
+    }
+}
+
+/// 向服务器发起播放请求，在后台把响应写入临时文件，完整且大小一致时改名为缓存文件。
+async fn start_download(
+    http: &reqwest::Client,
+    target: PathBuf,
+    source: &Source,
+) -> Result<Arc<Download>, String> {
+    let response = retry::send(&RetryPolicy::API, "stream", || http.get(&source.url)).await?;
+    if !response.status().is_success() {
+        return Err(format!("stream 返回 HTTP {}", response.status()));
+    }
+    let total = header(&response, CONTENT_LENGTH).and_then(|value| value.parse().ok());
+    let mime = header(&response, CONTENT_TYPE).unwrap_or_else(|| "audio/mpeg".to_string());
+
+    let mut part = target.clone().into_os_string();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let file = tokio::fs::File::create(&part)
+        .await
+        .map_err(|e| format!("创建缓存文件失败: {}", e))?;
+    let (sender, progress) = watch::channel(Progress::Running(0));
+    let download = Arc::new(Download {
+        part: part.clone(),
+        target: target.clone(),
+        total,
+        mime,
+        progress,
+    });
+
+    let expected = source.size.or(total);
+    tauri::async_runtime::spawn(async move {
+        let state = match write_through(response, file, &sender).await {
+            Ok(written) if Some(written) == expected => {
+                match tokio::fs::rename(&part, &target).await {
+                    Ok(()) => Progress::Saved(written),
+                    Err(_) => Progress::Unsaved(written),
+                }
+            }
+            Ok(written) => Progress::Unsaved(written),
+            Err(_) => Progress::Failed,
+        };
+        let _ = sender.send(state);
+    });
+    Ok(download)
+}
+
+/// 把响应体写入临时文件并逐块汇报进度，返回写入的总字节数。
+async fn write_through(
+    response: reqwest::Response,
+    mut file: tokio::fs::File,
+    progress: &watch::Sender<Progress>,
+) -> Result<u64, String> {
+    let mut written = 0;
+    let mut stream = response.bytes_stream();
+    loop {
+        let chunk = match tokio::time::timeout(STALL_TIMEOUT, stream.next()).await {
+            Ok(Some(chunk)) => chunk.map_err(|e| format!("播放流中断: {}", e))?,
+            Ok(None) => break,
+            Err(_) => return Err("播放流长时间无数据".to_string()),
+        };
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("写入缓存文件失败: {}", e))?;
+        written += chunk.len() as u64;
+        let _ = progress.send(Progress::Running(written));
+    }
+    // 落盘后再改名，避免断电后留下不完整的正式缓存
+    file.flush()
+        .await
+        .map_err(|e| format!("写入缓存文件失败: {}", e))?;
+    file.sync_all()
+        .await
+        .map_err(|e| format!("写入缓存文件失败: {}", e))?;
+    Ok(written)
+}
+
+/// 不经缓存，直接向服务器请求一段数据。
+async fn fetch_range(
+    http: &reqwest::Client,
+    source: &Source,
+    start: u64,
+    end: Option<u64>,
+    max_len: u64,
+    total: Option<u64>,
+) -> Result<Chunk, String> {
+    let last = end
+        .unwrap_or(u64::MAX)
+        .min(start.saturating_add(max_len) - 1);
+    let range = format!("bytes={}-{}", start, last);
+    let response = retry::send(&RetryPolicy::API, "stream", || {
+        http.get(&source.url).header(RANGE, range.as_str())
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(format!("stream 返回 HTTP {}", response.status()));
+    }
+    let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mime = header(&response, CONTENT_TYPE).unwrap_or_else(|| "audio/mpeg".to_string());
+    // Content-Range: bytes start-end/total
+    let total = header(&response, CONTENT_RANGE)
+        .and_then(|value| {
+            value
+                .rsplit_once('/')
+                .and_then(|(_, total)| total.parse().ok())
+        })
+        .or(total);
+    // 服务器忽略了 Range 时从头返回整个文件，边读边跳过前面的部分，取够后即断开
+    let skip = if partial { 0 } else { start };
+    let data = read_window(response, skip, last - start + 1).await?;
+    if data.is_empty() {
+        return Err("请求的范围超出文件长度".to_string());
+    }
+    Ok(Chunk {
+        end: start + data.len() as u64 - 1,
+        data,
+        start,
+        total,
+        mime,
+    })
+}
+
+/// 跳过响应体开头 `skip` 字节后最多读取 `len` 字节，不把整个响应读入内存。
+async fn read_window(response: reqwest::Response, skip: u64, len: u64) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    let mut position = 0u64;
+    let mut stream = response.bytes_stream();
+    while (data.len() as u64) < len {
+        let Some(chunk) = stream.next().await else {
+            break;
+        };
+        let chunk = chunk.map_err(|e| format!("读取播放流失败: {}", e))?;
+        let chunk_end = position + chunk.len() as u64;
+        if chunk_end > skip {
+            let from = skip.saturating_sub(position) as usize;
+            let wanted = (len - data.len() as u64) as usize;
+            let to = chunk.len().min(from + wanted);
+            data.extend_from_slice(&chunk[from..to]);
+        }
+        position = chunk_end;
+    }
+    Ok(data)
+}
+
+fn header(response: &reqwest::Response, name: HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
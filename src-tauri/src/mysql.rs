@@ -0,0 +1,59 @@
+use serde::Deserialize;
+use sqlx::mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+/// 前端保存的 MySQL 连接信息，密码保存在凭据存储中，不在这里出现。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MysqlTarget {
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    pub username: String,
+}
+
+impl MysqlTarget {
+    /// 密码在凭据存储中的引用。
+    pub fn key_ref(&self) -> String {
+        format!(
+            "mysql:{}@{}:{}/{}",
+            self.username, self.host, self.port, self.database
+        )
+    }
+
+    /// 注册到 SQL 插件时使用的连接名，不含密码，前端用它调用 `Database.get`。
+    pub fn db_name(&self) -> String {
+        format!(
+            "mysql://{}@{}:{}/{}",
+            self.username, self.host, self.port, self.database
+        )
+    }
+
+    /// 建立连接池，失败时返回可直接展示的错误信息。
+    pub async fn connect(&self, password: &str) -> Result<MySqlPool, String> {
+        if self.host.is_empty() || self.database.is_empty() || self.username.is_empty() {
+            return Err("MySQL 配置不完整".to_string());
+        }
+        let options = MySqlConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .database(&self.database)
+            .username(&self.username)
+            .password(password);
+        MySqlPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("连接 MySQL 失败: {}", e))
+    }
+}
+
+/// 把连接池交给 SQL 插件管理，已有同名连接时先关闭旧连接。
+pub async fn register(app: &AppHandle, name: String, pool: MySqlPool) {
+    let instances = app.state::<DbInstances>();
+    let mut instances = instances.0.write().await;
+    if let Some(DbPool::MySql(previous)) = instances.insert(name, DbPool::MySql(pool)) {
+        previous.close().await;
+    }
+}
@@ -0,0 +1,461 @@
+use crate::app_state::AppState;
+use crate::credentials::{Credential, CredentialKind};
+use crate::retry::{self, Failure, RetryPolicy};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+use url::Url;
+
+/// 前端 `useOpenlistStore` 持久化登录态所用的文件与键名。
+pub const SESSION_STORE: &str = "openlist-session.dat";
+pub const SESSION_KEY: &str = "openlistSession";
+
+/// 前端保存的 OpenList 登录态，Token 保存在凭据存储中，这里只记录 `key_ref`。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OpenlistSession {
+    pub base_url: Option<String>,
+    pub username: Option<String>,
+    pub key_ref: Option<String>,
+    /// 旧版本直接保存的明文 Token，启动时迁移到凭据存储。
+    pub token: Option<String>,
+}
+
+/// 读取前端保存的登录态，Token 从凭据存储中取出后构造客户端。
+pub fn session_client(app: &AppHandle) -> Result<OpenlistClient, String> {
+    let session = read_session(app)?.ok_or_else(|| "尚未登录 OpenList".to_string())?;
+    let (Some(base_url), Some(key_ref)) = (session.base_url, session.key_ref) else {
+        return Err("尚未登录 OpenList".to_string());
+    };
+    let app_state = app.state::<AppState>();
+    let token = app_state.credentials.resolve(&key_ref)?.secret;
+    OpenlistClient::new(app_state.http.clone(), &base_url, &token)
+}
+
+/// 构造客户端并确认当前登录的仍是任务创建时的服务器。
+pub fn session_client_for(app: &AppHandle, server: &str) -> Result<OpenlistClient, String> {
+    let client = session_client(app)?;
+    if !server.is_empty() && client.base_url().as_str() != server {
+        return Err(format!("当前登录的网盘已不是 {}，请重新登录后重试", server));
+    }
+    Ok(client)
+}
+
+/// 把旧版本登录态中的明文 Token 移入凭据存储，并改写为只含 `key_ref` 的登录态。
+pub fn migrate_session(app: &AppHandle) -> Result<(), String> {
+    let Some(session) = read_session(app)? else {
+        return Ok(());
+    };
+    let (Some(base_url), Some(token)) = (session.base_url, session.token) else {
+        return Ok(());
+    };
+    let key_ref = session_key_ref(&normalize_base_url(&base_url)?);
+    if !token.trim().is_empty() {
+        let credential = Credential {
+            kind: CredentialKind::ApiKey,
+            secret: token,
+        };
+        app.state::<AppState>()
+            .credentials
+            .store(&key_ref, credential)?;
+    }
+
+    let store = app
+        .store(SESSION_STORE)
+        .map_err(|e| format!("读取 OpenList 登录态失败: {}", e))?;
+    store.set(
+        SESSION_KEY,
+        serde_json::json!({
+            "baseUrl": base_url,
+            "username": session.username,
+            "keyRef": key_ref,
+        }),
+    );
+    store
+        .save()
+        .map_err(|e| format!("保存 OpenList 登录态失败: {}", e))
+}
+
+/// 服务器 Token 在凭据存储中的 `key_ref`。
+pub fn session_key_ref(base_url: &Url) -> String {
+    format!("openlist:{}", base_url)
+}
+
+/// 用账号密码登录，返回 Token。
+pub async fn login(
+    http: &reqwest::Client,
+    base_url: &Url,
+    username: &str,
+    password: &str,
+) -> Result<String, String> {
+    let url = base_url
+        .join("api/auth/login")
+        .map_err(|e| format!("拼接接口地址失败: {}", e))?;
+    let body = serde_json::json!({
+        "username": username,
+        "password": password,
+    });
+    let response = retry::send(&RetryPolicy::API, "/api/auth/login", || {
+        http.post(url.clone()).json(&body)
+    })
+    .await?;
+    let data: LoginData = read_response("/api/auth/login", response).await?;
+    data.token
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| "登录成功但未返回 Token，请检查 OpenList 版本".to_string())
+}
+
+fn read_session(app: &AppHandle) -> Result<Option<OpenlistSession>, String> {
+    let store = app
+        .store(SESSION_STORE)
+        .map_err(|e| format!("读取 OpenList 登录态失败: {}", e))?;
+    Ok(store
+        .get(SESSION_KEY)
+        .and_then(|value| serde_json::from_value(value).ok()))
+}
+
+/// 整理 OpenList 基础地址，仅保留协议与主机，逻辑对应前端 `normalizeOpenlistBaseUrl`。
+pub fn normalize_base_url(raw: &str) -> Result<Url, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("请填写网盘基础地址".to_string());
+    }
+
+    let lower = trimmed.to_ascii_lowercase();
+    let with_scheme = if lower.starts_with("http://") || lower.starts_with("https://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+
+    let mut url = Url::parse(&with_scheme).map_err(|e| format!("无法识别的地址: {}", e))?;
+    url.set_path("/");
+    url.set_query(None);
+    url.set_fragment(None);
+    Ok(url)
+}
+
+/// OpenList 接口客户端。
+pub struct OpenlistClient {
+    http: reqwest::Client,
+    base_url: Url,
+    token: String,
+}
+
+impl OpenlistClient {
+    pub fn new(http: reqwest::Client, base_url: &str, token: &str) -> Result<Self, String> {
+        let trimmed = token.trim();
+        let token = trimmed
+            .strip_prefix("Bearer ")
+            .or_else(|| trimmed.strip_prefix("bearer "))
+            .unwrap_or(trimmed)
+            .trim();
+        if token.is_empty() {
+            return Err("未提供 Token，请重新登录后重试".to_string());
+        }
+        Ok(Self {
+            http,
+            base_url: normalize_base_url(base_url)?,
+            token: token.to_string(),
+        })
+    }
+
+    /// 规范化后的服务器地址。
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// 共享的 HTTP 客户端，供直链下载复用。
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// 拼接接口地址。
+    pub fn url(&self, path: &str) -> Result<Url, String> {
+        self.base_url
+            .join(path.trim_start_matches('/'))
+            .map_err(|e| format!("拼接接口地址失败: {}", e))
+    }
+
+    /// 以 JSON 调用接口并校验业务状态码，返回 `data` 字段；
+    /// 只读接口的网络抖动按策略重试，删除、新建、改名等写操作只发送一次。
+    pub async fn post<T: DeserializeOwned + Default>(
+        &self,
+        path: &str,
+        body: &Value,
+    ) -> Result<T, String> {
+        let url = self.url(path)?;
+        let policy = if is_read_only(path) {
+            RetryPolicy::API
+        } else {
+            RetryPolicy::ONCE
+        };
+        let response = retry::send(&policy, path, || {
+            self.http
+                .post(url.clone())
+                .header(reqwest::header::AUTHORIZATION, &self.token)
+                .json(body)
+        })
+        .await?;
+
+        read_response(path, response).await
+    }
+
+    /// 获取单个文件详情，包含可直接下载的 `raw_url`。
+    pub async fn get(&self, path: &str, password: Option<&str>) -> Result<FsDetail, String> {
+        let body = serde_json::json!({
+            "path": path,
+            "password": password.unwrap_or_default(),
+        });
+        self.post("/api/fs/get", &body).await
+    }
+
+    /// 解析文件的下载地址：优先使用 `raw_url`，否则回退到带签名的 `/d` 代理地址。
+    pub fn download_url(&self, path: &str, detail: &FsDetail) -> Result<Url, String> {
+        if !detail.raw_url.is_empty() {
+            let raw = Url::parse(&detail.raw_url).or_else(|_| self.url(&detail.raw_url))?;
+            return Ok(raw);
+        }
+
+        let encoded: String = path
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, COMPONENT).to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut url = self.url(&format!("d/{}", encoded.trim_start_matches('/')))?;
+        if !detail.sign.is_empty() {
+            url.query_pairs_mut().append_pair("sign", &detail.sign);
+        }
+        Ok(url)
+    }
+
+    /// 以流式请求体上传文件到 `/api/fs/put`，`remote_path` 为网盘中的完整路径。
+    /// 请求体无法重放，失败时只标记是否可重试，由传输队列整体重新上传。
+    pub async fn put(&self, remote_path: &str, upload: PutRequest) -> Result<(), Failure> {
+        let mut request = self
+            .http
+            .put(self.url("/api/fs/put")?)
+            .header(reqwest::header::AUTHORIZATION, &self.token)
+            .header("File-Path", encode_path(remote_path))
+            .header("As-Task", upload.as_task.to_string())
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .header(reqwest::header::CONTENT_LENGTH, upload.size);
+        if let Some(modified) = upload.last_modified {
+            request = request.header("Last-Modified", modified.to_string());
+        }
+
+        let response = request
+            .body(upload.body)
+            .send()
+            .await
+            .map_err(|e| Failure::from_request(format!("请求上传接口失败: {}", e), &e))?;
+        let status = response.status();
+        if retry::is_retryable_status(status) {
+            let failure = Failure::from_status(
+                format!("/api/fs/put 请求失败: {}", status),
+                status,
+                response.headers(),
+            );
+            return Err(failure);
+        }
+        read_response::<Value>("/api/fs/put", response)
+            .await
+            .map(|_| ())
+            .map_err(Failure::fatal)
+    }
+
+    /// 删除目录下的多个文件或文件夹。
+    pub async fn remove(&self, dir: &str, names: &[String]) -> Result<(), String> {
+        let body = serde_json::json!({
+            "dir": if dir.is_empty() { "/" } else { dir },
+            "names": names,
+        });
+        self.post::<Value>("/api/fs/remove", &body)
+            .await
+            .map(|_| ())
+    }
+
+    /// 列出目录内容，支持分页、强制刷新与加密目录密码。
+    pub async fn list(&self, path: &str, options: &ListOptions) -> Result<FsList, String> {
+        let body = serde_json::json!({
+            "path": if path.is_empty() { "/" } else { path },
+            "password": options.password.clone().unwrap_or_default(),
+            "page": options.page.max(1),
+            "per_page": options.per_page,
+            "refresh": options.refresh,
+        });
+        self.post("/api/fs/list", &body).await
+    }
+}
+
+/// 查询类接口可以安全重发，其余接口（删除、新建、改名、移动等）重发可能重复执行。
+fn is_read_only(path: &str) -> bool {
+    matches!(
+        path,
+        "/api/fs/get" | "/api/fs/list" | "/api/fs/dirs" | "/api/fs/search" | "/api/me"
+    )
+}
+
+/// 上传请求的参数。
+pub struct PutRequest {
+    pub body: reqwest::Body,
+    pub size: u64,
+    /// 源文件修改时间（毫秒时间戳），OpenList 会据此保留原始时间。
+    pub last_modified: Option<u64>,
+    /// 是否交给服务端以后台任务方式转存。
+    pub as_task: bool,
+}
+
+/// 与 `encodeURIComponent` 一致的转义集合，保证中文路径可放入请求头。
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// 转义网盘路径，用于 `File-Path` 请求头。
+fn encode_path(path: &str) -> String {
+    utf8_percent_encode(path, COMPONENT).to_string()
+}
+
+/// 校验 HTTP 状态并解析业务响应。
+async fn read_response<T: DeserializeOwned + Default>(
+    path: &str,
+    response: reqwest::Response,
+) -> Result<T, String> {
+    let status = response.status();
+    let payload: Value = response.json().await.unwrap_or(Value::Null);
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(format!(
+            "登录已失效，请重新登录（{}）",
+            error_reason(&payload, status)
+        ));
+    }
+    if !status.is_success() {
+        return Err(format!(
+            "{} 请求失败: {}",
+            path,
+            error_reason(&payload, status)
+        ));
+    }
+
+    ensure_success(payload)
+}
+
+/// 从业务响应中提取失败原因。
+fn error_reason(payload: &Value, status: reqwest::StatusCode) -> String {
+    payload
+        .get("message")
+        .or_else(|| payload.get("error"))
+        .and_then(Value::as_str)
+        .filter(|reason| !reason.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| status.to_string())
+}
+
+/// 校验 OpenList 业务状态码，成功时取出 `data`。
+fn ensure_success<T: DeserializeOwned + Default>(mut payload: Value) -> Result<T, String> {
+    let code = payload.get("code").and_then(Value::as_i64).unwrap_or(200);
+    if code != 200 {
+        let reason = payload
+            .get("message")
+            .or_else(|| payload.get("error"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("OpenList 接口返回异常（{}）", code));
+        return Err(if code == 401 {
+            format!("登录已失效，请重新登录（{}）", reason)
+        } else {
+            reason
+        });
+    }
+
+    match payload.get_mut("data").map(Value::take) {
+        Some(Value::Null) | None => Ok(T::default()),
+        Some(data) => {
+            serde_json::from_value(data).map_err(|e| format!("解析 OpenList 响应失败: {}", e))
+        }
+    }
+}
+
+/// `/api/auth/login` 的返回数据。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct LoginData {
+    token: Option<String>,
+}
+
+/// `/api/fs/list` 的请求参数。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListOptions {
+    pub page: u32,
+    /// 每页条数，0 表示不分页。
+    pub per_page: u32,
+    /// 是否跳过服务端缓存重新拉取。
+    pub refresh: bool,
+    /// 加密目录的访问密码。
+    pub password: Option<String>,
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            per_page: 0,
+            refresh: false,
+            password: None,
+        }
+    }
+}
+
+/// `/api/fs/list` 的返回数据。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FsList {
+    #[serde(deserialize_with = "null_as_default")]
+    pub content: Vec<FsObject>,
+    pub total: u64,
+    pub write: bool,
+    pub provider: String,
+}
+
+/// OpenList 目录中的单个对象。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FsObject {
+    pub name: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub modified: String,
+    pub sign: String,
+    pub thumb: String,
+}
+
+/// 空目录时 OpenList 会返回 `content: null`，统一视为空列表。
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// `/api/fs/get` 的返回数据。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FsDetail {
+    pub name: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub sign: String,
+    pub raw_url: String,
+}
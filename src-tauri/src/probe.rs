@@ -0,0 +1,212 @@
+use crate::app_state::{ServerInfo, ServerKind};
+use crate::credentials::{Credential, CredentialKind};
+use crate::openlist;
+use crate::retry::{self, RetryPolicy};
+use crate::subsonic::{self, SubsonicAuth, SubsonicClient};
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+/// 鉴权失败相关的 Subsonic 错误码：40 用户名或密码错误、41 不支持 token、
+/// 42 不支持该鉴权方式、43 鉴权参数冲突、44 API Key 无效、50 无权限。
+const AUTH_ERROR_CODES: &[i64] = &[40, 41, 42, 43, 44, 50];
+
+/// 添加服务器时的错误，前端按 `kind` 区分提示。
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ServerError {
+    /// 地址格式错误。
+    InvalidUrl(String),
+    /// 网络不可达或服务器无响应。
+    Unreachable(String),
+    /// 用户名、密码或 API Key 错误。
+    Unauthorized(String),
+    /// 不是 Navidrome/Subsonic 或 OpenList 服务器。
+    Unsupported(String),
+    /// 找不到要更新的服务器配置。
+    NotFound(String),
+    /// 保存配置或读取凭据失败。
+    Storage(String),
+}
+
+impl From<String> for ServerError {
+    fn from(message: String) -> Self {
+        Self::Storage(message)
+    }
+}
+
+/// 探测结果：服务器类型、规范化后的地址与版本信息。
+pub struct ProbedServer {
+    pub kind: ServerKind,
+    pub base_url: Url,
+    pub info: ServerInfo,
+}
+
+/// 探测服务器并校验凭据；未指定类型时依次尝试 Subsonic 与 OpenList。
+pub async fn probe_server(
+    http: &reqwest::Client,
+    kind: Option<ServerKind>,
+    base_url: &str,
+    username: &str,
+    credential: Credential,
+) -> Result<ProbedServer, ServerError> {
+    match kind {
+        Some(ServerKind::Navidrome) => probe_subsonic(http, base_url, username, credential).await,
+        Some(ServerKind::Openlist) => probe_openlist(http, base_url, username, credential).await,
+        None => match probe_subsonic(http, base_url, username, credential.clone()).await {
+            Err(ServerError::Unsupported(_)) => {
+                probe_openlist(http, base_url, username, credential).await
+            }
+            result => result,
+        },
+    }
+}
+
+/// 调用 `ping` 识别 Subsonic 服务器并校验凭据，OpenSubsonic 服务器额外读取扩展列表。
+async fn probe_subsonic(
+    http: &reqwest::Client,
+    base_url: &str,
+    username: &str,
+    credential: Credential,
+) -> Result<ProbedServer, ServerError> {
+    let base_url = subsonic::normalize_base_url(base_url).map_err(ServerError::InvalidUrl)?;
+    let auth = SubsonicAuth::from_credential(username, credential);
+    let client = SubsonicClient::new(http.clone(), base_url.as_str(), auth)
+        .map_err(ServerError::Unauthorized)?;
+    let url = client
+        .endpoint("ping", &[])
+        .map_err(ServerError::Unauthorized)?;
+
+    let response = retry::send(&RetryPolicy::API, "ping", || http.get(url.clone()))
+        .await
+        .map_err(ServerError::Unreachable)?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(ServerError::Unauthorized(format!(
+            "服务器拒绝访问（{}）",
+            status
+        )));
+    }
+    if !status.is_success() {
+        return Err(ServerError::Unsupported(format!(
+            "不是 Subsonic 服务器（HTTP {}）",
+            status
+        )));
+    }
+    let mut body: Value = response
+        .json()
+        .await
+        .map_err(|_| ServerError::Unsupported("不是 Subsonic 服务器".to_string()))?;
+    let payload = body
+        .get_mut("subsonic-response")
+        .map(Value::take)
+        .ok_or_else(|| ServerError::Unsupported("不是 Subsonic 服务器".to_string()))?;
+
+    if payload.get("status").and_then(Value::as_str) != Some("ok") {
+        let error = payload.get("error");
+        let code = error.and_then(|e| e.get("code")).and_then(Value::as_i64);
+        let message = error
+            .and_then(|e| e.get("message"))
+            .and_then(Value::as_str)
+            .unwrap_or("Subsonic 接口返回异常");
+        let message = format!("{}（错误码 {}）", message, code.unwrap_or_default());
+        return Err(match code {
+            Some(code) if AUTH_ERROR_CODES.contains(&code) => ServerError::Unauthorized(message),
+            _ => ServerError::Unsupported(message),
+        });
+    }
+
+    let text = |key: &str| payload.get(key).and_then(Value::as_str).map(str::to_string);
+    let open_subsonic = payload
+        .get("openSubsonic")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let mut info = ServerInfo {
+        server_type: text("type").or_else(|| Some("subsonic".to_string())),
+        api_version: text("version"),
+        server_version: text("serverVersion"),
+        open_subsonic,
+        extensions: Vec::new(),
+    };
+    if open_subsonic {
+        // 扩展列表只用于功能判断，读取失败不影响添加
+        info.extensions = client
+            .get_open_subsonic_extensions()
+            .await
+            .unwrap_or_default();
+    }
+
+    Ok(ProbedServer {
+        kind: ServerKind::Navidrome,
+        base_url,
+        info,
+    })
+}
+
+/// 读取 `/api/public/settings` 识别 OpenList 服务器，再用账号密码登录校验凭据。
+async fn probe_openlist(
+    http: &reqwest::Client,
+    base_url: &str,
+    username: &str,
+    credential: Credential,
+) -> Result<ProbedServer, ServerError> {
+    let base_url = openlist::normalize_base_url(base_url).map_err(ServerError::InvalidUrl)?;
+    let endpoint = |path: &str| {
+        base_url
+            .join(path)
+            .map_err(|e| ServerError::InvalidUrl(format!("拼接接口地址失败: {}", e)))
+    };
+
+    let settings_url = endpoint("api/public/settings")?;
+    let response = retry::send(&RetryPolicy::API, "/api/public/settings", || {
+        http.get(settings_url.clone())
+    })
+    .await
+    .map_err(ServerError::Unreachable)?;
+    let unsupported = || ServerError::Unsupported("不是 Navidrome 或 OpenList 服务器".to_string());
+    if !response.status().is_success() {
+        return Err(unsupported());
+    }
+    let settings: Value = response.json().await.map_err(|_| unsupported())?;
+    if settings.get("code").and_then(Value::as_i64) != Some(200) {
+        return Err(unsupported());
+    }
+
+    if credential.kind != CredentialKind::Password {
+        return Err(ServerError::Unauthorized(
+            "OpenList 仅支持用户名密码登录".to_string(),
+        ));
+    }
+    let login_url = endpoint("api/auth/login")?;
+    let body = serde_json::json!({
+        "username": username,
+        "password": credential.secret,
+    });
+    let response = retry::send(&RetryPolicy::API, "/api/auth/login", || {
+        http.post(login_url.clone()).json(&body)
+    })
+    .await
+    .map_err(ServerError::Unreachable)?;
+    let login: Value = response.json().await.unwrap_or(Value::Null);
+    if login.get("code").and_then(Value::as_i64) != Some(200) {
+        let reason = login
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("用户名或密码错误");
+        return Err(ServerError::Unauthorized(reason.to_string()));
+    }
+
+    let server_version = settings
+        .pointer("/data/version")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Ok(ProbedServer {
+        kind: ServerKind::Openlist,
+        base_url,
+        info: ServerInfo {
+            server_type: Some("openlist".to_string()),
+            server_version,
+            ..ServerInfo::default()
+        },
+    })
+}
@@ -0,0 +1,208 @@
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::time::{Duration, SystemTime};
+
+/// 重试策略：最大尝试次数与指数退避参数。
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// 总尝试次数（含第一次）。
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// `Retry-After` 允许的最长等待，避免服务端给出过大的值导致调用长时间挂起。
+    pub max_retry_after: Duration,
+}
+
+impl RetryPolicy {
+    /// Subsonic/OpenList 接口调用：快速失败，避免界面长时间等待。
+    pub const API: Self = Self {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(5),
+        max_retry_after: Duration::from_secs(10),
+    };
+
+    /// 不重试：创建、修改、删除等非幂等调用重发可能重复执行，只发送一次。
+    pub const ONCE: Self = Self {
+        max_attempts: 1,
+        ..Self::API
+    };
+
+    /// 上传/下载任务：允许更多次数与更长的退避。
+    pub const TRANSFER: Self = Self {
+        max_attempts: 5,
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(60),
+        max_retry_after: Duration::from_secs(300),
+    };
+
+    /// 第 `attempt` 次失败后的等待时间：优先遵循 `Retry-After`，否则指数退避并加入随机抖动。
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(wait) = retry_after {
+            return wait.min(self.max_retry_after);
+        }
+        let exponent = attempt.saturating_sub(1).min(16);
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        // 等额抖动：一半固定、一半随机，避免多个任务同时重试
+        let half = backoff / 2;
+        let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter)
+    }
+
+    /// 是否还能再尝试一次。
+    pub fn allows(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+}
+
+/// 单次尝试的失败信息，携带是否可重试与服务端建议的等待时间。
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub message: String,
+    pub retryable: bool,
+    pub retry_after: Option<Duration>,
+}
+
+impl Failure {
+    /// 不可重试的失败（参数错误、鉴权失败等）。
+    pub fn fatal(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: false,
+            retry_after: None,
+        }
+    }
+
+    /// 可重试的失败（网络中断、超时等）。
+    pub fn transient(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retryable: true,
+            retry_after: None,
+        }
+    }
+
+    /// 根据 HTTP 状态码与响应头判断是否可重试。
+    pub fn from_status(
+        message: impl Into<String>,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            retryable: is_retryable_status(status),
+            retry_after: retry_after(headers),
+        }
+    }
+
+    /// 根据请求错误类型判断是否可重试。
+    pub fn from_request(message: impl Into<String>, error: &reqwest::Error) -> Self {
+        Self {
+            message: message.into(),
+            retryable: is_retryable_error(error),
+            retry_after: None,
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::fatal(message)
+    }
+}
+
+/// 请求发送失败，区分服务器不可达（连接失败或超时）与其他错误。
+#[derive(Debug, Clone)]
+pub struct SendError {
+    pub message: String,
+    pub unreachable: bool,
+}
+
+impl From<String> for SendError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            unreachable: false,
+        }
+    }
+}
+
+impl From<SendError> for String {
+    fn from(error: SendError) -> Self {
+        error.message
+    }
+}
+
+/// 反向代理抖动、限流与网关超时等可重试的状态码。
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// 连接失败、超时与传输中断可重试；构造请求、重定向或解析响应的错误不可重试。
+pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.is_body()
+}
+
+/// 解析 `Retry-After`，支持秒数与 HTTP 日期两种格式。
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// 发送可重放的请求：网络错误与可重试状态码按策略退避后重发，
+/// 返回第一个不需要重试的响应（或最后一次的响应），由调用方继续解析。
+pub async fn send(
+    policy: &RetryPolicy,
+    label: &str,
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, String> {
+    try_send(policy, label, build).await.map_err(String::from)
+}
+
+/// 与 [`send`] 相同，失败时注明服务器是否不可达，调用方可据此决定是否改用本地数据。
+pub async fn try_send(
+    policy: &RetryPolicy,
+    label: &str,
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, SendError> {
+    let mut attempt = 1;
+    loop {
+        let failure = match build().send().await {
+            Ok(response) => {
+                let status = response.status();
+                if !is_retryable_status(status) || !policy.allows(attempt) {
+                    return Ok(response);
+                }
+                Failure::from_status(status.to_string(), status, response.headers())
+            }
+            Err(e) => {
+                let failure = Failure::from_request(format!("请求 {} 失败: {}", label, e), &e);
+                if !failure.retryable || !policy.allows(attempt) {
+                    return Err(SendError {
+                        message: failure.message,
+                        unreachable: e.is_connect() || e.is_timeout(),
+                    });
+                }
+                failure
+            }
+        };
+        tokio::time::sleep(policy.delay(attempt, failure.retry_after)).await;
+        attempt += 1;
+    }
+}
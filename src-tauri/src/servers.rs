@@ -0,0 +1,104 @@
+use crate::app_state::{ServerConfig, ServerKind};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+/// 按保存时的顺序读取全部服务器配置。
+pub async fn load_servers(pool: &SqlitePool) -> Result<Vec<ServerConfig>, String> {
+    let rows = sqlx::query(
+        "SELECT id, kind, name, base_url, username, key_ref, is_default, info
+         FROM servers ORDER BY position",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("读取服务器配置失败: {}", e))?;
+
+    let servers = rows
+        .into_iter()
+        .map(|row| ServerConfig {
+            id: row.get("id"),
+            kind: parse_kind(row.get("kind")),
+            name: row.get("name"),
+            base_url: row.get("base_url"),
+            username: row.get("username"),
+            key_ref: row.get("key_ref"),
+            is_default: row.get("is_default"),
+            info: serde_json::from_str(row.get("info")).unwrap_or_default(),
+        })
+        .collect();
+    Ok(servers)
+}
+
+/// 整体覆盖保存服务器列表，列表下标即排序位置。
+pub async fn save_servers(pool: &SqlitePool, servers: &[ServerConfig]) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("保存服务器配置失败: {}", e))?;
+    sqlx::query("DELETE FROM servers")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("保存服务器配置失败: {}", e))?;
+    for (position, server) in servers.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO servers (id, kind, name, base_url, username, key_ref, is_default, info,
+                                  position)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&server.id)
+        .bind(kind_str(server.kind))
+        .bind(&server.name)
+        .bind(&server.base_url)
+        .bind(&server.username)
+        .bind(&server.key_ref)
+        .bind(server.is_default)
+        .bind(serde_json::to_string(&server.info).unwrap_or_else(|_| "{}".to_string()))
+        .bind(position as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("保存服务器配置失败: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("保存服务器配置失败: {}", e))
+}
+
+/// 保证每类服务器恰好有一个默认项：缺失时取该类的第一个。
+pub fn ensure_default(servers: &mut [ServerConfig]) {
+    for kind in [ServerKind::Navidrome, ServerKind::Openlist] {
+        let has_default = servers
+            .iter()
+            .any(|server| server.kind == kind && server.is_default);
+        if !has_default {
+            if let Some(first) = servers.iter_mut().find(|server| server.kind == kind) {
+                first.is_default = true;
+            }
+        }
+    }
+}
+
+/// 将指定服务器设为同类中的默认项。
+pub fn set_default(servers: &mut [ServerConfig], id: &str) -> Result<(), String> {
+    let kind = servers
+        .iter()
+        .find(|server| server.id == id)
+        .map(|server| server.kind)
+        .ok_or_else(|| format!("未找到服务器配置: {}", id))?;
+    for server in servers.iter_mut().filter(|server| server.kind == kind) {
+        server.is_default = server.id == id;
+    }
+    Ok(())
+}
+
+fn kind_str(kind: ServerKind) -> &'static str {
+    match kind {
+        ServerKind::Navidrome => "navidrome",
+        ServerKind::Openlist => "openlist",
+    }
+}
+
+fn parse_kind(value: &str) -> ServerKind {
+    match value {
+        "openlist" => ServerKind::Openlist,
+        _ => ServerKind::Navidrome,
+    }
+}
@@ -0,0 +1,130 @@
+//! 拖拽上传的暂存区。
+//!
+//! webview 拿不到拖入文件的本地路径，前端把文件按块以二进制写入暂存目录，
+//! 之后的标签处理与上传都只传暂存文件的路径。启动时只保留传输队列仍在引用的暂存文件。
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tokio::io::AsyncWriteExt;
+
+/// 暂存目录，位于应用缓存目录。
+const STAGING_DIR: &str = "staging";
+
+/// 新建的暂存文件，`path` 可直接交给标签与上传命令。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StagedFile {
+    pub id: String,
+    pub path: String,
+}
+
+/// 以 ID 索引的暂存文件，每个文件放在以 ID 命名的子目录中以保留原文件名。
+#[derive(Default)]
+pub struct Staging {
+    root: OnceLock<PathBuf>,
+    files: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl Staging {
+    /// 创建暂存目录，上次遗留的文件由 `retain` 在恢复传输队列后清理。
+    pub fn init(&self, cache_dir: &Path) -> Result<(), String> {
+        let root = cache_dir.join(STAGING_DIR);
+        std::fs::create_dir_all(&root).map_err(|e| format!("创建暂存目录失败: {}", e))?;
+        self.root
+            .set(root)
+            .map_err(|_| "暂存区已初始化".to_string())
+    }
+
+    /// 为拖入的文件创建空的暂存文件。
+    pub fn create(&self, file_name: &str) -> Result<StagedFile, String> {
+        let root = self.root.get().ok_or("暂存区尚未初始化")?;
+        // 只取文件名部分，避免写到暂存目录之外
+        let name = Path::new(file_name)
+            .file_name()
+            .filter(|name| !name.is_empty())
+            .ok_or("文件名无效")?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let dir = root.join(&id);
+        std::fs::create_dir_all(&dir).map_err(|e| format!("创建暂存目录失败: {}", e))?;
+        let path = dir.join(name);
+        std::fs::File::create(&path).map_err(|e| format!("创建暂存文件失败: {}", e))?;
+        self.files
+            .lock()
+            .expect("暂存区锁定失败")
+            .insert(id.clone(), path.clone());
+        Ok(StagedFile {
+            id,
+            path: path.to_string_lossy().to_string(),
+        })
+    }
+
+    /// 把一块数据追加到暂存文件末尾，返回当前文件长度。
+    pub async fn append(&self, id: &str, data: &[u8]) -> Result<u64, String> {
+        let path = self.path(id)?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .map_err(|e| format!("打开暂存文件失败: {}", e))?;
+        file.write_all(data)
+            .await
+            .map_err(|e| format!("写入暂存文件失败: {}", e))?;
+        file.flush()
+            .await
+            .map_err(|e| format!("写入暂存文件失败: {}", e))?;
+        let metadata = file
+            .metadata()
+            .await
+            .map_err(|e| format!("读取暂存文件失败: {}", e))?;
+        Ok(metadata.len())
+    }
+
+    /// 删除暂存文件及其目录，ID 不存在时忽略。
+    pub fn discard(&self, id: &str) {
+        let removed = self.files.lock().expect("暂存区锁定失败").remove(id);
+        if let Some(dir) = removed.as_deref().and_then(Path::parent) {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    /// 保留传输队列仍在引用的暂存文件并重新登记，删除其余遗留文件。
+    pub fn retain(&self, referenced: &HashSet<PathBuf>) {
+        let Some(root) = self.root.get() else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(root) else {
+            return;
+        };
+        let mut files = self.files.lock().expect("暂存区锁定失败");
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let kept = std::fs::read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|file| file.path())
+                .find(|path| referenced.contains(path));
+            match (kept, entry.file_name().to_str()) {
+                (Some(path), Some(id)) => {
+                    files.insert(id.to_string(), path);
+                }
+                _ if dir.is_dir() => {
+                    let _ = std::fs::remove_dir_all(&dir);
+                }
+                _ => {
+                    let _ = std::fs::remove_file(&dir);
+                }
+            }
+        }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        self.files
+            .lock()
+            .expect("暂存区锁定失败")
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("暂存文件不存在: {}", id))
+    }
+}
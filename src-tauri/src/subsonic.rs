@@ -0,0 +1,674 @@
+use crate::credentials::{Credential, CredentialKind};
+use crate::retry::{self, RetryPolicy, SendError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+/// 与前端 `buildSubsonicTokenParams` 保持一致的协议版本与客户端标识。
+pub const API_VERSION: &str = "1.16.1";
+pub const CLIENT_NAME: &str = "musicboom";
+
+/// Subsonic 鉴权信息：可直接携带 token/salt，也可由密码即时生成，
+/// 或使用 OpenSubsonic 的 API Key。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubsonicAuth {
+    pub username: String,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    pub salt: Option<String>,
+    pub api_key: Option<String>,
+}
+
+impl SubsonicAuth {
+    /// 由服务器用户名与 `key_ref` 对应的凭据构造鉴权信息。
+    pub fn from_credential(username: &str, credential: Credential) -> Self {
+        let mut auth = Self {
+            username: username.to_string(),
+            ..Self::default()
+        };
+        match credential.kind {
+            CredentialKind::Password => auth.password = Some(credential.secret),
+            CredentialKind::ApiKey => auth.api_key = Some(credential.secret),
+        }
+        auth
+    }
+
+    /// 是否使用 API Key 鉴权。
+    fn api_key(&self) -> Option<&str> {
+        self.api_key
+            .as_deref()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+    }
+
+    /// 解析出本次请求使用的 token 与 salt，逻辑对应前端 `resolveSubsonicAuth`。
+    fn token_pair(&self) -> Result<(String, String), String> {
+        let token = self.token.as_deref().map(str::trim).unwrap_or_default();
+        let salt = self.salt.as_deref().map(str::trim).unwrap_or_default();
+        if !token.is_empty() && !salt.is_empty() {
+            return Ok((token.to_string(), salt.to_string()));
+        }
+
+        match self.password.as_deref().map(str::trim) {
+            Some(password) if !password.is_empty() => Ok(build_token(password)),
+            _ => Err("缺少 Subsonic 凭据，请重新登录".to_string()),
+        }
+    }
+}
+
+/// 生成随机盐并计算 md5(password + salt)。
+pub fn build_token(password: &str) -> (String, String) {
+    let salt = generate_salt();
+    let token = format!("{:x}", md5::compute(format!("{}{}", password, salt)));
+    (token, salt)
+}
+
+/// 生成 8 字节的十六进制随机盐。
+fn generate_salt() -> String {
+    let bytes: [u8; 8] = rand::random();
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// 规范化基础地址：缺少协议时补齐 https，并保证路径以 / 结尾，避免丢失反向代理前缀。
+pub fn normalize_base_url(raw: &str) -> Result<Url, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("缺少 Navidrome 基础地址".to_string());
+    }
+
+    let with_scheme = if has_scheme(trimmed) {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+
+    let mut base = Url::parse(&with_scheme).map_err(|e| format!("无效的服务器地址: {}", e))?;
+    if !base.path().ends_with('/') {
+        let path = format!("{}/", base.path());
+        base.set_path(&path);
+    }
+    Ok(base)
+}
+
+/// 判断地址是否已携带协议头（形如 `scheme://`）。
+fn has_scheme(value: &str) -> bool {
+    match value.split_once("://") {
+        Some((scheme, _)) => {
+            let mut chars = scheme.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+        }
+        None => false,
+    }
+}
+
+/// Subsonic/OpenSubsonic 接口客户端。
+pub struct SubsonicClient {
+    http: reqwest::Client,
+    base_url: Url,
+    auth: SubsonicAuth,
+}
+
+impl SubsonicClient {
+    pub fn new(http: reqwest::Client, base_url: &str, auth: SubsonicAuth) -> Result<Self, String> {
+        if auth.api_key().is_none() && auth.username.trim().is_empty() {
+            return Err("缺少 Subsonic 用户名".to_string());
+        }
+        Ok(Self {
+            http,
+            base_url: normalize_base_url(base_url)?,
+            auth,
+        })
+    }
+
+    /// 构造带鉴权参数的接口地址，每次调用都会生成新的 salt。
+    pub fn endpoint(&self, method: &str, params: &[(&str, String)]) -> Result<Url, String> {
+        let mut url = self
+            .base_url
+            .join(&format!("rest/{}", method))
+            .map_err(|e| format!("拼接接口地址失败: {}", e))?;
+        {
+            let mut query = url.query_pairs_mut();
+            match self.auth.api_key() {
+                Some(api_key) => {
+                    query.append_pair("apiKey", api_key);
+                }
+                None => {
+                    let (token, salt) = self.auth.token_pair()?;
+                    query
+                        .append_pair("u", self.auth.username.trim())
+                        .append_pair("t", &token)
+                        .append_pair("s", &salt);
+                }
+            }
+            query
+                .append_pair("v", API_VERSION)
+                .append_pair("c", CLIENT_NAME)
+                .append_pair("f", "json");
+            for (key, value) in params {
+                query.append_pair(key, value);
+            }
+        }
+        Ok(url)
+    }
+
+    /// 调用接口并取出 `subsonic-response` 中的指定字段，字段缺失时返回错误。
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+        field: &str,
+    ) -> Result<T, String> {
+        self.fetch(method, params, field)
+            .await?
+            .ok_or_else(|| format!("{} 响应缺少 {} 字段", method, field))
+    }
+
+    /// 与 `get` 相同，但字段缺失时返回默认值，用于没有数据时可能省略字段的列表与歌词接口。
+    pub async fn get_or_default<T: DeserializeOwned + Default>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+        field: &str,
+    ) -> Result<T, String> {
+        Ok(self.fetch(method, params, field).await?.unwrap_or_default())
+    }
+
+    /// 取出指定字段，字段缺失时为空；失败时注明服务器是否不可达。
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+        field: &str,
+    ) -> Result<Option<T>, SendError> {
+        let mut payload = self.request(method, params).await?;
+        payload
+            .get_mut(field)
+            .map(Value::take)
+            .map(|value| {
+                serde_json::from_value(value)
+                    .map_err(|e| format!("解析 {} 响应失败: {}", method, e).into())
+            })
+            .transpose()
+    }
+
+    /// 调用接口并校验业务状态，返回 `subsonic-response` 对象本身；
+    /// 只读接口的网络错误与网关错误按策略重试，写操作只发送一次，业务错误（如密码错误）直接返回。
+    pub async fn call(&self, method: &str, params: &[(&str, String)]) -> Result<Value, String> {
+        self.request(method, params).await.map_err(String::from)
+    }
+
+    /// 与 `call` 相同，失败时注明服务器是否不可达。
+    async fn request(&self, method: &str, params: &[(&str, String)]) -> Result<Value, SendError> {
+        let url = self.endpoint(method, params)?;
+        let policy = if is_read_only(method) {
+            RetryPolicy::API
+        } else {
+            RetryPolicy::ONCE
+        };
+        let response = retry::try_send(&policy, method, || self.http.get(url.clone())).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("{} 返回 HTTP {}", method, status).into());
+        }
+
+        let mut body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("解析 {} 响应失败: {}", method, e))?;
+        let payload = body
+            .get_mut("subsonic-response")
+            .map(Value::take)
+            .ok_or_else(|| format!("{} 响应缺少 subsonic-response 字段", method))?;
+
+        if payload.get("status").and_then(Value::as_str) != Some("ok") {
+            let error = payload.get("error");
+            let code = error.and_then(|e| e.get("code")).and_then(Value::as_i64);
+            let message = error
+                .and_then(|e| e.get("message"))
+                .and_then(Value::as_str)
+                .unwrap_or("Subsonic 接口返回异常");
+            return Err(match code {
+                Some(code) => format!("{}（错误码 {}）", message, code),
+                None => message.to_string(),
+            }
+            .into());
+        }
+
+        Ok(payload)
+    }
+
+    /// 读取 OpenSubsonic 扩展列表，仅在 `ping` 返回 `openSubsonic: true` 时可用。
+    pub async fn get_open_subsonic_extensions(&self) -> Result<Vec<OpenSubsonicExtension>, String> {
+        self.get_or_default("getOpenSubsonicExtensions", &[], "openSubsonicExtensions")
+            .await
+    }
+
+    /// 调用 search3，按艺人、专辑、歌曲分别分页；失败时注明服务器是否不可达，供离线搜索判断。
+    pub async fn search3(
+        &self,
+        query: &str,
+        paging: &SearchPaging,
+    ) -> Result<SearchResult3, SendError> {
+        let params = [
+            ("query", query.to_string()),
+            ("artistCount", paging.artist_count.to_string()),
+            ("artistOffset", paging.artist_offset.to_string()),
+            ("albumCount", paging.album_count.to_string()),
+            ("albumOffset", paging.album_offset.to_string()),
+            ("songCount", paging.song_count.to_string()),
+            ("songOffset", paging.song_offset.to_string()),
+        ];
+        Ok(self
+            .fetch("search3", &params, "searchResult3")
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// 获取按索引分组的全部艺人（ID3 模式），展开为列表。
+    pub async fn get_artists(&self) -> Result<Vec<Artist>, String> {
+        let artists: ArtistsId3 = self.get_or_default("getArtists", &[], "artists").await?;
+        Ok(artists
+            .index
+            .into_iter()
+            .flat_map(|index| index.artist)
+            .collect())
+    }
+
+    /// 按指定排序分页获取专辑列表（ID3 模式），单页最多 500 条。
+    pub async fn get_album_list2(
+        &self,
+        list_type: &str,
+        size: u32,
+        offset: u32,
+    ) -> Result<Vec<Album>, String> {
+        let params = [
+            ("type", list_type.to_string()),
+            ("size", size.to_string()),
+            ("offset", offset.to_string()),
+        ];
+        let list: AlbumList2 = self
+            .get_or_default("getAlbumList2", &params, "albumList2")
+            .await?;
+        Ok(list.album)
+    }
+
+    /// 读取曲库最近一次修改的时间（毫秒时间戳）；传入 `if_modified_since` 时，
+    /// 未变化的服务器只返回时间戳而不返回索引内容。
+    pub async fn get_indexes_last_modified(
+        &self,
+        if_modified_since: Option<i64>,
+    ) -> Result<i64, String> {
+        let params: Vec<(&str, String)> = if_modified_since
+            .map(|since| ("ifModifiedSince", since.to_string()))
+            .into_iter()
+            .collect();
+        let indexes: Indexes = self
+            .get_or_default("getIndexes", &params, "indexes")
+            .await?;
+        Ok(indexes.last_modified)
+    }
+
+    /// 获取专辑内的全部歌曲。
+    pub async fn get_album_songs(&self, id: &str) -> Result<Vec<Song>, String> {
+        let album: AlbumSongs = self
+            .get("getAlbum", &[("id", id.to_string())], "album")
+            .await?;
+        Ok(album.song)
+    }
+
+    /// 按歌曲 ID 获取歌词（OpenSubsonic `songLyrics` 扩展），优先返回带时间轴的版本并转为 LRC。
+    pub async fn get_lyrics_by_song_id(&self, id: &str) -> Result<Option<String>, String> {
+        let list: LyricsList = self
+            .get_or_default("getLyricsBySongId", &[("id", id.to_string())], "lyricsList")
+            .await?;
+        let lyrics = list
+            .structured_lyrics
+            .iter()
+            .find(|lyrics| lyrics.synced)
+            .or_else(|| list.structured_lyrics.first());
+        Ok(lyrics.map(StructuredLyrics::to_text))
+    }
+
+    /// 按艺人与标题获取纯文本歌词（Subsonic 原生接口）。
+    pub async fn get_lyrics(&self, artist: &str, title: &str) -> Result<Option<String>, String> {
+        let lyrics: Lyrics = self
+            .get_or_default(
+                "getLyrics",
+                &[("artist", artist.to_string()), ("title", title.to_string())],
+                "lyrics",
+            )
+            .await?;
+        Ok(lyrics.value.filter(|value| !value.trim().is_empty()))
+    }
+
+    /// 获取全部流派及其歌曲、专辑数量。
+    pub async fn get_genres(&self) -> Result<Vec<Genre>, String> {
+        let genres: GenreList = self.get_or_default("getGenres", &[], "genres").await?;
+        Ok(genres.genre)
+    }
+
+    /// 获取当前用户可见的全部歌单（不含曲目）。
+    pub async fn get_playlists(&self) -> Result<Vec<Playlist>, String> {
+        let list: PlaylistList = self
+            .get_or_default("getPlaylists", &[], "playlists")
+            .await?;
+        Ok(list.playlist)
+    }
+
+    /// 获取歌单详情及曲目。
+    pub async fn get_playlist(&self, id: &str) -> Result<Playlist, String> {
+        self.get("getPlaylist", &[("id", id.to_string())], "playlist")
+            .await
+    }
+
+    /// 创建歌单，Subsonic 1.14 起会返回新歌单详情，更早的服务器不返回时报错。
+    pub async fn create_playlist(
+        &self,
+        name: &str,
+        song_ids: &[String],
+    ) -> Result<Playlist, String> {
+        let mut params = vec![("name", name.to_string())];
+        params.extend(song_ids.iter().map(|id| ("songId", id.clone())));
+        self.get("createPlaylist", &params, "playlist").await
+    }
+
+    /// 更新歌单：改名、备注、公开状态，以及按 id 追加、按下标移除曲目。
+    pub async fn update_playlist(&self, id: &str, update: &PlaylistUpdate) -> Result<(), String> {
+        let mut params = vec![("playlistId", id.to_string())];
+        if let Some(name) = &update.name {
+            params.push(("name", name.clone()));
+        }
+        if let Some(comment) = &update.comment {
+            params.push(("comment", comment.clone()));
+        }
+        if let Some(public) = update.public {
+            params.push(("public", public.to_string()));
+        }
+        params.extend(
+            update
+                .song_ids_to_add
+                .iter()
+                .map(|song_id| ("songIdToAdd", song_id.clone())),
+        );
+        params.extend(
+            update
+                .song_indexes_to_remove
+                .iter()
+                .map(|index| ("songIndexToRemove", index.to_string())),
+        );
+        self.call("updatePlaylist", &params).await.map(|_| ())
+    }
+
+    /// 删除歌单。
+    pub async fn delete_playlist(&self, id: &str) -> Result<(), String> {
+        self.call("deletePlaylist", &[("id", id.to_string())])
+            .await
+            .map(|_| ())
+    }
+
+    /// 生成可直接交给播放器的 stream 地址，鉴权参数使用新的 salt。
+    pub fn stream_url(&self, track_id: &str, options: &StreamOptions) -> Result<Url, String> {
+        let mut params = vec![("id", track_id.to_string())];
+        if let Some(max_bit_rate) = options.max_bit_rate {
+            params.push(("maxBitRate", max_bit_rate.to_string()));
+        }
+        if let Some(format) = options.format.as_deref().filter(|f| !f.is_empty()) {
+            params.push(("format", format.to_string()));
+        }
+        if let Some(time_offset) = options.time_offset {
+            params.push(("timeOffset", time_offset.to_string()));
+        }
+        self.endpoint("stream", &params)
+    }
+}
+
+/// 只读接口（get*、search*、ping）可以安全重发；createPlaylist 等写操作不能。
+fn is_read_only(method: &str) -> bool {
+    method.starts_with("get") || method.starts_with("search") || method == "ping"
+}
+
+/// stream 接口的转码与定位参数。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StreamOptions {
+    /// 最大码率（kbps），0 表示不限制。
+    pub max_bit_rate: Option<u32>,
+    /// 目标格式，例如 `mp3`、`opus` 或 `raw`。
+    pub format: Option<String>,
+    /// 起播偏移（秒），仅在服务器转码时生效。
+    pub time_offset: Option<u32>,
+}
+
+/// search3 的分页参数，未传入的字段沿用 Subsonic 默认值。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchPaging {
+    pub artist_count: u32,
+    pub artist_offset: u32,
+    pub album_count: u32,
+    pub album_offset: u32,
+    pub song_count: u32,
+    pub song_offset: u32,
+}
+
+impl Default for SearchPaging {
+    fn default() -> Self {
+        Self {
+            artist_count: 20,
+            artist_offset: 0,
+            album_count: 20,
+            album_offset: 0,
+            song_count: 20,
+            song_offset: 0,
+        }
+    }
+}
+
+/// search3 返回的原始结果。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchResult3 {
+    pub artist: Vec<Artist>,
+    pub album: Vec<Album>,
+    pub song: Vec<Song>,
+}
+
+/// Subsonic 艺人条目。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Artist {
+    pub id: String,
+    pub name: String,
+    pub cover_art: Option<String>,
+    pub album_count: Option<u32>,
+    /// OpenSubsonic 的排序名。
+    pub sort_name: Option<String>,
+}
+
+/// getArtists 的外层包装。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ArtistsId3 {
+    index: Vec<ArtistIndex>,
+}
+
+/// getArtists 中按首字母分组的艺人。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ArtistIndex {
+    artist: Vec<Artist>,
+}
+
+/// Subsonic 专辑条目。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub artist: Option<String>,
+    pub artist_id: Option<String>,
+    pub cover_art: Option<String>,
+    pub song_count: Option<u32>,
+    pub duration: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    pub created: Option<String>,
+    /// 专辑最近一次变更的时间（Navidrome 扩展），用于增量同步。
+    pub changed: Option<String>,
+    pub sort_name: Option<String>,
+}
+
+/// getAlbum 返回的专辑详情，只取其中的歌曲。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct AlbumSongs {
+    song: Vec<Song>,
+}
+
+/// getIndexes 的返回数据，只关心最近修改时间。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Indexes {
+    last_modified: i64,
+}
+
+/// getAlbumList2 的外层包装。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct AlbumList2 {
+    album: Vec<Album>,
+}
+
+/// Subsonic 歌曲条目。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Song {
+    pub id: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_id: Option<String>,
+    pub cover_art: Option<String>,
+    pub duration: Option<u32>,
+    pub size: Option<u64>,
+    pub artist_id: Option<String>,
+    pub track: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    pub suffix: Option<String>,
+    pub bit_rate: Option<u32>,
+    pub sort_name: Option<String>,
+}
+
+/// getGenres 的外层包装。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct GenreList {
+    genre: Vec<Genre>,
+}
+
+/// Subsonic 流派条目，名称位于 `value` 字段。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Genre {
+    pub value: String,
+    pub song_count: Option<u32>,
+    pub album_count: Option<u32>,
+}
+
+/// getLyrics 返回的歌词。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Lyrics {
+    value: Option<String>,
+}
+
+/// getLyricsBySongId 的外层包装。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct LyricsList {
+    structured_lyrics: Vec<StructuredLyrics>,
+}
+
+/// OpenSubsonic 结构化歌词，`start` 为毫秒（仅同步歌词有值）。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct StructuredLyrics {
+    synced: bool,
+    line: Vec<LyricsLine>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct LyricsLine {
+    start: Option<u64>,
+    value: String,
+}
+
+impl StructuredLyrics {
+    /// 同步歌词输出为 LRC 格式，否则逐行拼接。
+    fn to_text(&self) -> String {
+        self.line
+            .iter()
+            .map(|line| match (self.synced, line.start) {
+                (true, Some(start)) => format!(
+                    "[{:02}:{:02}.{:02}]{}",
+                    start / 60_000,
+                    start / 1000 % 60,
+                    start % 1000 / 10,
+                    line.value
+                ),
+                _ => line.value.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// getPlaylists 的外层包装。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct PlaylistList {
+    playlist: Vec<Playlist>,
+}
+
+/// Subsonic 歌单条目，`entry` 仅在 getPlaylist 中返回。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: String,
+    pub name: String,
+    pub comment: Option<String>,
+    pub owner: Option<String>,
+    pub public: Option<bool>,
+    pub song_count: Option<u32>,
+    pub duration: Option<u32>,
+    pub cover_art: Option<String>,
+    pub changed: Option<String>,
+    pub entry: Vec<Song>,
+}
+
+/// updatePlaylist 的可选修改项，未传入的字段保持不变。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PlaylistUpdate {
+    pub name: Option<String>,
+    pub comment: Option<String>,
+    pub public: Option<bool>,
+    pub song_ids_to_add: Vec<String>,
+    /// 需要移除的曲目下标（基于更新前的顺序）。
+    pub song_indexes_to_remove: Vec<u32>,
+}
+
+/// OpenSubsonic 扩展及其支持的版本。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenSubsonicExtension {
+    pub name: String,
+    pub versions: Vec<i64>,
+}
@@ -0,0 +1,557 @@
+//! 应用锚点 ID 的读写。
+//!
+//! 锚点写在各格式专用的字段中，不再占用用户的注释：ID3v2 为 `TXXX:APP_ANCHOR_ID`，
+//! Vorbis 注释与 APEv2 为 `APP_ANCHOR_ID`，MP4 为 `----:com.musicboom:anchor`。
+//! WAV/AIFF 的 RIFF INFO 只有固定字段，锚点写在同一容器内的 ID3 块中。
+//! 旧版本写在注释里的 `APP_ANCHOR_ID:<id>` 仍可识别，并可按需迁移到专用字段。
+
+use super::ape::ApeTag;
+use super::{ape, dsf, id3v1, ogg};
+use id3::TagLike;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// ID3v2 `TXXX` 描述与 Vorbis 注释字段名。
+pub const ANCHOR_KEY: &str = "APP_ANCHOR_ID";
+/// 旧版本写在注释中的前缀。
+const LEGACY_PREFIX: &str = "APP_ANCHOR_ID:";
+const MP4_MEAN: &str = "com.musicboom";
+const MP4_NAME: &str = "anchor";
+/// Vorbis 注释中可能存放旧版锚点的字段。
+const VORBIS_COMMENT_KEYS: &[&str] = &["COMMENT", "DESCRIPTION"];
+const APE_COMMENT_KEY: &str = "Comment";
+
+/// 设置中的标签类型开关，决定 MP3/AAC 写入哪些标签，以及 WAV、AIFF、DSF、APE、WavPack
+/// 能否写入锚点。FLAC、MP4 与 Ogg 使用各自的原生标签，不受开关影响。
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TagOptions {
+    pub enable_apev2: bool,
+    pub enable_id3v1: bool,
+    pub enable_id3v2: bool,
+    /// 写入的 ID3v2 版本，为空时沿用文件原有的版本，新标签为 2.4。
+    pub id3v2_version: Option<Id3v2Version>,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        Self {
+            enable_apev2: false,
+            enable_id3v1: false,
+            enable_id3v2: true,
+            id3v2_version: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Id3v2Version {
+    #[serde(rename = "2.3")]
+    V23,
+    #[serde(rename = "2.4")]
+    V24,
+}
+
+impl TagOptions {
+    fn id3_version(&self, tag: &id3::Tag) -> id3::Version {
+        match self.id3v2_version {
+            Some(Id3v2Version::V23) => id3::Version::Id3v23,
+            Some(Id3v2Version::V24) => id3::Version::Id3v24,
+            None => tag.version(),
+        }
+    }
+}
+
+/// 文件中的锚点。
+#[derive(Debug, Clone, Serialize)]
+pub struct AnchorInfo {
+    pub path: String,
+    pub anchor_id: Option<String>,
+    /// 锚点只存在于旧版注释中，尚未迁移到专用字段。
+    pub legacy: bool,
+}
+
+/// 支持写入锚点的标签格式。
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Mpeg,
+    /// ADTS 封装的 AAC。
+    Aac,
+    /// WAV 与 AIFF，ID3 标签作为块存放在容器中。
+    Chunked,
+    Dsf,
+    Flac,
+    Mp4,
+    /// Ogg Vorbis 与 Opus。
+    Ogg,
+    /// Monkey's Audio 与 WavPack。
+    Ape,
+}
+
+impl Format {
+    fn of(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("mp3") => Ok(Self::Mpeg),
+            Some("aac") => Ok(Self::Aac),
+            Some("wav") | Some("aif") | Some("aiff") => Ok(Self::Chunked),
+            Some("dsf") => Ok(Self::Dsf),
+            Some("flac") => Ok(Self::Flac),
+            Some("m4a") | Some("mp4") => Ok(Self::Mp4),
+            Some("ogg") | Some("oga") | Some("opus") => Ok(Self::Ogg),
+            Some("ape") | Some("wv") => Ok(Self::Ape),
+            _ => Err(
+                "不支持的音频格式，目前支持FLAC、MP3、M4A、OGG、OPUS、WAV、AIFF、AAC、APE、WV、DSF"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// 专用字段与注释中找到的锚点。
+struct Found {
+    dedicated: Option<String>,
+    legacy: Option<String>,
+}
+
+impl Found {
+    /// 合并同一文件中两种标签的结果，前者优先。
+    fn or(self, other: Found) -> Found {
+        Found {
+            dedicated: self.dedicated.or(other.dedicated),
+            legacy: self.legacy.or(other.legacy),
+        }
+    }
+}
+
+/// 读取锚点，专用字段优先。
+pub fn read(path: &Path) -> Result<AnchorInfo, String> {
+    let found = match Format::of(path)? {
+        Format::Mpeg | Format::Aac => found_id3(&load_id3(path)?).or(found_ape(&ape::read(path)?)),
+        Format::Chunked => found_id3(&load_id3(path)?),
+        Format::Dsf => found_id3(&dsf::read(path)?),
+        Format::Flac => read_flac(path)?,
+        Format::Mp4 => read_mp4(path)?,
+        Format::Ogg => read_ogg(path)?,
+        Format::Ape => found_ape(&ape::read(path)?),
+    };
+    let legacy = found.dedicated.is_none() && found.legacy.is_some();
+    Ok(AnchorInfo {
+        path: path.to_string_lossy().to_string(),
+        anchor_id: found.dedicated.or(found.legacy),
+        legacy,
+    })
+}
+
+/// 把锚点写入专用字段，并从注释中去掉旧版锚点，注释的其余内容保持不变。
+///
+/// MP3/AAC 按开关写入 ID3v2 与 APEv2，启用 ID3v1 时同步更新 ID3v1 的基本字段。
+/// 修改先在同目录的临时副本上完成，再整体替换原文件，失败时原文件不受影响。
+pub fn write(path: &Path, anchor_id: &str, options: &TagOptions) -> Result<(), String> {
+    let format = Format::of(path)?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()));
+    std::fs::copy(path, &temp).map_err(|e| format!("创建临时文件失败: {}", e))?;
+    let result = write_format(format, &temp, anchor_id, options).and_then(|_| {
+        std::fs::File::open(&temp)
+            .and_then(|file| file.sync_all())
+            .and_then(|_| std::fs::rename(&temp, path))
+            .map_err(|e| format!("替换原文件失败: {}", e))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// 按格式在指定文件上直接写入锚点。
+fn write_format(
+    format: Format,
+    path: &Path,
+    anchor_id: &str,
+    options: &TagOptions,
+) -> Result<(), String> {
+    match format {
+        Format::Mpeg | Format::Aac => {
+            if !options.enable_id3v2 && !options.enable_apev2 {
+                return Err("ID3v1 放不下锚点，请在设置中启用 ID3v2 或 APEv2 标签".to_string());
+            }
+            let mut tag = load_id3(path)?;
+            if options.enable_id3v2 {
+                set_id3(&mut tag, anchor_id);
+                save_id3(path, &tag, options.id3_version(&tag))?;
+            }
+            if options.enable_apev2 {
+                ape::write(path, |tag| set_ape(tag, anchor_id))?;
+            }
+            if options.enable_id3v1 {
+                id3v1::write(path, &tag)?;
+            }
+            Ok(())
+        }
+        Format::Chunked => {
+            require(options.enable_id3v2, "ID3v2")?;
+            let mut tag = load_id3(path)?;
+            set_id3(&mut tag, anchor_id);
+            save_id3(path, &tag, options.id3_version(&tag))
+        }
+        Format::Dsf => {
+            require(options.enable_id3v2, "ID3v2")?;
+            let mut tag = dsf::read(path)?;
+            set_id3(&mut tag, anchor_id);
+            dsf::write(path, &tag, options.id3_version(&tag))
+        }
+        Format::Flac => write_flac(path, anchor_id),
+        Format::Mp4 => write_mp4(path, anchor_id),
+        Format::Ogg => write_ogg(path, anchor_id),
+        Format::Ape => {
+            require(options.enable_apev2, "APEv2")?;
+            ape::write(path, |tag| set_ape(tag, anchor_id))
+        }
+    }
+}
+
+/// 把注释中的旧版锚点迁移到专用字段，没有旧版锚点时不修改文件。
+pub fn migrate(path: &Path) -> Result<AnchorInfo, String> {
+    let mut info = read(path)?;
+    if let (true, Some(anchor_id)) = (info.legacy, info.anchor_id.as_deref()) {
+        // 写回旧版锚点所在的标签类型，MP3/AAC 的旧版锚点可能在 APEv2 注释中，
+        // 此时只在文件原本就有 ID3v2 标签时才同时写入 ID3v2
+        let (in_ape, has_id3) = match Format::of(path)? {
+            Format::Ape => (true, false),
+            Format::Mpeg | Format::Aac => (
+                found_ape(&ape::read(path)?).legacy.is_some(),
+                id3::Tag::read_from_path(path).is_ok(),
+            ),
+            _ => (false, true),
+        };
+        let options = TagOptions {
+            enable_apev2: in_ape,
+            enable_id3v2: !in_ape || has_id3,
+            ..TagOptions::default()
+        };
+        write(path, anchor_id, &options)?;
+        info.legacy = false;
+    }
+    Ok(info)
+}
+
+fn require(enabled: bool, kind: &str) -> Result<(), String> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(format!(
+            "该格式的锚点写在 {} 标签中，请在设置中启用 {}",
+            kind, kind
+        ))
+    }
+}
+
+/// 从注释中提取旧版锚点 ID。
+fn legacy_id(comment: &str) -> Option<String> {
+    let (_, rest) = split_legacy(comment)?;
+    let id: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit() || *c == '-')
+        .collect();
+    (!id.is_empty()).then_some(id)
+}
+
+/// 去掉注释中的旧版锚点，剩余内容为空时返回空；没有旧版锚点的注释原样返回。
+fn strip_legacy(comment: &str) -> Option<String> {
+    let Some((before, rest)) = split_legacy(comment) else {
+        return Some(comment.to_string());
+    };
+    let after = rest.trim_start_matches(|c: char| c.is_ascii_hexdigit() || c == '-');
+    let stripped = format!("{} {}", before.trim_end(), after.trim_start());
+    let stripped = stripped.trim();
+    (!stripped.is_empty()).then(|| stripped.to_string())
+}
+
+/// 按旧版前缀（不区分大小写）切分注释，返回前缀之前与之后的部分。
+fn split_legacy(comment: &str) -> Option<(&str, &str)> {
+    let start = comment.to_ascii_uppercase().find(LEGACY_PREFIX)?;
+    Some((&comment[..start], &comment[start + LEGACY_PREFIX.len()..]))
+}
+
+fn found_id3(tag: &id3::Tag) -> Found {
+    Found {
+        dedicated: tag
+            .extended_texts()
+            .find(|text| text.description == ANCHOR_KEY)
+            .map(|text| text.value.clone()),
+        legacy: tag.comments().find_map(|comment| legacy_id(&comment.text)),
+    }
+}
+
+fn set_id3(tag: &mut id3::Tag, anchor_id: &str) {
+    tag.remove_extended_text(Some(ANCHOR_KEY), None);
+    tag.add_frame(id3::frame::ExtendedText {
+        description: ANCHOR_KEY.to_string(),
+        value: anchor_id.to_string(),
+    });
+    let legacy: Vec<id3::frame::Comment> = tag
+        .comments()
+        .filter(|comment| split_legacy(&comment.text).is_some())
+        .cloned()
+        .collect();
+    for comment in legacy {
+        tag.remove_comment(Some(&comment.description), Some(&comment.text));
+        if let Some(text) = strip_legacy(&comment.text) {
+            tag.add_frame(id3::frame::Comment { text, ..comment });
+        }
+    }
+}
+
+/// 写回 ID3v2 标签，WAV/AIFF 写入容器中的 ID3 块，其他格式写在文件开头。
+fn save_id3(path: &Path, tag: &id3::Tag, version: id3::Version) -> Result<(), String> {
+    tag.write_to_path(path, version)
+        .map_err(|e| format!("写入ID3标签失败: {}", e))
+}
+
+/// 读取 ID3v2 标签，文件没有标签时返回空标签。
+fn load_id3(path: &Path) -> Result<id3::Tag, String> {
+    match id3::Tag::read_from_path(path) {
+        Ok(tag) => Ok(tag),
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Ok(id3::Tag::new()),
+        Err(e) => Err(format!("读取ID3标签失败: {}", e)),
+    }
+}
+
+fn read_flac(path: &Path) -> Result<Found, String> {
+    let tag =
+        metaflac::Tag::read_from_path(path).map_err(|e| format!("读取FLAC标签失败: {}", e))?;
+    let Some(comments) = tag.vorbis_comments() else {
+        return Ok(Found {
+            dedicated: None,
+            legacy: None,
+        });
+    };
+    let values = |key: &str| {
+        comments
+            .comments
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(key))
+            .flat_map(|(_, values)| values.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+    };
+    Ok(Found {
+        dedicated: values(ANCHOR_KEY).first().map(|value| value.to_string()),
+        legacy: VORBIS_COMMENT_KEYS
+            .iter()
+            .flat_map(|key| values(key))
+            .find_map(legacy_id),
+    })
+}
+
+fn write_flac(path: &Path, anchor_id: &str) -> Result<(), String> {
+    let mut tag =
+        metaflac::Tag::read_from_path(path).map_err(|e| format!("读取FLAC标签失败: {}", e))?;
+    let comments = &mut tag.vorbis_comments_mut().comments;
+    comments.retain(|name, _| !name.eq_ignore_ascii_case(ANCHOR_KEY));
+    comments.insert(ANCHOR_KEY.to_string(), vec![anchor_id.to_string()]);
+    for (name, values) in comments.iter_mut() {
+        if VORBIS_COMMENT_KEYS
+            .iter()
+            .any(|key| name.eq_ignore_ascii_case(key))
+        {
+            *values = values
+                .iter()
+                .filter_map(|value| strip_legacy(value))
+                .collect();
+        }
+    }
+    comments.retain(|_, values| !values.is_empty());
+    tag.write_to_path(path)
+        .map_err(|e| format!("写入FLAC标签失败: {}", e))
+}
+
+fn mp4_ident() -> mp4ameta::FreeformIdent<'static> {
+    mp4ameta::FreeformIdent::new(MP4_MEAN, MP4_NAME)
+}
+
+fn read_mp4(path: &Path) -> Result<Found, String> {
+    let tag = mp4ameta::Tag::read_from_path(path).map_err(|e| format!("读取MP4标签失败: {}", e))?;
+    let found = Found {
+        dedicated: tag.strings_of(&mp4_ident()).next().map(str::to_string),
+        legacy: tag.comment().and_then(legacy_id),
+    };
+    Ok(found)
+}
+
+fn write_mp4(path: &Path, anchor_id: &str) -> Result<(), String> {
+    let mut tag =
+        mp4ameta::Tag::read_from_path(path).map_err(|e| format!("读取MP4标签失败: {}", e))?;
+    tag.set_data(mp4_ident(), mp4ameta::Data::Utf8(anchor_id.to_string()));
+    if let Some(comment) = tag.comment().map(str::to_string) {
+        match strip_legacy(&comment) {
+            Some(comment) => tag.set_comment(comment),
+            None => tag.remove_comments(),
+        }
+    }
+    tag.write_to_path(path)
+        .map_err(|e| format!("写入MP4标签失败: {}", e))
+}
+
+fn read_ogg(path: &Path) -> Result<Found, String> {
+    let comments = ogg::read_comments(path)?;
+    let found = Found {
+        dedicated: comments.get(ANCHOR_KEY).next().map(str::to_string),
+        legacy: VORBIS_COMMENT_KEYS
+            .iter()
+            .flat_map(|key| comments.get(key))
+            .find_map(legacy_id),
+    };
+    Ok(found)
+}
+
+fn write_ogg(path: &Path, anchor_id: &str) -> Result<(), String> {
+    ogg::write_comments(path, |comments| {
+        comments.set(ANCHOR_KEY, vec![anchor_id.to_string()]);
+        for key in VORBIS_COMMENT_KEYS {
+            let values = comments.get(key).filter_map(strip_legacy).collect();
+            comments.set(key, values);
+        }
+    })
+}
+
+fn found_ape(tag: &ApeTag) -> Found {
+    Found {
+        dedicated: tag.get(ANCHOR_KEY).map(str::to_string),
+        legacy: tag.get(APE_COMMENT_KEY).and_then(legacy_id),
+    }
+}
+
+fn set_ape(tag: &mut ApeTag, anchor_id: &str) {
+    tag.set(ANCHOR_KEY, Some(anchor_id.to_string()));
+    if let Some(comment) = tag.get(APE_COMMENT_KEY).map(str::to_string) {
+        tag.set(APE_COMMENT_KEY, strip_legacy(&comment));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::testing::TempFile;
+
+    #[test]
+    fn legacy_ids() {
+        assert_eq!(
+            legacy_id("APP_ANCHOR_ID:0a1b-2C3d"),
+            Some("0a1b-2C3d".to_string())
+        );
+        assert_eq!(
+            legacy_id("备注 app_anchor_id:abc-123 其他"),
+            Some("abc-123".to_string())
+        );
+        assert_eq!(legacy_id("APP_ANCHOR_ID:"), None);
+        assert_eq!(legacy_id("APP_ANCHOR_ID: abc"), None);
+        assert_eq!(legacy_id("普通注释"), None);
+    }
+
+    #[test]
+    fn strips_legacy() {
+        assert_eq!(strip_legacy("APP_ANCHOR_ID:abc-123"), None);
+        assert_eq!(strip_legacy("  APP_ANCHOR_ID:abc-123  "), None);
+        assert_eq!(
+            strip_legacy("前面 APP_ANCHOR_ID:abc-123 后面"),
+            Some("前面 后面".to_string())
+        );
+        assert_eq!(
+            strip_legacy("note app_anchor_id:abc-123"),
+            Some("note".to_string())
+        );
+        assert_eq!(strip_legacy("普通注释"), Some("普通注释".to_string()));
+    }
+
+    #[test]
+    fn mp3_round_trip() {
+        let file = TempFile::new("mp3", &[0xff; 1024]);
+        let options = TagOptions {
+            enable_apev2: true,
+            enable_id3v1: true,
+            ..TagOptions::default()
+        };
+        write(file.path(), "abc-123", &options).unwrap();
+
+        let info = read(file.path()).unwrap();
+        assert_eq!(info.anchor_id.as_deref(), Some("abc-123"));
+        assert!(!info.legacy);
+        assert_eq!(
+            found_id3(&load_id3(file.path()).unwrap()).dedicated,
+            info.anchor_id
+        );
+        assert_eq!(
+            found_ape(&ape::read(file.path()).unwrap()).dedicated,
+            info.anchor_id
+        );
+        let data = file.bytes();
+        assert!(data[data.len() - 128..].starts_with(b"TAG"));
+    }
+
+    #[test]
+    fn migrates_id3_comment() {
+        let file = TempFile::new("mp3", &[0xff; 1024]);
+        let mut tag = id3::Tag::new();
+        tag.add_frame(id3::frame::Comment {
+            lang: "eng".to_string(),
+            description: String::new(),
+            text: "保留 APP_ANCHOR_ID:abc-123".to_string(),
+        });
+        save_id3(file.path(), &tag, id3::Version::Id3v23).unwrap();
+
+        let info = read(file.path()).unwrap();
+        assert_eq!(info.anchor_id.as_deref(), Some("abc-123"));
+        assert!(info.legacy);
+        let info = migrate(file.path()).unwrap();
+        assert!(!info.legacy);
+
+        let tag = load_id3(file.path()).unwrap();
+        assert_eq!(tag.version(), id3::Version::Id3v23);
+        let found = found_id3(&tag);
+        assert_eq!(found.dedicated.as_deref(), Some("abc-123"));
+        assert_eq!(found.legacy, None);
+        let comments: Vec<_> = tag
+            .comments()
+            .map(|comment| comment.text.as_str())
+            .collect();
+        assert_eq!(comments, ["保留"]);
+        assert!(ape::read(file.path()).unwrap().get(ANCHOR_KEY).is_none());
+    }
+
+    #[test]
+    fn migrates_ape_comment_without_adding_id3() {
+        let file = TempFile::new("mp3", &[0xff; 1024]);
+        ape::write(file.path(), |tag| {
+            tag.set(APE_COMMENT_KEY, Some("APP_ANCHOR_ID:abc-123".to_string()))
+        })
+        .unwrap();
+
+        let info = migrate(file.path()).unwrap();
+        assert_eq!(info.anchor_id.as_deref(), Some("abc-123"));
+        let tag = ape::read(file.path()).unwrap();
+        assert_eq!(tag.get(ANCHOR_KEY), Some("abc-123"));
+        assert_eq!(tag.get(APE_COMMENT_KEY), None);
+        assert!(id3::Tag::read_from_path(file.path()).is_err());
+        assert!(!read(file.path()).unwrap().legacy);
+    }
+
+    #[test]
+    fn requires_enabled_tag() {
+        let file = TempFile::new("mp3", &[0xff; 1024]);
+        let options = TagOptions {
+            enable_id3v2: false,
+            enable_id3v1: true,
+            ..TagOptions::default()
+        };
+        assert!(write(file.path(), "abc-123", &options).is_err());
+        assert_eq!(file.bytes(), [0xff; 1024]);
+    }
+}
@@ -0,0 +1,296 @@
+//! APEv2 标签读写。标签位于文件末尾，有 ID3v1 时在 ID3v1 之前。
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const PREAMBLE: &[u8] = b"APETAGEX";
+const VERSION: u32 = 2000;
+/// 头部与尾部的长度。
+const FRAME_LEN: u64 = 32;
+const ID3V1_LEN: u64 = 128;
+/// 标签带有头部。
+const HAS_HEADER: u32 = 1 << 31;
+/// 当前是头部而不是尾部。
+const IS_HEADER: u32 = 1 << 29;
+/// 条目类型位，0 为 UTF-8 文本。
+const ITEM_TYPE: u32 = 0b110;
+/// 二进制条目的类型位。
+const BINARY: u32 = 0b010;
+
+/// APEv2 标签条目，键不区分大小写。
+#[derive(Debug, Clone, Default)]
+pub struct ApeTag {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    key: String,
+    flags: u32,
+    value: Vec<u8>,
+}
+
+impl ApeTag {
+    /// 文本条目的值，多个值以 `\0` 分隔时取第一个。
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let item = self
+            .items
+            .iter()
+            .find(|item| item.key.eq_ignore_ascii_case(key) && item.flags & ITEM_TYPE == 0)?;
+        std::str::from_utf8(&item.value).ok()?.split('\0').next()
+    }
+
+    /// 二进制条目的值，如封面 `Cover Art (Front)`。
+    pub fn binary(&self, key: &str) -> Option<&[u8]> {
+        self.items
+            .iter()
+            .find(|item| item.key.eq_ignore_ascii_case(key) && item.flags & ITEM_TYPE == BINARY)
+            .map(|item| item.value.as_slice())
+    }
+
+    /// 设置文本条目，为空时删除，已有条目保持原来的位置。
+    pub fn set(&mut self, key: &str, value: Option<String>) {
+        let position = self
+            .items
+            .iter()
+            .position(|item| item.key.eq_ignore_ascii_case(key));
+        let Some(value) = value else {
+            self.items
+                .retain(|item| !item.key.eq_ignore_ascii_case(key));
+            return;
+        };
+        let item = Item {
+            key: key.to_string(),
+            flags: 0,
+            value: value.into_bytes(),
+        };
+        match position {
+            Some(position) => self.items[position] = item,
+            None => self.items.push(item),
+        }
+    }
+
+    fn parse(data: &[u8], count: u32) -> Option<Self> {
+        let mut items = Vec::new();
+        let mut offset = 0;
+        for _ in 0..count {
+            let header = data.get(offset..offset + 8)?;
+            let len = u32::from_le_bytes(header[..4].try_into().ok()?) as usize;
+            let flags = u32::from_le_bytes(header[4..].try_into().ok()?);
+            let key_start = offset + 8;
+            let key_len = data.get(key_start..)?.iter().position(|&b| b == 0)?;
+            let key = String::from_utf8_lossy(&data[key_start..key_start + key_len]).to_string();
+            let value_start = key_start + key_len + 1;
+            let value = data.get(value_start..value_start + len)?.to_vec();
+            items.push(Item { key, flags, value });
+            offset = value_start + len;
+        }
+        Some(Self { items })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut items = Vec::new();
+        for item in &self.items {
+            items.extend_from_slice(&(item.value.len() as u32).to_le_bytes());
+            items.extend_from_slice(&item.flags.to_le_bytes());
+            items.extend_from_slice(item.key.as_bytes());
+            items.push(0);
+            items.extend_from_slice(&item.value);
+        }
+        let size = items.len() as u32 + FRAME_LEN as u32;
+        let count = self.items.len() as u32;
+        let mut data = frame(size, count, HAS_HEADER | IS_HEADER);
+        data.extend(items);
+        data.extend(frame(size, count, HAS_HEADER));
+        data
+    }
+}
+
+fn frame(size: u32, count: u32, flags: u32) -> Vec<u8> {
+    let mut data = PREAMBLE.to_vec();
+    for value in [VERSION, size, count, flags] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&[0; 8]);
+    data
+}
+
+/// 读取 APEv2 标签，没有标签时返回空标签。
+pub fn read(path: &Path) -> Result<ApeTag, String> {
+    let mut file = File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+    let (_, tag) = locate(&mut file)?;
+    Ok(tag)
+}
+
+/// 修改 APEv2 标签并写回文件末尾，文件中的 ID3v1 保持在最后。
+pub fn write(path: &Path, edit: impl FnOnce(&mut ApeTag)) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("打开文件失败: {}", e))?;
+    let ((start, end), mut tag) = locate(&mut file)?;
+    edit(&mut tag);
+    let data = if tag.items.is_empty() {
+        Vec::new()
+    } else {
+        tag.to_bytes()
+    };
+    replace(&mut file, start, end, &data).map_err(|e| format!("写入APEv2标签失败: {}", e))
+}
+
+/// 用新内容替换文件中 `start..end` 的部分，之后的内容（ID3v1）保持不变。
+fn replace(file: &mut File, start: u64, end: u64, data: &[u8]) -> io::Result<()> {
+    let mut trailer = Vec::new();
+    file.seek(SeekFrom::Start(end))?;
+    file.read_to_end(&mut trailer)?;
+    file.set_len(start)?;
+    file.seek(SeekFrom::Start(start))?;
+    file.write_all(data)?;
+    file.write_all(&trailer)?;
+    file.flush()
+}
+
+/// 找到标签所在的范围，没有标签时为 ID3v1（或文件末尾）之前的空范围。
+fn locate(file: &mut File) -> Result<((u64, u64), ApeTag), String> {
+    let failed = |e: io::Error| format!("读取APEv2标签失败: {}", e);
+    let len = file.metadata().map_err(failed)?.len();
+    let mut end = len;
+    if len >= ID3V1_LEN && read_at(file, len - ID3V1_LEN, 3).map_err(failed)? == b"TAG" {
+        end = len - ID3V1_LEN;
+    }
+    if end < FRAME_LEN {
+        return Ok(((end, end), ApeTag::default()));
+    }
+    let footer = read_at(file, end - FRAME_LEN, FRAME_LEN as usize).map_err(failed)?;
+    if !footer.starts_with(PREAMBLE) {
+        return Ok(((end, end), ApeTag::default()));
+    }
+    let field = |at: usize| {
+        u32::from_le_bytes([footer[at], footer[at + 1], footer[at + 2], footer[at + 3]])
+    };
+    let (size, count, flags) = (field(12) as u64, field(16), field(20));
+    let header = if flags & HAS_HEADER != 0 {
+        FRAME_LEN
+    } else {
+        0
+    };
+    if size < FRAME_LEN || size + header > end {
+        return Err("APEv2标签损坏".to_string());
+    }
+    let items_start = end - size;
+    let items = read_at(file, items_start, (size - FRAME_LEN) as usize).map_err(failed)?;
+    let tag = ApeTag::parse(&items, count).ok_or("APEv2标签损坏")?;
+    Ok(((items_start - header, end), tag))
+}
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0; len];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::testing::TempFile;
+
+    fn audio() -> Vec<u8> {
+        (0..1000).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn id3v1() -> Vec<u8> {
+        let mut tag = b"TAG".to_vec();
+        tag.resize(ID3V1_LEN as usize, b'v');
+        tag
+    }
+
+    fn count_preambles(data: &[u8]) -> usize {
+        data.windows(PREAMBLE.len())
+            .filter(|window| *window == PREAMBLE)
+            .count()
+    }
+
+    #[test]
+    fn round_trip_before_id3v1() {
+        let original = [audio(), id3v1()].concat();
+        let file = TempFile::new("mp3", &original);
+
+        write(file.path(), |tag| {
+            tag.set("APP_ANCHOR_ID", Some("abc-123".to_string()));
+            tag.set("Comment", Some("备注".to_string()));
+        })
+        .unwrap();
+        let tag = read(file.path()).unwrap();
+        assert_eq!(tag.get("app_anchor_id"), Some("abc-123"));
+        assert_eq!(tag.get("COMMENT"), Some("备注"));
+
+        let data = file.bytes();
+        let end = data.len() - ID3V1_LEN as usize;
+        assert!(data.starts_with(&audio()));
+        assert!(data.ends_with(&id3v1()));
+        assert!(data[audio().len()..].starts_with(PREAMBLE));
+        assert!(data[end - FRAME_LEN as usize..end].starts_with(PREAMBLE));
+
+        // 修改已有标签时原位替换，条目保持原来的顺序
+        write(file.path(), |tag| {
+            tag.set("APP_ANCHOR_ID", Some("def-456".to_string()));
+        })
+        .unwrap();
+        let data = file.bytes();
+        assert_eq!(count_preambles(&data), 2);
+        assert!(data.ends_with(&id3v1()));
+        let tag = read(file.path()).unwrap();
+        assert_eq!(tag.get("APP_ANCHOR_ID"), Some("def-456"));
+        let keys: Vec<_> = tag.items.iter().map(|item| item.key.as_str()).collect();
+        assert_eq!(keys, ["APP_ANCHOR_ID", "Comment"]);
+
+        write(file.path(), |tag| {
+            tag.set("APP_ANCHOR_ID", None);
+            tag.set("Comment", None);
+        })
+        .unwrap();
+        assert_eq!(file.bytes(), original);
+    }
+
+    #[test]
+    fn round_trip_without_id3v1() {
+        let file = TempFile::new("ape", &audio());
+        assert!(read(file.path()).unwrap().items.is_empty());
+
+        write(file.path(), |tag| {
+            tag.set("Title", Some("Song".to_string()))
+        })
+        .unwrap();
+        let data = file.bytes();
+        assert!(data.starts_with(&audio()));
+        assert!(data[data.len() - FRAME_LEN as usize..].starts_with(PREAMBLE));
+        assert_eq!(read(file.path()).unwrap().get("title"), Some("Song"));
+    }
+
+    #[test]
+    fn binary_items() {
+        let tag = ApeTag {
+            items: vec![
+                Item {
+                    key: "Cover Art (Front)".to_string(),
+                    flags: BINARY,
+                    value: vec![0, 1, 2, 3],
+                },
+                Item {
+                    key: "Title".to_string(),
+                    flags: 0,
+                    value: b"first\0second".to_vec(),
+                },
+            ],
+        };
+        let data = tag.to_bytes();
+        let items = &data[FRAME_LEN as usize..data.len() - FRAME_LEN as usize];
+        let tag = ApeTag::parse(items, 2).unwrap();
+        assert_eq!(tag.binary("cover art (front)"), Some(&[0, 1, 2, 3][..]));
+        assert_eq!(tag.get("Cover Art (Front)"), None);
+        assert_eq!(tag.get("TITLE"), Some("first"));
+    }
+}
@@ -0,0 +1,145 @@
+//! DSF 的 ID3v2 标签读写。标签位于文件末尾，由文件头中的元数据指针指向。
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// `DSD ` 块的长度，其后才是音频数据。
+const DSD_CHUNK_LEN: u64 = 28;
+/// 文件头中文件总长度与元数据指针的偏移。
+const FILE_SIZE_OFFSET: u64 = 12;
+const METADATA_OFFSET: u64 = 20;
+
+/// 读取 DSF 中的 ID3v2 标签，没有标签时返回空标签。
+pub fn read(path: &Path) -> Result<id3::Tag, String> {
+    let mut file = File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+    let failed = |e: io::Error| format!("读取DSF标签失败: {}", e);
+    let Some(pointer) = metadata_pointer(&mut file).map_err(failed)? else {
+        return Ok(id3::Tag::new());
+    };
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(pointer)).map_err(failed)?;
+    file.read_to_end(&mut data).map_err(failed)?;
+    match id3::Tag::read_from2(Cursor::new(data)) {
+        Ok(tag) => Ok(tag),
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Ok(id3::Tag::new()),
+        Err(e) => Err(format!("读取DSF标签失败: {}", e)),
+    }
+}
+
+/// 把 ID3v2 标签写到文件末尾，并更新文件头中的长度与元数据指针。
+pub fn write(path: &Path, tag: &id3::Tag, version: id3::Version) -> Result<(), String> {
+    let mut data = Vec::new();
+    tag.write_to(&mut data, version)
+        .map_err(|e| format!("写入DSF标签失败: {}", e))?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("打开文件失败: {}", e))?;
+    let write = |file: &mut File| -> io::Result<()> {
+        let start = match metadata_pointer(file)? {
+            Some(pointer) => pointer,
+            None => file.metadata()?.len(),
+        };
+        file.set_len(start)?;
+        file.seek(SeekFrom::Start(start))?;
+        file.write_all(&data)?;
+        let size = start + data.len() as u64;
+        file.seek(SeekFrom::Start(FILE_SIZE_OFFSET))?;
+        file.write_all(&size.to_le_bytes())?;
+        file.write_all(&start.to_le_bytes())?;
+        file.flush()
+    };
+    write(&mut file).map_err(|e| format!("写入DSF标签失败: {}", e))
+}
+
+/// 校验文件头并返回元数据指针，没有元数据时为空。
+fn metadata_pointer(file: &mut File) -> io::Result<Option<u64>> {
+    let mut header = [0; DSD_CHUNK_LEN as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    if !header.starts_with(b"DSD ") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "不是有效的DSF文件",
+        ));
+    }
+    let at = METADATA_OFFSET as usize;
+    let pointer = u64::from_le_bytes(header[at..at + 8].try_into().unwrap_or_default());
+    let len = file.metadata()?.len();
+    Ok((pointer >= DSD_CHUNK_LEN && pointer <= len).then_some(pointer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::testing::TempFile;
+    use id3::TagLike;
+
+    /// `DSD `、`fmt ` 与 `data` 块组成的最小 DSF 文件，没有元数据。
+    fn dsf() -> Vec<u8> {
+        let mut data = b"DSD ".to_vec();
+        data.extend_from_slice(&DSD_CHUNK_LEN.to_le_bytes());
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(b"fmt ");
+        data.extend_from_slice(&52u64.to_le_bytes());
+        data.extend_from_slice(&[0; 40]);
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&(12u64 + 4096).to_le_bytes());
+        data.extend((0..4096).map(|i| (i % 251) as u8));
+        let size = data.len() as u64;
+        data[12..20].copy_from_slice(&size.to_le_bytes());
+        data
+    }
+
+    fn header(data: &[u8], offset: u64) -> u64 {
+        let at = offset as usize;
+        u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn round_trip_updates_header() {
+        let original = dsf();
+        let file = TempFile::new("dsf", &original);
+        assert!(read(file.path()).unwrap().frames().next().is_none());
+
+        let mut tag = id3::Tag::new();
+        tag.set_title("Song");
+        write(file.path(), &tag, id3::Version::Id3v24).unwrap();
+        let data = file.bytes();
+        assert_eq!(header(&data, FILE_SIZE_OFFSET), data.len() as u64);
+        assert_eq!(header(&data, METADATA_OFFSET), original.len() as u64);
+        assert_eq!(data[..12], original[..12]);
+        assert_eq!(data[28..original.len()], original[28..]);
+        assert!(data[original.len()..].starts_with(b"ID3"));
+        assert_eq!(read(file.path()).unwrap().title(), Some("Song"));
+
+        // 再次写入时替换原有标签，而不是追加
+        tag.set_title("A much longer title than before");
+        tag.set_artist("Someone");
+        write(file.path(), &tag, id3::Version::Id3v23).unwrap();
+        let data = file.bytes();
+        assert_eq!(header(&data, FILE_SIZE_OFFSET), data.len() as u64);
+        assert_eq!(header(&data, METADATA_OFFSET), original.len() as u64);
+        let tag = read(file.path()).unwrap();
+        assert_eq!(tag.title(), Some("A much longer title than before"));
+        assert_eq!(tag.artist(), Some("Someone"));
+        assert_eq!(tag.version(), id3::Version::Id3v23);
+
+        let mut tag = id3::Tag::new();
+        tag.set_title("S");
+        write(file.path(), &tag, id3::Version::Id3v24).unwrap();
+        let data = file.bytes();
+        assert_eq!(header(&data, FILE_SIZE_OFFSET), data.len() as u64);
+        assert_eq!(read(file.path()).unwrap().artist(), None);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let file = TempFile::new("dsf", &[0; 64]);
+        assert!(read(file.path()).is_err());
+        assert!(write(file.path(), &id3::Tag::new(), id3::Version::Id3v24).is_err());
+        assert_eq!(file.bytes(), [0; 64]);
+    }
+}
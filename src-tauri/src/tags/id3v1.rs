@@ -0,0 +1,132 @@
+//! ID3v1 标签写入。ID3v1 只有固定长度的 Latin-1 字段，放不下锚点，
+//! 只用于让老旧播放器也能看到标题、艺术家与专辑。
+
+use id3::TagLike;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const TAG_LEN: u64 = 128;
+/// 未知流派。
+const NO_GENRE: u8 = 255;
+
+/// 按 ID3v2 标签的基本字段写入或更新文件末尾的 ID3v1，ID3v2 中没有的字段沿用已有的 ID3v1，
+/// 已有的注释与流派保持不变。
+pub fn write(path: &Path, tag: &id3::Tag) -> Result<(), String> {
+    let existing = id3::v1::Tag::read_from_path(path).ok();
+    let field = |value: Option<String>, old: fn(&id3::v1::Tag) -> &str| {
+        value.unwrap_or_else(|| existing.as_ref().map(old).unwrap_or_default().to_string())
+    };
+    let title = field(tag.title().map(str::to_string), |v1| &v1.title);
+    let artist = field(tag.artist().map(str::to_string), |v1| &v1.artist);
+    let album = field(tag.album().map(str::to_string), |v1| &v1.album);
+    let year = field(tag.year().map(|year| year.to_string()), |v1| &v1.year);
+    let comment = field(None, |v1| &v1.comment);
+    let mut data = Vec::with_capacity(TAG_LEN as usize);
+    data.extend_from_slice(b"TAG");
+    data.extend(latin1(&title, 30));
+    data.extend(latin1(&artist, 30));
+    data.extend(latin1(&album, 30));
+    data.extend(latin1(&year, 4));
+    data.extend(latin1(&comment, 28));
+    // ID3v1.1：注释第 29 字节为 0 时，第 30 字节为音轨号
+    data.push(0);
+    data.push(
+        tag.track()
+            .and_then(|track| u8::try_from(track).ok())
+            .or_else(|| existing.as_ref().and_then(|v1| v1.track))
+            .unwrap_or(0),
+    );
+    data.push(existing.map_or(NO_GENRE, |v1| v1.genre_id));
+
+    let write = || -> io::Result<()> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let len = file.metadata()?.len();
+        let mut offset = len;
+        if len >= TAG_LEN {
+            let mut magic = [0; 3];
+            file.seek(SeekFrom::Start(len - TAG_LEN))?;
+            file.read_exact(&mut magic)?;
+            if &magic == b"TAG" {
+                offset = len - TAG_LEN;
+            }
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&data)?;
+        file.flush()
+    };
+    write().map_err(|e| format!("写入ID3v1标签失败: {}", e))
+}
+
+/// 按 Latin-1 编码并截断或补零到固定长度，无法表示的字符写为 `?`。
+fn latin1(text: &str, len: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = text
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .take(len)
+        .collect();
+    bytes.resize(len, 0);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::testing::TempFile;
+
+    fn audio() -> Vec<u8> {
+        vec![0xff; 512]
+    }
+
+    fn existing() -> Vec<u8> {
+        let mut data = b"TAG".to_vec();
+        data.extend(latin1("Old Title", 30));
+        data.extend(latin1("Old Artist", 30));
+        data.extend(latin1("Old Album", 30));
+        data.extend(latin1("1999", 4));
+        data.extend(latin1("keep me", 28));
+        data.extend([0, 3, 17]);
+        data
+    }
+
+    #[test]
+    fn replaces_existing_tag() {
+        let file = TempFile::new("mp3", &[audio(), existing()].concat());
+        let mut tag = id3::Tag::new();
+        tag.set_title("New Title");
+        tag.set_album("New Album");
+        write(file.path(), &tag).unwrap();
+
+        let data = file.bytes();
+        assert_eq!(data.len(), audio().len() + TAG_LEN as usize);
+        assert!(data.starts_with(&audio()));
+        let v1 = id3::v1::Tag::read_from_path(file.path()).unwrap();
+        assert_eq!(v1.title, "New Title");
+        assert_eq!(v1.artist, "Old Artist");
+        assert_eq!(v1.album, "New Album");
+        assert_eq!(v1.year, "1999");
+        assert_eq!(v1.comment, "keep me");
+        assert_eq!(v1.track, Some(3));
+        assert_eq!(v1.genre_id, 17);
+    }
+
+    #[test]
+    fn appends_new_tag() {
+        let file = TempFile::new("mp3", &audio());
+        let mut tag = id3::Tag::new();
+        tag.set_title("标题 Title");
+        tag.set_artist("A".repeat(40));
+        tag.set_year(2024);
+        tag.set_track(7);
+        write(file.path(), &tag).unwrap();
+
+        let data = file.bytes();
+        assert_eq!(data.len(), audio().len() + TAG_LEN as usize);
+        let v1 = id3::v1::Tag::read_from_path(file.path()).unwrap();
+        assert_eq!(v1.title, "?? Title");
+        assert_eq!(v1.artist, "A".repeat(30));
+        assert_eq!(v1.year, "2024");
+        assert_eq!(v1.track, Some(7));
+        assert_eq!(v1.genre_id, NO_GENRE);
+    }
+}
@@ -0,0 +1,364 @@
+pub mod anchor;
+mod ape;
+mod dsf;
+mod id3v1;
+mod ogg;
+mod properties;
+
+use crate::media;
+use audiotags::Tag;
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// 批量读取时同时处理的文件数。
+const READ_CONCURRENCY: usize = 8;
+
+/// 本地音频文件的标签与技术参数。
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioMetadata {
+    pub path: String,
+    /// 文件大小（字节）。
+    pub size: u64,
+    /// 按文件头识别的 MIME 类型。
+    pub mime: String,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u16>,
+    pub track_total: Option<u16>,
+    pub disc_number: Option<u16>,
+    pub disc_total: Option<u16>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    /// 时长（秒）。
+    pub duration: Option<f64>,
+    /// 平均比特率（kbps）。
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u8>,
+    pub channels: Option<u8>,
+    pub cover: Option<EmbeddedCover>,
+}
+
+/// 内嵌封面，图片导出到缓存目录的 `covers` 下，同一张图片只保存一份。
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddedCover {
+    pub mime: String,
+    pub size: u64,
+    /// 导出的图片路径，未设置缓存目录时为空。
+    pub path: Option<String>,
+}
+
+/// 批量读取中单个文件的结果。
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataResult {
+    pub path: String,
+    pub metadata: Option<AudioMetadata>,
+    pub error: Option<String>,
+}
+
+/// 读取一个文件的元数据，`covers_dir` 为空时只返回封面的类型与大小。
+pub async fn read(path: PathBuf, covers_dir: Option<PathBuf>) -> Result<AudioMetadata, String> {
+    tokio::task::spawn_blocking(move || read_blocking(&path, covers_dir.as_deref()))
+        .await
+        .map_err(|e| format!("读取音频元数据失败: {}", e))?
+}
+
+/// 并行读取多个文件，结果与输入顺序一致，单个文件失败不影响其他文件。
+pub async fn read_many(paths: Vec<String>, covers_dir: Option<PathBuf>) -> Vec<MetadataResult> {
+    stream::iter(paths)
+        .map(|path| {
+            let covers_dir = covers_dir.clone();
+            async move {
+                match read(PathBuf::from(&path), covers_dir).await {
+                    Ok(metadata) => MetadataResult {
+                        path,
+                        metadata: Some(metadata),
+                        error: None,
+                    },
+                    Err(error) => MetadataResult {
+                        path,
+                        metadata: None,
+                        error: Some(error),
+                    },
+                }
+            }
+        })
+        .buffered(READ_CONCURRENCY)
+        .collect()
+        .await
+}
+
+fn read_blocking(path: &Path, covers_dir: Option<&Path>) -> Result<AudioMetadata, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("读取文件信息失败: {}", e))?
+        .len();
+    let mime = media::detect(&mut file, path).map_err(|e| format!("读取文件失败: {}", e))?;
+    // 无法解析的格式仍返回标签，技术参数留空
+    let properties = properties::read(&mut file, mime).unwrap_or_default();
+
+    let mut metadata = AudioMetadata {
+        path: path.to_string_lossy().to_string(),
+        size,
+        mime: mime.to_string(),
+        duration: properties.duration,
+        bitrate: properties.bitrate,
+        sample_rate: properties.sample_rate,
+        bit_depth: properties.bit_depth,
+        channels: properties.channels,
+        ..AudioMetadata::default()
+    };
+
+    // 按格式选择标签读取方式，文件没有标签时不算错误
+    let fields = match mime {
+        "audio/flac" | "audio/mp4" => Tag::new()
+            .read_from_path(path)
+            .ok()
+            .map(|tag| Fields::from_audiotags(tag.as_ref())),
+        // ID3 可能在文件开头，也可能作为 WAV/AIFF 容器中的块；MP3/AAC 还可能只带 APEv2
+        "audio/mpeg" | "audio/aac" | "audio/wav" | "audio/aiff" => {
+            match id3::Tag::read_from_path(path) {
+                Ok(tag) => Some(Fields::from_id3(&tag)),
+                Err(_) if matches!(mime, "audio/mpeg" | "audio/aac") => {
+                    ape::read(path).ok().map(|tag| Fields::from_ape(&tag))
+                }
+                Err(_) => None,
+            }
+        }
+        "audio/x-dsf" => dsf::read(path).ok().map(|tag| Fields::from_id3(&tag)),
+        "audio/ogg" | "audio/opus" => ogg::read_comments(path)
+            .ok()
+            .map(|comments| Fields::from_vorbis(&comments)),
+        "audio/x-ape" | "audio/x-wavpack" => ape::read(path).ok().map(|tag| Fields::from_ape(&tag)),
+        _ => None,
+    };
+    let Some(fields) = fields else {
+        return Ok(metadata);
+    };
+
+    metadata.title = fields.title;
+    metadata.artists = fields.artists;
+    metadata.album = fields.album;
+    metadata.album_artist = fields.album_artist;
+    metadata.track_number = fields.track_number;
+    metadata.track_total = fields.track_total;
+    metadata.disc_number = fields.disc_number;
+    metadata.disc_total = fields.disc_total;
+    metadata.year = fields.year;
+    metadata.genre = fields.genre;
+    metadata.duration = metadata.duration.or(fields.duration);
+    if let Some((mime, data)) = fields.cover {
+        metadata.cover = Some(EmbeddedCover {
+            path: covers_dir.and_then(|dir| save_cover(dir, &data, &mime)),
+            size: data.len() as u64,
+            mime,
+        });
+    }
+    Ok(metadata)
+}
+
+/// 各格式标签中读出的通用字段。
+#[derive(Debug, Default)]
+struct Fields {
+    title: Option<String>,
+    artists: Vec<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    track_number: Option<u16>,
+    track_total: Option<u16>,
+    disc_number: Option<u16>,
+    disc_total: Option<u16>,
+    year: Option<i32>,
+    genre: Option<String>,
+    duration: Option<f64>,
+    /// 封面的 MIME 类型与图片数据。
+    cover: Option<(String, Vec<u8>)>,
+}
+
+impl Fields {
+    /// FLAC 与 MP4 标签。
+    fn from_audiotags(tag: &dyn audiotags::AudioTag) -> Self {
+        Self {
+            title: text(tag.title()),
+            artists: texts(tag.artists().unwrap_or_default()),
+            album: text(tag.album_title()),
+            album_artist: text(tag.album_artist()),
+            track_number: tag.track_number(),
+            track_total: tag.total_tracks(),
+            disc_number: tag.disc_number(),
+            disc_total: tag.total_discs(),
+            year: tag.year(),
+            genre: text(tag.genre()),
+            duration: tag.duration(),
+            cover: tag
+                .album_cover()
+                .map(|picture| (String::from(picture.mime_type), picture.data.to_vec())),
+        }
+    }
+
+    /// ID3v2 标签，封面优先取封面类型的图片。
+    fn from_id3(tag: &id3::Tag) -> Self {
+        use id3::TagLike;
+        let number = |value: Option<u32>| value.and_then(|value| u16::try_from(value).ok());
+        let cover = tag
+            .pictures()
+            .find(|picture| picture.picture_type == id3::frame::PictureType::CoverFront)
+            .or_else(|| tag.pictures().next())
+            .map(|picture| (picture.mime_type.clone(), picture.data.clone()));
+        Self {
+            title: text(tag.title()),
+            artists: texts(tag.artists().unwrap_or_default()),
+            album: text(tag.album()),
+            album_artist: text(tag.album_artist()),
+            track_number: number(tag.track()),
+            track_total: number(tag.total_tracks()),
+            disc_number: number(tag.disc()),
+            disc_total: number(tag.total_discs()),
+            year: tag
+                .year()
+                .or_else(|| tag.date_recorded().map(|date| date.year)),
+            genre: tag.genre_parsed().and_then(|genre| text(Some(&genre))),
+            duration: tag.duration().map(|millis| f64::from(millis) / 1000.0),
+            cover,
+        }
+    }
+
+    /// Ogg Vorbis/Opus 注释，编号可能写成 `3/12`。
+    fn from_vorbis(comments: &ogg::VorbisComments) -> Self {
+        let first = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| comments.get(key).find_map(|value| text(Some(value))))
+        };
+        let (track_number, track_total) = split_number(first(&["TRACKNUMBER"]));
+        let (disc_number, disc_total) = split_number(first(&["DISCNUMBER"]));
+        Self {
+            title: first(&["TITLE"]),
+            artists: texts(comments.get("ARTIST").collect()),
+            album: first(&["ALBUM"]),
+            album_artist: first(&["ALBUMARTIST", "ALBUM ARTIST"]),
+            track_number,
+            track_total: track_total.or(number(first(&["TRACKTOTAL", "TOTALTRACKS"]))),
+            disc_number,
+            disc_total: disc_total.or(number(first(&["DISCTOTAL", "TOTALDISCS"]))),
+            year: year(first(&["DATE", "YEAR"])),
+            genre: first(&["GENRE"]),
+            ..Self::default()
+        }
+    }
+
+    /// APEv2 标签，二进制封面条目为“文件名\0图片数据”。
+    fn from_ape(tag: &ape::ApeTag) -> Self {
+        let (track_number, track_total) = split_number(text(tag.get("Track")));
+        let (disc_number, disc_total) = split_number(text(tag.get("Disc")));
+        let cover = tag.binary("Cover Art (Front)").and_then(|value| {
+            let start = value.iter().position(|byte| *byte == 0)? + 1;
+            let data = value[start..].to_vec();
+            let mime = match data.as_slice() {
+                [0x89, b'P', b'N', b'G', ..] => "image/png",
+                _ => "image/jpeg",
+            };
+            Some((mime.to_string(), data))
+        });
+        Self {
+            title: text(tag.get("Title")),
+            artists: texts(tag.get("Artist").into_iter().collect()),
+            album: text(tag.get("Album")),
+            album_artist: text(tag.get("Album Artist")),
+            track_number,
+            track_total,
+            disc_number,
+            disc_total,
+            year: year(text(tag.get("Year"))),
+            genre: text(tag.get("Genre")),
+            cover,
+            ..Self::default()
+        }
+    }
+}
+
+/// 去掉首尾空白，空字符串视为没有值。
+fn text(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn texts(values: Vec<&str>) -> Vec<String> {
+    values
+        .into_iter()
+        .filter_map(|value| text(Some(value)))
+        .collect()
+}
+
+fn number(value: Option<String>) -> Option<u16> {
+    value?.parse().ok()
+}
+
+/// 拆分 `3/12` 形式的编号与总数。
+fn split_number(value: Option<String>) -> (Option<u16>, Option<u16>) {
+    let Some(value) = value else {
+        return (None, None);
+    };
+    match value.split_once('/') {
+        Some((number, total)) => (number.trim().parse().ok(), total.trim().parse().ok()),
+        None => (value.parse().ok(), None),
+    }
+}
+
+/// 从 `2004-05-01` 等日期中取年份。
+fn year(value: Option<String>) -> Option<i32> {
+    value?.get(..4)?.parse().ok()
+}
+
+/// 以内容摘要命名保存封面，已存在时直接复用。
+fn save_cover(dir: &Path, data: &[u8], mime: &str) -> Option<String> {
+    let extension = match mime {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        _ => "jpg",
+    };
+    let path = dir.join(format!("{:x}.{}", md5::compute(data), extension));
+    if !path.exists() {
+        std::fs::create_dir_all(dir).ok()?;
+        std::fs::write(&path, data).ok()?;
+    }
+    Some(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod testing {
+    use std::path::{Path, PathBuf};
+
+    /// 测试用的临时文件，离开作用域时删除。
+    pub struct TempFile(PathBuf);
+
+    impl TempFile {
+        pub fn new(extension: &str, data: &[u8]) -> Self {
+            let name = format!("musicboom-{}.{}", uuid::Uuid::new_v4(), extension);
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, data).expect("write temp file");
+            Self(path)
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.0
+        }
+
+        pub fn bytes(&self) -> Vec<u8> {
+            std::fs::read(&self.0).expect("read temp file")
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Ogg Vorbis/Opus 的注释头读写。
+//!
+//! 注释头所在的页按新长度重新分页，之后同一逻辑流的页序号顺延并重新计算校验和，
+//! 音频数据本身不变。
+
+use std::path::Path;
+
+/// Ogg 页校验和使用的 CRC-32 表（多项式 0x04c11db7，不反转）。
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// 逻辑流的编码，决定注释头的标记以及标识头之后还有几个头部包。
+#[derive(Debug, Clone, Copy)]
+enum Codec {
+    Vorbis,
+    Opus,
+}
+
+impl Codec {
+    fn detect(ident: &[u8]) -> Option<Self> {
+        if ident.starts_with(b"\x01vorbis") {
+            Some(Self::Vorbis)
+        } else if ident.starts_with(b"OpusHead") {
+            Some(Self::Opus)
+        } else {
+            None
+        }
+    }
+
+    fn comment_magic(self) -> &'static [u8] {
+        match self {
+            Self::Vorbis => b"\x03vorbis",
+            Self::Opus => b"OpusTags",
+        }
+    }
+
+    /// 标识头之后的头部包数量：Vorbis 为注释头与设置头，Opus 只有注释头。
+    fn extra_headers(self) -> usize {
+        match self {
+            Self::Vorbis => 2,
+            Self::Opus => 1,
+        }
+    }
+}
+
+/// Vorbis 注释块，字段名不区分大小写。
+#[derive(Debug, Clone)]
+pub struct VorbisComments {
+    vendor: Vec<u8>,
+    fields: Vec<String>,
+    /// 注释之后的内容（Vorbis 的帧标志位、Opus 的填充数据），原样保留。
+    trailer: Vec<u8>,
+}
+
+impl VorbisComments {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor { data, offset: 0 };
+        let vendor_len = cursor.u32()? as usize;
+        let vendor = cursor.take(vendor_len)?.to_vec();
+        let count = cursor.u32()?;
+        let mut fields = Vec::new();
+        for _ in 0..count {
+            let len = cursor.u32()? as usize;
+            fields.push(String::from_utf8_lossy(cursor.take(len)?).to_string());
+        }
+        Some(Self {
+            vendor,
+            fields,
+            trailer: data[cursor.offset..].to_vec(),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(self.vendor.len() as u32).to_le_bytes());
+        data.extend_from_slice(&self.vendor);
+        data.extend_from_slice(&(self.fields.len() as u32).to_le_bytes());
+        for field in &self.fields {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&self.trailer);
+        data
+    }
+
+    /// 指定字段的全部取值。
+    pub fn get<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields.iter().filter_map(move |field| {
+            let (name, value) = field.split_once('=')?;
+            name.eq_ignore_ascii_case(key).then_some(value)
+        })
+    }
+
+    /// 用新的取值替换指定字段，取值为空时删除该字段。
+    pub fn set(&mut self, key: &str, values: Vec<String>) {
+        let position = self.fields.iter().position(|field| is_field(field, key));
+        self.fields.retain(|field| !is_field(field, key));
+        let values = values.into_iter().map(|value| format!("{}={}", key, value));
+        let at = position.unwrap_or(self.fields.len()).min(self.fields.len());
+        self.fields.splice(at..at, values);
+    }
+}
+
+fn is_field(field: &str, key: &str) -> bool {
+    field
+        .split_once('=')
+        .is_some_and(|(name, _)| name.eq_ignore_ascii_case(key))
+}
+
+/// 读取 Ogg 文件首个逻辑流的注释。
+pub fn read_comments(path: &Path) -> Result<VorbisComments, String> {
+    let data = std::fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let headers = Headers::parse(&data)?;
+    Ok(headers.comments)
+}
+
+/// 修改 Ogg 文件首个逻辑流的注释并写回文件。
+pub fn write_comments(path: &Path, edit: impl FnOnce(&mut VorbisComments)) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let mut headers = Headers::parse(&data)?;
+    edit(&mut headers.comments);
+
+    let mut comment = headers.codec.comment_magic().to_vec();
+    comment.extend(headers.comments.to_bytes());
+    let mut packets = vec![comment];
+    packets.extend(headers.rest);
+    let pages = paginate(&packets, headers.serial, 1);
+    let delta = (pages.len() as u32).wrapping_sub(headers.pages);
+
+    let mut output = Vec::with_capacity(data.len() + 1024);
+    output.extend_from_slice(&data[..headers.start]);
+    for page in pages {
+        output.extend(page);
+    }
+    let mut offset = headers.end;
+    // 之后同一逻辑流的页序号顺延，其他逻辑流与无法识别的内容原样保留
+    while let Some(page) = Page::parse(&data, offset) {
+        let mut bytes = data[offset..page.end].to_vec();
+        if delta != 0 && page.serial == headers.serial {
+            let sequence = page.sequence.wrapping_add(delta);
+            bytes[18..22].copy_from_slice(&sequence.to_le_bytes());
+            seal(&mut bytes);
+        }
+        output.extend(bytes);
+        offset = page.end;
+    }
+    output.extend_from_slice(&data[offset..]);
+    std::fs::write(path, output).map_err(|e| format!("写入文件失败: {}", e))
+}
+
+/// 文件开头的头部包及其所在的页范围。
+struct Headers {
+    codec: Codec,
+    serial: u32,
+    comments: VorbisComments,
+    /// 注释头之后的其他头部包（Vorbis 的设置头）。
+    rest: Vec<Vec<u8>>,
+    /// 注释头所在首页的偏移。
+    start: usize,
+    /// 最后一个头部包所在页的结束偏移。
+    end: usize,
+    /// 注释头与其余头部包占用的页数。
+    pages: u32,
+}
+
+impl Headers {
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        let invalid = || "不是有效的 Ogg 文件".to_string();
+        let first = Page::parse(data, 0).ok_or_else(invalid)?;
+        let codec = Codec::detect(first.body(data)).ok_or("不支持的 Ogg 编码")?;
+
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+        let mut packet = Vec::new();
+        let mut offset = first.end;
+        let mut pages = 0;
+        while packets.len() < codec.extra_headers() {
+            let page = Page::parse(data, offset).ok_or_else(invalid)?;
+            if page.serial != first.serial {
+                return Err("不支持多路复用的 Ogg 文件".to_string());
+            }
+            let mut body = page.body_start;
+            for &lacing in &page.lacing {
+                if packets.len() == codec.extra_headers() {
+                    return Err("Ogg 头部页中混有音频数据".to_string());
+                }
+                packet.extend_from_slice(&data[body..body + lacing as usize]);
+                body += lacing as usize;
+                if lacing < 255 {
+                    packets.push(std::mem::take(&mut packet));
+                }
+            }
+            pages += 1;
+            offset = page.end;
+        }
+
+        let mut packets = packets.into_iter();
+        let comment = packets.next().ok_or_else(invalid)?;
+        let magic = codec.comment_magic();
+        let comments = comment
+            .strip_prefix(magic)
+            .and_then(VorbisComments::parse)
+            .ok_or("Ogg 注释头损坏")?;
+        Ok(Self {
+            codec,
+            serial: first.serial,
+            comments,
+            rest: packets.collect(),
+            start: first.end,
+            end: offset,
+            pages,
+        })
+    }
+}
+
+/// 一个 Ogg 页的位置信息。
+struct Page {
+    serial: u32,
+    sequence: u32,
+    lacing: Vec<u8>,
+    body_start: usize,
+    end: usize,
+}
+
+impl Page {
+    fn parse(data: &[u8], offset: usize) -> Option<Self> {
+        let header = data.get(offset..offset + 27)?;
+        if !header.starts_with(b"OggS") {
+            return None;
+        }
+        let segments = header[26] as usize;
+        let lacing = data.get(offset + 27..offset + 27 + segments)?.to_vec();
+        let body_start = offset + 27 + segments;
+        let end = body_start + lacing.iter().map(|&len| len as usize).sum::<usize>();
+        if end > data.len() {
+            return None;
+        }
+        Some(Self {
+            serial: u32::from_le_bytes(header[14..18].try_into().ok()?),
+            sequence: u32::from_le_bytes(header[18..22].try_into().ok()?),
+            lacing,
+            body_start,
+            end,
+        })
+    }
+
+    fn body<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.body_start..self.end]
+    }
+}
+
+/// 把头部包重新分页，有包结束的页颗粒位置为 0，否则为 -1。
+fn paginate(packets: &[Vec<u8>], serial: u32, first_sequence: u32) -> Vec<Vec<u8>> {
+    // (分段长度, 是否为包的最后一段)
+    let mut segments = Vec::new();
+    for packet in packets {
+        let full = packet.len() / 255;
+        segments.extend(std::iter::repeat_n((255u8, false), full));
+        segments.push(((packet.len() % 255) as u8, true));
+    }
+    let data: Vec<u8> = packets.concat();
+
+    let mut pages = Vec::new();
+    let mut offset = 0;
+    let mut continued = false;
+    for (index, chunk) in segments.chunks(255).enumerate() {
+        let body_len: usize = chunk.iter().map(|&(len, _)| len as usize).sum();
+        let completes = chunk.iter().any(|&(_, last)| last);
+        let granule = if completes { 0 } else { u64::MAX };
+        let mut page = Vec::with_capacity(27 + chunk.len() + body_len);
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(if continued { 0x01 } else { 0 });
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&(first_sequence + index as u32).to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(chunk.len() as u8);
+        page.extend(chunk.iter().map(|&(len, _)| len));
+        page.extend_from_slice(&data[offset..offset + body_len]);
+        seal(&mut page);
+        pages.push(page);
+        offset += body_len;
+        continued = chunk.last().is_some_and(|&(_, last)| !last);
+    }
+    pages
+}
+
+/// 重新计算页校验和。
+fn seal(page: &mut [u8]) {
+    page[22..26].fill(0);
+    let crc = page.iter().fold(0u32, |crc, &byte| {
+        (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    });
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::testing::TempFile;
+
+    const SERIAL: u32 = 0x1234_5678;
+
+    /// 单个包组成的页，按需设置标志位与颗粒位置。
+    fn page(packet: &[u8], sequence: u32, flags: u8, granule: u64) -> Vec<u8> {
+        let mut pages = paginate(&[packet.to_vec()], SERIAL, sequence);
+        assert_eq!(pages.len(), 1);
+        let mut page = pages.remove(0);
+        page[5] = flags;
+        page[6..14].copy_from_slice(&granule.to_le_bytes());
+        seal(&mut page);
+        page
+    }
+
+    fn comments(fields: &[&str], trailer: &[u8]) -> VorbisComments {
+        VorbisComments {
+            vendor: b"test vendor".to_vec(),
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            trailer: trailer.to_vec(),
+        }
+    }
+
+    /// 标识头、注释头与设置头之后跟两页音频的 Vorbis 文件。
+    fn vorbis() -> (Vec<u8>, Vec<u8>) {
+        let mut ident = b"\x01vorbis".to_vec();
+        ident.resize(30, 0);
+        let mut comment = b"\x03vorbis".to_vec();
+        comment.extend(comments(&["TITLE=Song", "COMMENT=note"], &[1]).to_bytes());
+        // 设置头超过 255 字节，跨越多个分段
+        let mut setup = b"\x05vorbis".to_vec();
+        setup.resize(600, 0xaa);
+
+        let mut data = page(&ident, 0, 0x02, 0);
+        for header in paginate(&[comment, setup.clone()], SERIAL, 1) {
+            data.extend(header);
+        }
+        data.extend(page(&[0x11; 100], 2, 0, 1000));
+        data.extend(page(&[0x22; 100], 3, 0x04, 2000));
+        (data, setup)
+    }
+
+    /// 校验每一页的校验和与页序号，返回各页的颗粒位置与内容。
+    fn check_pages(data: &[u8]) -> Vec<(u64, Vec<u8>)> {
+        let mut pages = Vec::new();
+        let mut offset = 0;
+        while let Some(page) = Page::parse(data, offset) {
+            let mut bytes = data[offset..page.end].to_vec();
+            seal(&mut bytes);
+            assert_eq!(bytes, &data[offset..page.end], "page {} crc", page.sequence);
+            assert_eq!(page.serial, SERIAL);
+            assert_eq!(page.sequence, pages.len() as u32);
+            let granule = u64::from_le_bytes(data[offset + 6..offset + 14].try_into().unwrap());
+            pages.push((granule, page.body(data).to_vec()));
+            offset = page.end;
+        }
+        assert_eq!(offset, data.len());
+        pages
+    }
+
+    #[test]
+    fn vorbis_round_trip() {
+        let (data, setup) = vorbis();
+        let original = check_pages(&data);
+        let file = TempFile::new("ogg", &data);
+
+        write_comments(file.path(), |comments| {
+            comments.set("APP_ANCHOR_ID", vec!["abc-123".to_string()]);
+            comments.set("title", vec!["新标题".to_string()]);
+        })
+        .unwrap();
+        let comments = read_comments(file.path()).unwrap();
+        assert_eq!(
+            comments.get("APP_ANCHOR_ID").collect::<Vec<_>>(),
+            ["abc-123"]
+        );
+        assert_eq!(comments.get("TITLE").collect::<Vec<_>>(), ["新标题"]);
+        assert_eq!(comments.get("comment").collect::<Vec<_>>(), ["note"]);
+        assert_eq!(comments.trailer, [1]);
+
+        let data = file.bytes();
+        let pages = check_pages(&data);
+        assert_eq!(pages.len(), original.len());
+        assert_eq!(pages[pages.len() - 2..], original[original.len() - 2..]);
+        assert_eq!(Headers::parse(&data).unwrap().rest, [setup]);
+    }
+
+    #[test]
+    fn vorbis_comment_spanning_pages() {
+        let (data, setup) = vorbis();
+        let original = check_pages(&data);
+        let file = TempFile::new("ogg", &data);
+
+        // 超过一页 255 个分段的容量，注释头需要分成多页
+        let lyrics = "x".repeat(70_000);
+        write_comments(file.path(), |comments| {
+            comments.set("LYRICS", vec![lyrics.clone()]);
+        })
+        .unwrap();
+        let data = file.bytes();
+        let pages = check_pages(&data);
+        assert!(pages.len() > original.len());
+        assert_eq!(pages[pages.len() - 2..], original[original.len() - 2..]);
+        let headers = Headers::parse(&data).unwrap();
+        assert_eq!(headers.comments.get("LYRICS").next(), Some(lyrics.as_str()));
+        assert_eq!(headers.rest, [setup]);
+
+        write_comments(file.path(), |comments| comments.set("LYRICS", Vec::new())).unwrap();
+        let data = file.bytes();
+        assert_eq!(check_pages(&data), original);
+        assert_eq!(read_comments(file.path()).unwrap().get("LYRICS").count(), 0);
+    }
+
+    #[test]
+    fn opus_round_trip() {
+        let mut head = b"OpusHead".to_vec();
+        head.resize(19, 0);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(comments(&["ARTIST=Someone"], &[0; 16]).to_bytes());
+        let mut data = page(&head, 0, 0x02, 0);
+        data.extend(page(&tags, 1, 0, 0));
+        data.extend(page(&[0x33; 50], 2, 0x04, 960));
+        let original = check_pages(&data);
+        let file = TempFile::new("opus", &data);
+
+        write_comments(file.path(), |comments| {
+            comments.set("APP_ANCHOR_ID", vec!["abc-123".to_string()]);
+        })
+        .unwrap();
+        let data = file.bytes();
+        let pages = check_pages(&data);
+        assert_eq!(pages.last(), original.last());
+        let headers = Headers::parse(&data).unwrap();
+        assert_eq!(headers.comments.get("ARTIST").next(), Some("Someone"));
+        assert_eq!(
+            headers.comments.get("APP_ANCHOR_ID").next(),
+            Some("abc-123")
+        );
+        assert_eq!(headers.comments.trailer, [0; 16]);
+        assert!(headers.rest.is_empty());
+    }
+}
@@ -0,0 +1,550 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// 查找 MPEG/ADTS 帧同步字时最多扫描的字节数。
+const FRAME_SCAN_LEN: usize = 64 * 1024;
+/// 读取 Ogg 末页颗粒位置时扫描的文件尾部长度。
+const OGG_TAIL_LEN: u64 = 64 * 1024;
+
+/// MPEG-1 Layer I/II/III 与 MPEG-2/2.5 Layer I、II/III 的比特率表（kbps）。
+const MPEG_BITRATES: [[u32; 16]; 5] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0,
+    ],
+    [
+        0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0,
+    ],
+];
+/// MPEG-1 的采样率，MPEG-2 减半，MPEG-2.5 再减半。
+const MPEG_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+/// AAC（ADTS）采样率索引表。
+const AAC_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+/// WavPack 采样率索引表。
+const WAVPACK_SAMPLE_RATES: [u32; 15] = [
+    6000, 8000, 9600, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000, 64000, 88200, 96000,
+    192000,
+];
+
+/// 从音频流头部解析出的技术参数，无法识别的格式各项为空。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Properties {
+    /// 时长（秒）。
+    pub duration: Option<f64>,
+    /// 平均比特率（kbps）。
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    /// 位深，有损格式为空。
+    pub bit_depth: Option<u8>,
+    pub channels: Option<u8>,
+}
+
+/// 按文件头识别格式并解析技术参数。
+pub fn read(file: &mut File, mime: &str) -> io::Result<Properties> {
+    let len = file.metadata()?.len();
+    let mut properties = match mime {
+        "audio/flac" => flac(file)?,
+        "audio/mpeg" => mpeg(file, len)?,
+        "audio/aac" => adts(file, 0, len)?,
+        "audio/wav" => wav(file)?,
+        "audio/aiff" => aiff(file)?,
+        "audio/ogg" | "audio/opus" => ogg(file, len)?,
+        "audio/mp4" => mp4(file, len)?,
+        "audio/x-dsf" => dsf(file)?,
+        "audio/x-wavpack" => wavpack(file)?,
+        _ => Properties::default(),
+    };
+    // 没有给出比特率的格式按文件大小估算
+    if properties.bitrate.is_none() {
+        properties.bitrate = properties
+            .duration
+            .filter(|duration| *duration > 0.0)
+            .map(|duration| (len as f64 * 8.0 / duration / 1000.0).round() as u32);
+    }
+    Ok(properties)
+}
+
+/// FLAC：首个元数据块 STREAMINFO。
+fn flac(file: &mut File) -> io::Result<Properties> {
+    let block = read_at(file, 4, 4 + 34)?;
+    if block.len() < 4 + 34 || block[0] & 0x7F != 0 {
+        return Ok(Properties::default());
+    }
+    let info = &block[4..];
+    let sample_rate =
+        (u32::from(info[10]) << 12) | (u32::from(info[11]) << 4) | (u32::from(info[12]) >> 4);
+    let channels = ((info[12] >> 1) & 0x07) + 1;
+    let bit_depth = (((info[12] & 0x01) << 4) | (info[13] >> 4)) + 1;
+    let samples = (u64::from(info[13] & 0x0F) << 32) | u64::from(be_u32(&info[14..18]));
+    Ok(Properties {
+        duration: duration(samples, sample_rate),
+        bitrate: None,
+        sample_rate: Some(sample_rate).filter(|rate| *rate > 0),
+        bit_depth: Some(bit_depth),
+        channels: Some(channels),
+    })
+}
+
+/// MP3：跳过 ID3v2 后解析首帧，优先使用 Xing/Info/VBRI 头中的总帧数。
+fn mpeg(file: &mut File, len: u64) -> io::Result<Properties> {
+    let head = read_at(file, 0, 10)?;
+    let mut offset = 0;
+    if head.starts_with(b"ID3") && head.len() == 10 {
+        let size = head[6..10]
+            .iter()
+            .fold(0u64, |size, byte| (size << 7) | u64::from(byte & 0x7F));
+        let footer = if head[5] & 0x10 != 0 { 10 } else { 0 };
+        offset = 10 + size + footer;
+    }
+
+    let window = read_at(file, offset, FRAME_SCAN_LEN)?;
+    let Some((position, frame)) = (0..window.len().saturating_sub(4))
+        .find_map(|i| MpegFrame::parse(&window[i..]).map(|frame| (i, frame)))
+    else {
+        // 可能是带 ID3 头的 ADTS 流
+        return adts(file, offset, len);
+    };
+    let audio_start = offset + position as u64;
+    let audio_len = len.saturating_sub(audio_start);
+    let data = &window[position..];
+
+    let frames = frame.xing_frames(data).or_else(|| frame.vbri_frames(data));
+    let (duration, bitrate) = match frames {
+        Some(frames) => {
+            let duration = frames as f64 * f64::from(frame.samples) / f64::from(frame.sample_rate);
+            let bitrate = (duration > 0.0)
+                .then(|| (audio_len as f64 * 8.0 / duration / 1000.0).round() as u32);
+            (Some(duration), bitrate)
+        }
+        None => (
+            Some(audio_len as f64 * 8.0 / (f64::from(frame.bitrate) * 1000.0)),
+            Some(frame.bitrate),
+        ),
+    };
+    Ok(Properties {
+        duration,
+        bitrate,
+        sample_rate: Some(frame.sample_rate),
+        bit_depth: None,
+        channels: Some(frame.channels),
+    })
+}
+
+/// MPEG 音频帧头。
+struct MpegFrame {
+    mpeg1: bool,
+    bitrate: u32,
+    sample_rate: u32,
+    channels: u8,
+    /// 每帧采样数。
+    samples: u32,
+}
+
+impl MpegFrame {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let [sync, b1, b2, b3, ..] = *data else {
+            return None;
+        };
+        if sync != 0xFF || b1 & 0xE0 != 0xE0 {
+            return None;
+        }
+        let version = (b1 >> 3) & 0x03;
+        let layer = (b1 >> 1) & 0x03;
+        let bitrate_index = usize::from(b2 >> 4);
+        let rate_index = usize::from((b2 >> 2) & 0x03);
+        if version == 1
+            || layer == 0
+            || bitrate_index == 0
+            || bitrate_index == 15
+            || rate_index == 3
+        {
+            return None;
+        }
+        let mpeg1 = version == 3;
+        let table = match (mpeg1, layer) {
+            (true, 3) => 0,
+            (true, 2) => 1,
+            (true, _) => 2,
+            (false, 3) => 3,
+            (false, _) => 4,
+        };
+        let divisor = match version {
+            3 => 1,
+            2 => 2,
+            _ => 4,
+        };
+        let samples = match (layer, mpeg1) {
+            (3, _) => 384,
+            (2, _) | (1, true) => 1152,
+            _ => 576,
+        };
+        Some(Self {
+            mpeg1,
+            bitrate: MPEG_BITRATES[table][bitrate_index],
+            sample_rate: MPEG_SAMPLE_RATES[rate_index] / divisor,
+            channels: if b3 >> 6 == 3 { 1 } else { 2 },
+            samples,
+        })
+    }
+
+    /// Xing/Info 头位于边信息之后。
+    fn xing_frames(&self, data: &[u8]) -> Option<u32> {
+        let side_info = match (self.mpeg1, self.channels) {
+            (true, 1) => 17,
+            (true, _) => 32,
+            (false, 1) => 9,
+            (false, _) => 17,
+        };
+        let tag = data.get(4 + side_info..4 + side_info + 12)?;
+        if &tag[..4] != b"Xing" && &tag[..4] != b"Info" {
+            return None;
+        }
+        // 标志位第 0 位表示带有总帧数
+        (tag[7] & 0x01 != 0).then(|| be_u32(&tag[8..12]))
+    }
+
+    /// VBRI 头固定位于帧头后 32 字节。
+    fn vbri_frames(&self, data: &[u8]) -> Option<u32> {
+        let tag = data.get(36..36 + 18)?;
+        (&tag[..4] == b"VBRI").then(|| be_u32(&tag[14..18]))
+    }
+}
+
+/// AAC（ADTS）：按首帧长度估算比特率与时长。
+fn adts(file: &mut File, offset: u64, len: u64) -> io::Result<Properties> {
+    let window = read_at(file, offset, FRAME_SCAN_LEN)?;
+    let Some(header) = window
+        .windows(7)
+        .find(|header| header[0] == 0xFF && header[1] & 0xF6 == 0xF0)
+    else {
+        return Ok(Properties::default());
+    };
+    let Some(&sample_rate) = AAC_SAMPLE_RATES.get(usize::from((header[2] >> 2) & 0x0F)) else {
+        return Ok(Properties::default());
+    };
+    let channels = ((header[2] & 0x01) << 2) | (header[3] >> 6);
+    let frame_len = (u32::from(header[3] & 0x03) << 11)
+        | (u32::from(header[4]) << 3)
+        | (u32::from(header[5]) >> 5);
+    // 每帧 1024 个采样
+    let bitrate = f64::from(frame_len) * 8.0 * f64::from(sample_rate) / 1024.0 / 1000.0;
+    let audio_len = len.saturating_sub(offset);
+    Ok(Properties {
+        duration: (bitrate > 0.0).then(|| audio_len as f64 * 8.0 / (bitrate * 1000.0)),
+        bitrate: Some(bitrate.round() as u32).filter(|bitrate| *bitrate > 0),
+        sample_rate: Some(sample_rate),
+        bit_depth: None,
+        channels: Some(channels).filter(|channels| *channels > 0),
+    })
+}
+
+/// WAV：`fmt ` 与 `data` 块。
+fn wav(file: &mut File) -> io::Result<Properties> {
+    let mut properties = Properties::default();
+    let mut byte_rate = 0;
+    let mut offset = 12;
+    while let Some((id, size)) = chunk_header(file, offset, false)? {
+        match &id {
+            b"fmt " => {
+                let fmt = read_at(file, offset + 8, 16)?;
+                if fmt.len() < 16 {
+                    break;
+                }
+                properties.channels = Some(le_u16(&fmt[2..4]) as u8);
+                properties.sample_rate = Some(le_u32(&fmt[4..8]));
+                byte_rate = le_u32(&fmt[8..12]);
+                properties.bit_depth = Some(le_u16(&fmt[14..16]) as u8);
+                properties.bitrate = Some(byte_rate * 8 / 1000);
+            }
+            b"data" => {
+                if byte_rate > 0 {
+                    properties.duration = Some(size as f64 / f64::from(byte_rate));
+                }
+                break;
+            }
+            _ => {}
+        }
+        // 块按偶数字节对齐
+        offset += 8 + size + (size & 1);
+    }
+    Ok(properties)
+}
+
+/// AIFF/AIFC：`COMM` 块，采样率为 80 位扩展精度浮点数。
+fn aiff(file: &mut File) -> io::Result<Properties> {
+    let mut offset = 12;
+    while let Some((id, size)) = chunk_header(file, offset, true)? {
+        if &id == b"COMM" {
+            let comm = read_at(file, offset + 8, 18)?;
+            if comm.len() < 18 {
+                break;
+            }
+            let channels = be_u16(&comm[0..2]);
+            let frames = be_u32(&comm[2..6]);
+            let bit_depth = be_u16(&comm[6..8]);
+            let sample_rate = extended_to_f64(&comm[8..18]).round() as u32;
+            return Ok(Properties {
+                duration: duration(u64::from(frames), sample_rate),
+                bitrate: Some(sample_rate * u32::from(channels) * u32::from(bit_depth) / 1000),
+                sample_rate: Some(sample_rate),
+                bit_depth: Some(bit_depth as u8),
+                channels: Some(channels as u8),
+            });
+        }
+        offset += 8 + size + (size & 1);
+    }
+    Ok(Properties::default())
+}
+
+/// Ogg Vorbis/Opus：首个数据包为标识头，时长取自最后一页的颗粒位置。
+fn ogg(file: &mut File, len: u64) -> io::Result<Properties> {
+    let page = read_at(file, 0, 27 + 255 + 30)?;
+    if page.len() < 28 {
+        return Ok(Properties::default());
+    }
+    let packet = &page[27 + usize::from(page[26])..];
+    let (sample_rate, channels, bitrate, pre_skip, granule_rate) =
+        if packet.len() >= 30 && packet.starts_with(b"\x01vorbis") {
+            let rate = le_u32(&packet[12..16]);
+            let nominal = le_u32(&packet[20..24]) as i32;
+            (
+                rate,
+                packet[11],
+                (nominal > 0).then_some(nominal as u32 / 1000),
+                0,
+                rate,
+            )
+        } else if packet.len() >= 19 && packet.starts_with(b"OpusHead") {
+            // 颗粒位置固定以 48 kHz 计
+            let input_rate = le_u32(&packet[12..16]);
+            let rate = if input_rate > 0 { input_rate } else { 48000 };
+            (
+                rate,
+                packet[9],
+                None,
+                u64::from(le_u16(&packet[10..12])),
+                48000,
+            )
+        } else {
+            return Ok(Properties::default());
+        };
+
+    let tail_start = len.saturating_sub(OGG_TAIL_LEN);
+    let tail = read_at(file, tail_start, OGG_TAIL_LEN as usize)?;
+    let granule = tail
+        .windows(14)
+        .rev()
+        .find(|page| page.starts_with(b"OggS"))
+        .map(|page| u64::from_le_bytes(page[6..14].try_into().unwrap_or_default()));
+    Ok(Properties {
+        duration: granule
+            .and_then(|granule| duration(granule.saturating_sub(pre_skip), granule_rate)),
+        bitrate,
+        sample_rate: Some(sample_rate),
+        bit_depth: None,
+        channels: Some(channels),
+    })
+}
+
+/// MP4/M4A：`moov/mvhd` 的时长与首个音频采样描述（`stsd`）。
+fn mp4(file: &mut File, len: u64) -> io::Result<Properties> {
+    let mut properties = Properties::default();
+    let Some((moov, moov_len)) = find_atom(file, 0, len, b"moov")? else {
+        return Ok(properties);
+    };
+    if let Some((mvhd, _)) = find_atom(file, moov, moov_len, b"mvhd")? {
+        let body = read_at(file, mvhd, 32)?;
+        if body.len() >= 32 {
+            let (timescale, length) = if body[0] == 1 {
+                (
+                    be_u32(&body[20..24]),
+                    u64::from_be_bytes(body[24..32].try_into().unwrap_or_default()),
+                )
+            } else {
+                (be_u32(&body[12..16]), u64::from(be_u32(&body[16..20])))
+            };
+            properties.duration = duration(length, timescale);
+        }
+    }
+
+    // moov/trak/mdia/minf/stbl/stsd，取第一个能解析出音频采样描述的轨道
+    let mut trak_offset = moov;
+    while let Some((trak, trak_len)) =
+        find_atom(file, trak_offset, moov + moov_len - trak_offset, b"trak")?
+    {
+        trak_offset = trak + trak_len;
+        let mut path = Some((trak, trak_len));
+        for name in [b"mdia", b"minf", b"stbl", b"stsd"] {
+            path = match path {
+                Some((start, len)) => find_atom(file, start, len, name)?,
+                None => None,
+            };
+        }
+        let Some((stsd, _)) = path else {
+            continue;
+        };
+        // stsd：版本与标志 4 字节、条目数 4 字节，随后是第一个采样描述
+        let entry = read_at(file, stsd + 8, 36)?;
+        if entry.len() < 36
+            || !matches!(
+                &entry[4..8],
+                b"mp4a" | b"alac" | b"fLaC" | b"Opus" | b"ac-3" | b"ec-3"
+            )
+        {
+            continue;
+        }
+        let channels = be_u16(&entry[24..26]);
+        let bit_depth = be_u16(&entry[26..28]);
+        let sample_rate = be_u32(&entry[32..36]) >> 16;
+        properties.channels = Some(channels as u8);
+        properties.sample_rate = Some(sample_rate);
+        // AAC 等有损编码的采样大小字段固定为 16，不代表位深
+        properties.bit_depth = matches!(&entry[4..8], b"alac" | b"fLaC").then_some(bit_depth as u8);
+        break;
+    }
+    Ok(properties)
+}
+
+/// DSF：`fmt ` 块位于 28 字节的 `DSD ` 块之后。
+fn dsf(file: &mut File) -> io::Result<Properties> {
+    let fmt = read_at(file, 28, 52)?;
+    if fmt.len() < 52 || !fmt.starts_with(b"fmt ") {
+        return Ok(Properties::default());
+    }
+    let channels = le_u32(&fmt[24..28]);
+    let sample_rate = le_u32(&fmt[28..32]);
+    let bit_depth = le_u32(&fmt[32..36]);
+    let samples = u64::from_le_bytes(fmt[36..44].try_into().unwrap_or_default());
+    Ok(Properties {
+        duration: duration(samples, sample_rate),
+        bitrate: Some(sample_rate / 1000 * channels),
+        sample_rate: Some(sample_rate),
+        bit_depth: Some(bit_depth as u8),
+        channels: Some(channels as u8),
+    })
+}
+
+/// WavPack：首个块头中的总采样数与标志位。
+fn wavpack(file: &mut File) -> io::Result<Properties> {
+    let header = read_at(file, 0, 32)?;
+    if header.len() < 32 {
+        return Ok(Properties::default());
+    }
+    let samples = le_u32(&header[12..16]);
+    let flags = le_u32(&header[24..28]);
+    let bit_depth = ((flags & 0x03) + 1) * 8;
+    let channels = if flags & 0x04 != 0 { 1 } else { 2 };
+    let sample_rate = WAVPACK_SAMPLE_RATES
+        .get(((flags >> 23) & 0x0F) as usize)
+        .copied();
+    Ok(Properties {
+        // 总采样数为 0xFFFFFFFF 表示未知
+        duration: sample_rate
+            .filter(|_| samples != u32::MAX)
+            .and_then(|rate| duration(u64::from(samples), rate)),
+        bitrate: None,
+        sample_rate,
+        bit_depth: Some(bit_depth as u8),
+        channels: Some(channels),
+    })
+}
+
+/// 读取 RIFF（小端）或 IFF（大端）块头。
+fn chunk_header(
+    file: &mut File,
+    offset: u64,
+    big_endian: bool,
+) -> io::Result<Option<([u8; 4], u64)>> {
+    let header = read_at(file, offset, 8)?;
+    if header.len() < 8 {
+        return Ok(None);
+    }
+    let id = [header[0], header[1], header[2], header[3]];
+    let size = if big_endian {
+        be_u32(&header[4..8])
+    } else {
+        le_u32(&header[4..8])
+    };
+    Ok(Some((id, u64::from(size))))
+}
+
+/// 在 `[start, start + len)` 范围内查找 MP4 子盒，返回盒体的起始位置与长度。
+fn find_atom(
+    file: &mut File,
+    start: u64,
+    len: u64,
+    name: &[u8; 4],
+) -> io::Result<Option<(u64, u64)>> {
+    let end = start + len;
+    let mut offset = start;
+    while offset + 8 <= end {
+        let header = read_at(file, offset, 16)?;
+        if header.len() < 8 {
+            break;
+        }
+        let (size, header_len) = match be_u32(&header[0..4]) {
+            0 => (end - offset, 8),
+            1 if header.len() >= 16 => (
+                u64::from_be_bytes(header[8..16].try_into().unwrap_or_default()),
+                16,
+            ),
+            size => (u64::from(size), 8),
+        };
+        if size < header_len {
+            break;
+        }
+        if &header[4..8] == name {
+            return Ok(Some((offset + header_len, size - header_len)));
+        }
+        offset += size;
+    }
+    Ok(None)
+}
+
+/// 从 `offset` 起最多读取 `len` 字节，文件较短时返回实际读到的部分。
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn duration(samples: u64, sample_rate: u32) -> Option<f64> {
+    (sample_rate > 0 && samples > 0).then(|| samples as f64 / f64::from(sample_rate))
+}
+
+/// IEEE 754 80 位扩展精度浮点数（AIFF 采样率）。
+fn extended_to_f64(bytes: &[u8]) -> f64 {
+    let exponent = i32::from(be_u16(&bytes[0..2]) & 0x7FFF);
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap_or_default());
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+fn be_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn le_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
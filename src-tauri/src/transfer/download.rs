@@ -0,0 +1,267 @@
+use super::ProgressReporter;
+use crate::app_state::{AppState, QueueSource, QueueTask};
+use crate::commands::subsonic_client;
+use crate::media::cache;
+use crate::openlist;
+use crate::retry::Failure;
+use crate::subsonic::StreamOptions;
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+/// 执行一次下载任务，已存在的 `.part` 会作为断点继续；
+/// 网络中断等可重试的失败由调度器退避后再次调用，从断点续传。
+pub async fn run_download(app: &AppHandle, task: &QueueTask) -> Result<(), Failure> {
+    let reporter = ProgressReporter::new(app.clone(), task.id.clone());
+    match task.source {
+        QueueSource::Openlist => download_openlist(app, task, &reporter).await,
+        QueueSource::Navidrome => download_song(app, task, &reporter).await,
+    }
+}
+
+/// OpenList 文件：先解析直链，再按远端大小续传。
+async fn download_openlist(
+    app: &AppHandle,
+    task: &QueueTask,
+    reporter: &ProgressReporter,
+) -> Result<(), Failure> {
+    let client = openlist::session_client_for(app, &task.server)?;
+    let detail = client
+        .get(&task.remote_path, task.password.as_deref())
+        .await?;
+    if detail.is_dir {
+        return Err(Failure::fatal("不支持直接下载文件夹"));
+    }
+    let url = client.download_url(&task.remote_path, &detail)?;
+    let expected = (detail.size > 0).then_some(detail.size);
+    download_file(
+        client.http(),
+        url,
+        Path::new(&task.local_path),
+        expected,
+        reporter,
+    )
+    .await
+}
+
+/// Navidrome 歌曲：在线播放缓存中已有完整文件时直接复制，否则从 stream 接口续传。
+async fn download_song(
+    app: &AppHandle,
+    task: &QueueTask,
+    reporter: &ProgressReporter,
+) -> Result<(), Failure> {
+    let app_state = app.state::<AppState>();
+    let target = Path::new(&task.local_path);
+    let cache_dir = app_state
+        .media_roots
+        .lock()
+        .expect("媒体目录锁定失败")
+        .cache
+        .clone();
+    let cached = cache_dir
+        .and_then(|dir| cache::cache_file(&dir, &task.remote_path))
+        .filter(|path| path.is_file());
+    if let Some(cached) = cached {
+        return copy_cached(&cached, target, reporter).await;
+    }
+
+    let client = subsonic_client(&app_state, &task.server)?;
+    let url = client.stream_url(&task.remote_path, &StreamOptions::default())?;
+    download_file(&app_state.http, url, target, None, reporter).await
+}
+
+/// 从在线播放缓存复制到 `.part`，再与下载一样重命名为目标文件。
+async fn copy_cached(
+    cached: &Path,
+    target: &Path,
+    reporter: &ProgressReporter,
+) -> Result<(), Failure> {
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("创建下载目录失败: {}", e))?;
+    }
+    let part = part_path(target);
+    let size = tokio::fs::copy(cached, &part)
+        .await
+        .map_err(|e| format!("复制缓存文件失败: {}", e))?;
+    reporter.report(size, size);
+    tokio::fs::rename(&part, target)
+        .await
+        .map_err(|e| format!("重命名下载文件失败: {}", e))?;
+    Ok(())
+}
+
+/// `.part` 临时文件路径，下载完成后再原子重命名为目标文件。
+pub fn part_path(target: &Path) -> PathBuf {
+    with_suffix(target, ".part")
+}
+
+/// 记录 `.part` 对应远端版本（ETag 或 Last-Modified）的文件，续传时用于 `If-Range`。
+fn validator_path(target: &Path) -> PathBuf {
+    with_suffix(target, ".part.validator")
+}
+
+fn with_suffix(target: &Path, suffix: &str) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    target.with_file_name(name)
+}
+
+/// 删除未完成的 `.part` 及其版本记录。
+pub fn remove_partial(target: &Path) {
+    let _ = std::fs::remove_file(part_path(target));
+    let _ = std::fs::remove_file(validator_path(target));
+}
+
+/// 断点续传下载：从 `.part` 的现有长度继续请求 Range，完成后校验大小并重命名。
+async fn download_file(
+    http: &reqwest::Client,
+    url: Url,
+    target: &Path,
+    expected: Option<u64>,
+    reporter: &ProgressReporter,
+) -> Result<(), Failure> {
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("创建下载目录失败: {}", e))?;
+    }
+
+    let part = part_path(target);
+    let total = fetch_range(http, &url, target, expected, reporter).await?;
+    let written = file_len(&part).await;
+    if written != total {
+        return Err(Failure::fatal(format!(
+            "文件大小不一致：期望 {} 字节，实际 {} 字节",
+            total, written
+        )));
+    }
+    tokio::fs::rename(&part, target)
+        .await
+        .map_err(|e| format!("重命名下载文件失败: {}", e))?;
+    let _ = tokio::fs::remove_file(validator_path(target)).await;
+    Ok(())
+}
+
+/// 读取文件当前长度，不存在时视为 0。
+async fn file_len(path: &Path) -> u64 {
+    tokio::fs::metadata(path)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or(0)
+}
+
+/// 从 `.part` 的末尾发起一次 Range 请求并追加写入，返回文件总大小；
+/// 中断时 `.part` 中已写入的数据会保留。远端文件已变化时 `If-Range` 使服务器返回完整内容，
+/// 此时从头重新下载。
+async fn fetch_range(
+    http: &reqwest::Client,
+    url: &Url,
+    target: &Path,
+    expected: Option<u64>,
+    reporter: &ProgressReporter,
+) -> Result<u64, Failure> {
+    let part = part_path(target);
+    let mut offset = file_len(&part).await;
+    match expected {
+        Some(size) if offset == size => return Ok(size),
+        // 比远端文件还大，说明 `.part` 已损坏，丢弃后从头下载
+        Some(size) if offset > size => {
+            remove_partial(target);
+            offset = 0;
+        }
+        _ => {}
+    }
+
+    let mut request = http.get(url.clone());
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        if let Ok(validator) = tokio::fs::read_to_string(validator_path(target)).await {
+            request = request.header(IF_RANGE, validator.trim());
+        }
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| Failure::from_request(format!("请求下载地址失败: {}", e), &e))?;
+
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+        // 服务器认为已无剩余内容，交由上层按大小校验
+        return Ok(expected.unwrap_or(offset));
+    }
+    if !status.is_success() {
+        return Err(Failure::from_status(
+            format!("下载地址返回 HTTP {}", status),
+            status,
+            response.headers(),
+        ));
+    }
+    if status != StatusCode::PARTIAL_CONTENT {
+        // 服务器不支持 Range 或远端文件已变化，只能从头开始
+        offset = 0;
+    }
+    match validator(response.headers()) {
+        Some(validator) => {
+            let _ = tokio::fs::write(validator_path(target), validator).await;
+        }
+        None => {
+            let _ = tokio::fs::remove_file(validator_path(target)).await;
+        }
+    }
+
+    let remaining = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    let total = expected.or(remaining.map(|len| offset + len)).unwrap_or(0);
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(offset > 0)
+        .write(true)
+        .truncate(offset == 0)
+        .open(&part)
+        .await
+        .map_err(|e| format!("打开临时文件失败: {}", e))?;
+
+    let mut written = offset;
+    reporter.report(written, total);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let bytes = chunk.map_err(|e| Failure::transient(format!("下载连接中断: {}", e)))?;
+        file.write_all(&bytes)
+            .await
+            .map_err(|e| format!("写入临时文件失败: {}", e))?;
+        written += bytes.len() as u64;
+        reporter.report(written, total);
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("写入临时文件失败: {}", e))?;
+    // 重命名前落盘，避免崩溃后留下大小正确但内容不完整的文件
+    file.sync_all()
+        .await
+        .map_err(|e| format!("写入临时文件失败: {}", e))?;
+
+    Ok(if total > 0 { total } else { written })
+}
+
+/// 用于 `If-Range` 的远端版本：强 ETag 优先，否则为 Last-Modified。
+fn validator(headers: &HeaderMap) -> Option<String> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+}
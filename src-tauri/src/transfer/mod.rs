@@ -0,0 +1,126 @@
+mod download;
+mod scheduler;
+mod store;
+mod upload;
+
+use download::{remove_partial, run_download};
+use upload::run_upload;
+
+pub use scheduler::Scheduler;
+
+use crate::app_state::{AppState, QueueStatus, QueueTask};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 传输进度事件名，载荷为 [`ProgressEvent`]。
+pub const PROGRESS_EVENT: &str = "queue://progress";
+/// 任务状态变化事件名，载荷为最新的 [`QueueTask`]。
+pub const STATE_EVENT: &str = "queue://state";
+/// 任务结束（完成、失败或取消）事件名，载荷为最新的 [`QueueTask`]。
+pub const FINISHED_EVENT: &str = "queue://finished";
+
+/// 两次进度推送之间的最小间隔。
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+/// 速度平滑系数，越大越贴近瞬时速度。
+const SPEED_SMOOTHING: f64 = 0.3;
+
+/// 推送给前端的传输进度。
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    pub id: String,
+    pub transferred: u64,
+    pub total: u64,
+    /// 平滑后的速度（字节/秒）。
+    pub speed: f64,
+    /// 预计剩余秒数，速度未知时为空。
+    pub eta: Option<f64>,
+}
+
+/// 单个任务的进度汇报器：按固定间隔更新队列中的任务并向前端推送事件。
+pub struct ProgressReporter {
+    app: AppHandle,
+    id: String,
+    window: Mutex<SpeedWindow>,
+}
+
+/// 上一次推送时的采样点。
+#[derive(Default)]
+struct SpeedWindow {
+    last: Option<(Instant, u64)>,
+    speed: f64,
+}
+
+impl ProgressReporter {
+    pub fn new(app: AppHandle, id: String) -> Self {
+        Self {
+            app,
+            id,
+            window: Mutex::new(SpeedWindow::default()),
+        }
+    }
+
+    /// 汇报已传输字节数；未到推送间隔时直接忽略，传输完成时总会推送。
+    pub fn report(&self, transferred: u64, total: u64) {
+        let now = Instant::now();
+        let speed = {
+            let mut window = self.window.lock().expect("进度锁定失败");
+            match window.last {
+                // 第一次采样只记录起点（续传时起点不为 0）
+                None => window.speed = 0.0,
+                Some((at, _)) if now - at < PROGRESS_INTERVAL && transferred < total => return,
+                Some((at, bytes)) => {
+                    let elapsed = (now - at).as_secs_f64().max(0.001);
+                    let current = transferred.saturating_sub(bytes) as f64 / elapsed;
+                    window.speed = if window.speed > 0.0 {
+                        window.speed * (1.0 - SPEED_SMOOTHING) + current * SPEED_SMOOTHING
+                    } else {
+                        current
+                    };
+                }
+            }
+            window.last = Some((now, transferred));
+            window.speed
+        };
+
+        update_task(&self.app, &self.id, |task| {
+            task.transferred = transferred;
+            task.total = total;
+        });
+
+        let eta =
+            (speed > 0.0 && total > transferred).then(|| (total - transferred) as f64 / speed);
+        let _ = self.app.emit(
+            PROGRESS_EVENT,
+            ProgressEvent {
+                id: self.id.clone(),
+                transferred,
+                total,
+                speed,
+                eta,
+            },
+        );
+    }
+}
+
+/// 在队列锁内修改指定任务，并交给后台写入数据库。
+pub(crate) fn update_task(app: &AppHandle, id: &str, apply: impl FnOnce(&mut QueueTask)) {
+    let app_state = app.state::<AppState>();
+    let mut queues = app_state.queues.lock().expect("队列锁定失败");
+    if let Some(task) = queues.iter_mut().find(|task| task.id == id) {
+        apply(task);
+        app_state.scheduler.persist(id);
+    }
+}
+
+/// 推送任务状态变化，任务结束时额外推送结束事件。
+fn emit_state(app: &AppHandle, task: &QueueTask) {
+    let _ = app.emit(STATE_EVENT, task.clone());
+    if matches!(
+        task.status,
+        QueueStatus::Done | QueueStatus::Failed | QueueStatus::Cancelled
+    ) {
+        let _ = app.emit(FINISHED_EVENT, task.clone());
+    }
+}
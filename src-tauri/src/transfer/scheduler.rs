@@ -0,0 +1,359 @@
+use super::{emit_state, run_download, run_upload, store};
+use crate::app_state::{AppState, QueueAttempt, QueueKind, QueueStatus, QueueTask};
+use crate::credentials::{Credential, CredentialKind};
+use crate::db::now_millis;
+use crate::retry::{Failure, RetryPolicy};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+/// 同时进行的下载任务数。
+const MAX_DOWNLOADS: usize = 3;
+/// 同时进行的上传任务数。
+const MAX_UPLOADS: usize = 2;
+/// 加密目录密码在凭据存储中的 `key_ref` 前缀，后接任务 ID。
+const PASSWORD_REF_PREFIX: &str = "queue-password:";
+
+/// 上传/下载调度器：按类型限制并发，通过取消令牌中断正在进行的 I/O。
+pub struct Scheduler {
+    downloads: Arc<Semaphore>,
+    uploads: Arc<Semaphore>,
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+    persist_tx: OnceLock<UnboundedSender<String>>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            downloads: Arc::new(Semaphore::new(MAX_DOWNLOADS)),
+            uploads: Arc::new(Semaphore::new(MAX_UPLOADS)),
+            tokens: Mutex::new(HashMap::new()),
+            persist_tx: OnceLock::new(),
+        }
+    }
+}
+
+impl Scheduler {
+    /// 从数据库恢复队列并启动写入任务，排队中的任务会继续执行。
+    pub async fn restore(&self, app: &AppHandle, pool: SqlitePool) -> Result<(), String> {
+        let mut tasks = store::load_tasks(&pool).await?;
+        // 加密目录密码不写入数据库，从凭据存储取回，重启后的下载仍可继续
+        let credentials = &app.state::<AppState>().credentials;
+        for task in tasks.iter_mut().filter(|task| {
+            task.kind == QueueKind::Download
+                && !matches!(task.status, QueueStatus::Done | QueueStatus::Cancelled)
+        }) {
+            task.password = credentials
+                .load(&password_ref(&task.id))
+                .ok()
+                .flatten()
+                .map(|credential| credential.secret);
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        if self.persist_tx.set(tx).is_err() {
+            return Err("传输队列已恢复过".to_string());
+        }
+        tauri::async_runtime::spawn(persist_loop(app.clone(), pool, rx));
+
+        let queued: Vec<String> = tasks
+            .iter()
+            .filter(|task| task.status == QueueStatus::Queued)
+            .map(|task| task.id.clone())
+            .collect();
+        let ids: Vec<String> = tasks.iter().map(|task| task.id.clone()).collect();
+        app.state::<AppState>()
+            .queues
+            .lock()
+            .expect("队列锁定失败")
+            .extend(tasks);
+        // 运行中的任务已在读取时改为暂停，这里回写一次
+        for id in &ids {
+            self.persist(id);
+        }
+        for id in queued {
+            self.spawn(app, id);
+        }
+        Ok(())
+    }
+
+    /// 通知后台写入任务保存最新快照（任务已移除时删除记录）。
+    pub fn persist(&self, id: &str) {
+        if let Some(tx) = self.persist_tx.get() {
+            let _ = tx.send(id.to_string());
+        }
+    }
+
+    /// 加入队列并立即排队执行。
+    pub fn enqueue(&self, app: &AppHandle, task: QueueTask) {
+        let id = task.id.clone();
+        emit_state(app, &task);
+        let app_state = app.state::<AppState>();
+        if let Some(password) = &task.password {
+            let credential = Credential {
+                kind: CredentialKind::Password,
+                secret: password.clone(),
+            };
+            let _ = app_state.credentials.store(&password_ref(&id), credential);
+        }
+        app_state.queues.lock().expect("队列锁定失败").push(task);
+        self.persist(&id);
+        self.spawn(app, id);
+    }
+
+    /// 移除已完成或已取消的任务记录，连同保存的加密目录密码。
+    pub fn clear_finished(&self, app: &AppHandle) {
+        let app_state = app.state::<AppState>();
+        let removed: Vec<QueueTask> = {
+            let mut queues = app_state.queues.lock().expect("队列锁定失败");
+            let (finished, remaining): (Vec<QueueTask>, Vec<QueueTask>) =
+                queues.drain(..).partition(|task| {
+                    matches!(task.status, QueueStatus::Done | QueueStatus::Cancelled)
+                });
+            *queues = remaining;
+            finished
+        };
+        for task in &removed {
+            // 上次运行中结束的任务没有读取密码，按类型清理
+            if task.kind == QueueKind::Download {
+                let _ = app_state.credentials.remove(&password_ref(&task.id));
+            }
+            self.persist(&task.id);
+        }
+    }
+
+    /// 暂停排队中或进行中的任务，已写入的 `.part` 数据会保留。
+    pub fn pause(&self, app: &AppHandle, id: &str) {
+        let paused = transition(app, id, |task| match task.status {
+            QueueStatus::Queued | QueueStatus::Running => {
+                task.status = QueueStatus::Paused;
+                true
+            }
+            _ => false,
+        });
+        if paused {
+            self.stop(id);
+        }
+    }
+
+    /// 恢复暂停或失败的任务，下载会从 `.part` 断点继续，重试次数重新计算。
+    pub fn resume(&self, app: &AppHandle, id: &str) {
+        let resumed = transition(app, id, |task| match task.status {
+            QueueStatus::Paused | QueueStatus::Failed => {
+                task.status = QueueStatus::Queued;
+                task.error = None;
+                task.retries = 0;
+                task.attempts.clear();
+                true
+            }
+            _ => false,
+        });
+        if resumed {
+            self.spawn(app, id.to_string());
+        }
+    }
+
+    /// 取消任务并中断 I/O，同时清理未完成的临时文件。
+    pub fn cancel(&self, app: &AppHandle, id: &str) {
+        let cancelled = transition(app, id, |task| match task.status {
+            QueueStatus::Done | QueueStatus::Cancelled => false,
+            _ => {
+                task.status = QueueStatus::Cancelled;
+                true
+            }
+        });
+        if !cancelled {
+            return;
+        }
+        self.stop(id);
+
+        let task = find_task(app, id);
+        if let Some(task) = task.filter(|task| task.kind == QueueKind::Download) {
+            super::remove_partial(std::path::Path::new(&task.local_path));
+        }
+    }
+
+    /// 触发任务的取消令牌。
+    fn stop(&self, id: &str) {
+        if let Some(token) = self.tokens.lock().expect("调度器锁定失败").remove(id) {
+            token.cancel();
+        }
+    }
+
+    /// 为任务创建取消令牌并在后台执行。
+    fn spawn(&self, app: &AppHandle, id: String) {
+        let token = CancellationToken::new();
+        if let Some(previous) = self
+            .tokens
+            .lock()
+            .expect("调度器锁定失败")
+            .insert(id.clone(), token.clone())
+        {
+            previous.cancel();
+        }
+        tauri::async_runtime::spawn(run(app.clone(), id, token));
+    }
+
+    /// 按任务类型选择并发信号量。
+    fn limiter(&self, kind: QueueKind) -> Arc<Semaphore> {
+        match kind {
+            QueueKind::Download => self.downloads.clone(),
+            QueueKind::Upload => self.uploads.clone(),
+        }
+    }
+
+    /// 正常结束的任务移除自己的令牌；已被取消的令牌由暂停/取消流程处理。
+    fn finish(&self, id: &str, token: &CancellationToken) {
+        if !token.is_cancelled() {
+            self.tokens.lock().expect("调度器锁定失败").remove(id);
+        }
+    }
+}
+
+/// 等待并发名额后执行任务，暂停或取消时直接丢弃进行中的请求。
+async fn run(app: AppHandle, id: String, token: CancellationToken) {
+    let Some(task) = find_task(&app, &id) else {
+        return;
+    };
+    let app_state = app.state::<AppState>();
+    let limiter = app_state.scheduler.limiter(task.kind);
+
+    let permit = tokio::select! {
+        permit = limiter.acquire_owned() => permit,
+        _ = token.cancelled() => return,
+    };
+    let Ok(_permit) = permit else {
+        return;
+    };
+
+    let started = transition(&app, &id, |task| {
+        let queued = task.status == QueueStatus::Queued;
+        if queued {
+            task.status = QueueStatus::Running;
+        }
+        queued
+    });
+    if !started {
+        return;
+    }
+
+    let policy = RetryPolicy::TRANSFER;
+    let mut attempt = 1;
+    let result = loop {
+        let job = async {
+            match task.kind {
+                QueueKind::Upload => run_upload(&app, &task).await,
+                QueueKind::Download => run_download(&app, &task).await,
+            }
+        };
+        let result = tokio::select! {
+            result = job => result,
+            _ = token.cancelled() => return,
+        };
+        match result {
+            Err(failure) if failure.retryable && policy.allows(attempt) => {
+                let delay = policy.delay(attempt, failure.retry_after);
+                record_attempt(&app, &id, attempt, &failure, Some(delay));
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = token.cancelled() => return,
+                }
+                attempt += 1;
+            }
+            Err(failure) => {
+                record_attempt(&app, &id, attempt, &failure, None);
+                break Err(failure.message);
+            }
+            Ok(()) => break Ok(()),
+        }
+    };
+
+    transition(&app, &id, |task| {
+        if task.status != QueueStatus::Running {
+            return false;
+        }
+        match result {
+            Ok(()) => task.status = QueueStatus::Done,
+            Err(e) => {
+                task.status = QueueStatus::Failed;
+                task.error = Some(e);
+            }
+        }
+        true
+    });
+    app_state.scheduler.finish(&id, &token);
+}
+
+/// 记录一次失败的尝试；`retry_in` 为空表示不再重试。
+fn record_attempt(
+    app: &AppHandle,
+    id: &str,
+    attempt: u32,
+    failure: &Failure,
+    retry_in: Option<Duration>,
+) {
+    transition(app, id, |task| {
+        if task.status != QueueStatus::Running {
+            return false;
+        }
+        if retry_in.is_some() {
+            task.retries += 1;
+        }
+        task.error = Some(failure.message.clone());
+        task.attempts.push(QueueAttempt {
+            attempt,
+            error: failure.message.clone(),
+            at: now_millis(),
+            retry_in: retry_in.map(|delay| delay.as_millis() as u64),
+        });
+        true
+    });
+}
+
+/// 任务的加密目录密码在凭据存储中的引用。
+fn password_ref(id: &str) -> String {
+    format!("{}{}", PASSWORD_REF_PREFIX, id)
+}
+
+/// 读取任务快照。
+fn find_task(app: &AppHandle, id: &str) -> Option<QueueTask> {
+    app.state::<AppState>()
+        .queues
+        .lock()
+        .expect("队列锁定失败")
+        .iter()
+        .find(|task| task.id == id)
+        .cloned()
+}
+
+/// 在锁内按规则切换状态；发生切换时落盘并推送状态事件。
+fn transition(app: &AppHandle, id: &str, apply: impl FnOnce(&mut QueueTask) -> bool) -> bool {
+    let app_state = app.state::<AppState>();
+    let snapshot = {
+        let mut queues = app_state.queues.lock().expect("队列锁定失败");
+        let Some(task) = queues.iter_mut().find(|task| task.id == id) else {
+            return false;
+        };
+        if !apply(task) {
+            return false;
+        }
+        task.clone()
+    };
+    app_state.scheduler.persist(id);
+    emit_state(app, &snapshot);
+    true
+}
+
+/// 串行写入数据库，保证同一任务的快照按顺序落盘。
+async fn persist_loop(app: AppHandle, pool: SqlitePool, mut rx: UnboundedReceiver<String>) {
+    while let Some(id) = rx.recv().await {
+        let _ = match find_task(&app, &id) {
+            Some(task) => store::save_task(&pool, &task).await,
+            None => store::delete_task(&pool, &id).await,
+        };
+    }
+}
@@ -0,0 +1,141 @@
+use crate::app_state::{QueueKind, QueueSource, QueueStatus, QueueTask};
+use crate::db::now_millis;
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+/// 读取全部任务；上次退出时仍在运行的任务恢复为暂停，等待用户继续。
+pub async fn load_tasks(pool: &SqlitePool) -> Result<Vec<QueueTask>, String> {
+    let rows = sqlx::query(
+        "SELECT id, kind, filename, local_path, remote_path, server, source, status,
+                transferred, total, retries, attempts, error, as_task
+         FROM queue_tasks ORDER BY created_at",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("读取传输队列失败: {}", e))?;
+
+    let tasks = rows
+        .into_iter()
+        .map(|row| {
+            let status = parse_status(row.get("status"));
+            QueueTask {
+                id: row.get("id"),
+                kind: parse_kind(row.get("kind")),
+                filename: row.get("filename"),
+                local_path: row.get("local_path"),
+                remote_path: row.get("remote_path"),
+                server: row.get("server"),
+                source: parse_source(row.get("source")),
+                transferred: row.get::<i64, _>("transferred").max(0) as u64,
+                total: row.get::<i64, _>("total").max(0) as u64,
+                retries: row.get::<i64, _>("retries").max(0) as u32,
+                attempts: serde_json::from_str(row.get("attempts")).unwrap_or_default(),
+                status: if status == QueueStatus::Running {
+                    QueueStatus::Paused
+                } else {
+                    status
+                },
+                error: row.get("error"),
+                as_task: row.get("as_task"),
+                password: None,
+            }
+        })
+        .collect();
+    Ok(tasks)
+}
+
+/// 写入或更新任务快照（加密目录密码保存在凭据存储中，不写入数据库）。
+pub async fn save_task(pool: &SqlitePool, task: &QueueTask) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO queue_tasks (id, kind, filename, local_path, remote_path, server, source,
+                                  status, transferred, total, retries, attempts, error,
+                                  as_task, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+            status = excluded.status,
+            transferred = excluded.transferred,
+            total = excluded.total,
+            retries = excluded.retries,
+            attempts = excluded.attempts,
+            error = excluded.error",
+    )
+    .bind(&task.id)
+    .bind(kind_str(task.kind))
+    .bind(&task.filename)
+    .bind(&task.local_path)
+    .bind(&task.remote_path)
+    .bind(&task.server)
+    .bind(source_str(task.source))
+    .bind(status_str(task.status))
+    .bind(task.transferred as i64)
+    .bind(task.total as i64)
+    .bind(task.retries as i64)
+    .bind(serde_json::to_string(&task.attempts).unwrap_or_else(|_| "[]".to_string()))
+    .bind(&task.error)
+    .bind(task.as_task)
+    .bind(now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("保存传输任务失败: {}", e))?;
+    Ok(())
+}
+
+/// 删除任务记录。
+pub async fn delete_task(pool: &SqlitePool, id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM queue_tasks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("删除传输任务失败: {}", e))?;
+    Ok(())
+}
+
+fn kind_str(kind: QueueKind) -> &'static str {
+    match kind {
+        QueueKind::Upload => "Upload",
+        QueueKind::Download => "Download",
+    }
+}
+
+fn parse_kind(value: &str) -> QueueKind {
+    match value {
+        "Upload" => QueueKind::Upload,
+        _ => QueueKind::Download,
+    }
+}
+
+fn source_str(source: QueueSource) -> &'static str {
+    match source {
+        QueueSource::Openlist => "Openlist",
+        QueueSource::Navidrome => "Navidrome",
+    }
+}
+
+fn parse_source(value: &str) -> QueueSource {
+    match value {
+        "Navidrome" => QueueSource::Navidrome,
+        _ => QueueSource::Openlist,
+    }
+}
+
+fn status_str(status: QueueStatus) -> &'static str {
+    match status {
+        QueueStatus::Queued => "Queued",
+        QueueStatus::Running => "Running",
+        QueueStatus::Paused => "Paused",
+        QueueStatus::Failed => "Failed",
+        QueueStatus::Done => "Done",
+        QueueStatus::Cancelled => "Cancelled",
+    }
+}
+
+fn parse_status(value: &str) -> QueueStatus {
+    match value {
+        "Queued" => QueueStatus::Queued,
+        "Running" => QueueStatus::Running,
+        "Failed" => QueueStatus::Failed,
+        "Done" => QueueStatus::Done,
+        "Cancelled" => QueueStatus::Cancelled,
+        _ => QueueStatus::Paused,
+    }
+}
@@ -0,0 +1,72 @@
+use super::ProgressReporter;
+use crate::app_state::QueueTask;
+use crate::openlist::{self, OpenlistClient, PutRequest};
+use crate::retry::Failure;
+use futures_util::StreamExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tauri::AppHandle;
+use tokio_util::io::ReaderStream;
+
+/// 每次从磁盘读取的块大小。
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// 执行一次上传任务，每次都从头开始（OpenList 的 put 接口不支持续传）。
+pub async fn run_upload(app: &AppHandle, task: &QueueTask) -> Result<(), Failure> {
+    let client = openlist::session_client_for(app, &task.server)?;
+    let reporter = Arc::new(ProgressReporter::new(app.clone(), task.id.clone()));
+    upload_file(
+        &client,
+        Path::new(&task.local_path),
+        &task.remote_path,
+        task.as_task,
+        reporter,
+    )
+    .await
+}
+
+/// 从磁盘流式读取文件并上传，内存占用只与块大小相关。
+async fn upload_file(
+    client: &OpenlistClient,
+    local_path: &Path,
+    remote_path: &str,
+    as_task: bool,
+    reporter: Arc<ProgressReporter>,
+) -> Result<(), Failure> {
+    let file = tokio::fs::File::open(local_path)
+        .await
+        .map_err(|e| format!("打开本地文件失败: {}", e))?;
+    let metadata = file
+        .metadata()
+        .await
+        .map_err(|e| format!("读取文件信息失败: {}", e))?;
+    let size = metadata.len();
+    let last_modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64);
+
+    reporter.report(0, size);
+    let mut sent = 0u64;
+    let stream = ReaderStream::with_capacity(file, CHUNK_SIZE).map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            sent += bytes.len() as u64;
+            reporter.report(sent, size);
+        }
+        chunk
+    });
+
+    client
+        .put(
+            remote_path,
+            PutRequest {
+                body: reqwest::Body::wrap_stream(stream),
+                size,
+                last_modified,
+                as_task,
+            },
+        )
+        .await
+}
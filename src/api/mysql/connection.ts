@@ -1,13 +1,16 @@
-import  Database from "@tauri-apps/plugin-sql";
+import { invoke } from "@tauri-apps/api/core";
+import { toMysqlTarget } from "../../services/mysql-config";
 import type { MysqlConfig, MysqlTestResult } from "../../types/settings";
 
+// 测试 MySQL 连接：密码留空时使用凭据存储中已保存的密码
 export async function testMysqlConnection(
   config: MysqlConfig
 ): Promise<MysqlTestResult> {
   try {
-    const databaseUrl = `mysql://${config.username}:${config.password}@${config.host}:${config.port}/${config.database}`;
-    const db = await Database.load(databaseUrl);
-    await db.close();
+    await invoke("mysql_test", {
+      config: toMysqlTarget(config),
+      password: config.password || null,
+    });
     return { success: true, message: "MySQL连接成功" };
   } catch (error) {
     const errorMessage = error instanceof Error ? error.message : String(error);
@@ -1,5 +1,6 @@
 export { loginNavidrome } from "./login";
-export { buildStreamUrl, fetchAllSongs, getSongById, getSongs } from "./songs";
+export { registerNavidromeServer } from "./server";
+export { fetchAllSongs, getSongById, getSongs } from "./songs";
 export {
   buildCoverUrl,
   buildNavidromeUrl,
@@ -0,0 +1,44 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { LoginPayload } from "../../types/auth";
+import { normalizeBaseUrl } from "./utils";
+
+// Rust 服务器注册表中的配置，字段与 app_state.rs 的 ServerConfig 对应
+interface ServerConfig {
+  id: string;
+  kind: "navidrome" | "openlist";
+  base_url: string;
+  username: string;
+  key_ref: string;
+}
+
+// 把 Navidrome 账号登记到 Rust 服务器注册表，密码只写入凭据存储，返回服务器 ID；
+// 下载与在线播放由 Rust 按 ID 生成带鉴权的地址，密码不再出现在前端拼接的 URL 中
+export async function registerNavidromeServer(payload: LoginPayload): Promise<string> {
+  const baseUrl = normalizeBaseUrl(payload.baseUrl);
+  const servers = await invoke<ServerConfig[]>("list_servers");
+  const existing = servers.find(
+    (server) =>
+      server.kind === "navidrome" &&
+      server.username === payload.username &&
+      normalizeBaseUrl(server.base_url) === baseUrl
+  );
+
+  const keyRef = existing?.key_ref ?? `navidrome:${payload.username}@${baseUrl}`;
+  await invoke("set_credential", { keyRef, kind: "password", secret: payload.password });
+  const updated = await invoke<ServerConfig[]>("add_server", {
+    id: existing?.id ?? null,
+    kind: "navidrome",
+    name: "",
+    baseUrl,
+    username: payload.username,
+    keyRef,
+  });
+
+  const server = existing
+    ? updated.find((item) => item.id === existing.id)
+    : updated.find((item) => item.key_ref === keyRef);
+  if (!server) {
+    throw new Error("登记 Navidrome 服务器失败");
+  }
+  return server.id;
+}
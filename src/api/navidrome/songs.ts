@@ -135,23 +135,6 @@ async function fetchSongsWithSubsonic(
   return results;
 }
 
-// 构造可直接播放的音频流地址，供播放器组件使用
-export function buildStreamUrl(options: FetchSongsOptions & { songId: string }) {
-  const baseUrl = options.baseUrl.trim();
-  if (!baseUrl) {
-    throw new Error("缺少 Navidrome 基础地址，无法生成播放链接");
-  }
-
-  const auth = resolveSubsonicAuth(options);
-  if (!auth) {
-    throw new Error("缺少 Subsonic 凭据，无法生成播放链接");
-  }
-
-  const params = buildSubsonicTokenParams(auth);
-  params.set("id", options.songId);
-  return buildNavidromeUrl(baseUrl, `rest/stream?${params.toString()}`);
-}
-
 // 支持 Bearer Token 与 Subsonic 双栈拉取歌曲
 export async function fetchAllSongs(options: FetchSongsOptions): Promise<NavidromeSong[]> {
   const baseUrl = options.baseUrl.trim();
@@ -1,16 +1,13 @@
-import { requestJson } from "../../utils/request";
-import {
-  ensureOpenlistSuccess,
-  normalizeOpenlistBaseUrl,
-  OpenlistApiError,
-  type OpenlistApiResponse,
-} from "./utils";
+import { invoke } from "@tauri-apps/api/core";
+import { toOpenlistError } from "./utils";
 
+// openlist_browse 返回的条目，字段与 commands/mod.rs 的 FileEntry 对应
 interface RawOpenlistEntry {
   name: string;
-  size?: number;
-  modified?: string;
-  is_dir?: boolean;
+  path: string;
+  size: number;
+  modified: string;
+  is_dir: boolean;
 }
 
 export interface OpenlistFileEntry {
@@ -30,7 +27,11 @@ export interface OpenlistDirectoryResult {
   directories: { name: string; path: string }[];
 }
 
-type OpenlistListResponse = OpenlistApiResponse<{ content?: RawOpenlistEntry[] }>;
+interface OpenlistListing {
+  entries: RawOpenlistEntry[];
+  total: number;
+  write: boolean;
+}
 
 function formatSize(size?: number) {
   if (!size || size <= 0) return "--";
@@ -53,65 +54,35 @@ function formatDate(value?: string) {
   return date.toLocaleString();
 }
 
-function joinPath(parent: string, name: string) {
-  if (!parent || parent === "/") return `/${name}`;
-  return `${parent}/${name}`;
-}
-
-// 拉取指定路径下的真实目录与文件列表
-export async function listOpenlistDirectory(
-  baseUrl: string,
-  token: string,
-  path = "/"
-): Promise<OpenlistDirectoryResult> {
-  const normalizedBaseUrl = normalizeOpenlistBaseUrl(baseUrl);
+// 拉取指定路径下的真实目录与文件列表，请求由 Rust 带上凭据存储中的 Token 发出
+export async function listOpenlistDirectory(path = "/"): Promise<OpenlistDirectoryResult> {
   const currentPath = path || "/";
-  const trimmedToken = token?.trim();
-
-  if (!trimmedToken) {
-    throw new Error("未提供 Token，请重新登录后重试");
-  }
-
-  const authorization = trimmedToken.replace(/^Bearer\s+/i, "");
 
+  let listing: OpenlistListing;
   try {
-    const payload = await requestJson<OpenlistListResponse>(`${normalizedBaseUrl}/api/fs/list`, {
-      method: "POST",
-      headers: {
-        "Content-Type": "application/json",
-        Authorization: authorization,
-      },
-      body: { path: currentPath, password: "" },
-    });
-
-    const data = ensureOpenlistSuccess(payload);
-
-    const rawList: RawOpenlistEntry[] = data?.content || [];
-    const entries: OpenlistFileEntry[] = rawList.map((item) => {
-      const isDir = Boolean(item.is_dir);
-      const updatedTime = item.modified ? new Date(item.modified).getTime() : undefined;
-      return {
-        name: isDir ? `${item.name}/` : item.name,
-        type: isDir ? "文件夹" : "文件",
-        size: isDir ? "--" : formatSize(item.size),
-        sizeValue: isDir ? undefined : item.size,
-        updated: formatDate(item.modified),
-        updatedTime,
-        isDir,
-        path: joinPath(currentPath, item.name),
-      };
-    });
-
-    const directories = entries
-      .filter((item) => item.isDir)
-      .map((dir) => ({ name: dir.name.replace(/\/$/, ""), path: dir.path }));
-
-    return { entries, directories };
+    listing = await invoke<OpenlistListing>("openlist_browse", { path: currentPath });
   } catch (error) {
-    if (error instanceof OpenlistApiError) {
-      throw error;
-    }
-    const reason = error instanceof Error ? error.message : String(error);
-    throw new Error(`获取目录失败：${reason}`);
+    throw toOpenlistError(error);
   }
+
+  const entries: OpenlistFileEntry[] = listing.entries.map((item) => {
+    const isDir = Boolean(item.is_dir);
+    const updatedTime = item.modified ? new Date(item.modified).getTime() : undefined;
+    return {
+      name: isDir ? `${item.name}/` : item.name,
+      type: isDir ? "文件夹" : "文件",
+      size: isDir ? "--" : formatSize(item.size),
+      sizeValue: isDir ? undefined : item.size,
+      updated: formatDate(item.modified),
+      updatedTime,
+      isDir,
+      path: item.path,
+    };
+  });
+
+  const directories = entries
+    .filter((item) => item.isDir)
+    .map((dir) => ({ name: dir.name.replace(/\/$/, ""), path: dir.path }));
+
+  return { entries, directories };
 }
@@ -1,15 +1,15 @@
+import { invoke } from "@tauri-apps/api/core";
 import type { OpenlistLoginPayload, OpenlistLoginSuccess } from "../../types/openlist";
-import { requestJson } from "../../utils/request";
 import { normalizeOpenlistBaseUrl } from "./utils";
 
-interface OpenlistLoginResponse {
-  data?: { token?: string };
-  token?: string;
-  message?: string;
-  error?: string;
+// openlist_login 的返回值，字段与 commands/mod.rs 的 OpenlistLogin 对应
+interface OpenlistLoginResult {
+  base_url: string;
+  username: string;
+  key_ref: string;
 }
 
-// OpenList 登录请求：通过真实接口换取 Token，确保凭据有效
+// OpenList 登录请求：由 Rust 换取 Token 并写入凭据存储，前端只拿到 key_ref
 export async function loginOpenlist(
   payload: OpenlistLoginPayload
 ): Promise<OpenlistLoginSuccess & { baseUrl: string }> {
@@ -20,25 +20,16 @@ export async function loginOpenlist(
     throw new Error("请填写用户名和密码");
   }
 
-  // 请求 OpenList 官方登录接口，成功后返回可复用的 Token
   try {
-    const payload = await requestJson<OpenlistLoginResponse>(`${baseUrl}/api/auth/login`, {
-      method: "POST",
-      headers: {
-        "Content-Type": "application/json",
-      },
-      body: { username, password },
-    });
-
-    const token = payload.data?.token || payload.token;
-    if (!token) {
-      throw new Error("登录成功但未返回 Token，请检查 OpenList 版本");
-    }
-
-    return {
+    const result = await invoke<OpenlistLoginResult>("openlist_login", {
       baseUrl,
-      token,
       username,
+      password,
+    });
+    return {
+      baseUrl: result.base_url,
+      username: result.username,
+      keyRef: result.key_ref,
     };
   } catch (error) {
     const reason = error instanceof Error ? error.message : String(error);
@@ -50,3 +50,10 @@ export function ensureOpenlistSuccess<T>(payload: OpenlistApiResponse<T>): T {
   const reason = payload.message || payload.error || `OpenList 接口返回异常（${code}）`;
   throw new OpenlistApiError(reason, code);
 }
+
+// 把 Rust 命令返回的错误转成 OpenlistApiError，登录失效或凭据缺失时标记为 401
+export function toOpenlistError(error: unknown): OpenlistApiError {
+  const reason = error instanceof Error ? error.message : String(error);
+  const expired = /登录已失效|尚未登录|请重新登录/.test(reason);
+  return new OpenlistApiError(reason, expired ? 401 : undefined);
+}
@@ -67,7 +67,7 @@ router.beforeEach((to, _from, next) => {
   if (
     to.name === "openlist-drive" &&
     openlistHydrated.value &&
-    !openlistState.token
+    !openlistState.keyRef
   ) {
     next({ name: "openlist-login" });
     return;
@@ -77,7 +77,7 @@ router.beforeEach((to, _from, next) => {
   if (
     to.name === "openlist-login" &&
     openlistHydrated.value &&
-    openlistState.token
+    openlistState.keyRef
   ) {
     next({ name: "openlist-drive" });
     return;
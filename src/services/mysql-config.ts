@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import { appDataDir, join } from "@tauri-apps/api/path";
 import { readTextFile, writeTextFile, exists, mkdir } from "@tauri-apps/plugin-fs";
 import type { MysqlConfig } from "../types/settings";
@@ -16,7 +17,15 @@ const DEFAULT_CONFIG: MysqlConfig = {
 };
 
 /**
- * MySQL 配置管理器
+ * 传给 Rust 的连接信息，不含密码
+ */
+export function toMysqlTarget(config: MysqlConfig) {
+  const { host, port, database, username } = config;
+  return { host, port: Number(port), database, username };
+}
+
+/**
+ * MySQL 配置管理器：密码保存在 Rust 凭据存储中，配置文件只记录连接信息
  */
 class MysqlConfigManager {
   private config: MysqlConfig | null = null;
@@ -56,8 +65,12 @@ class MysqlConfigManager {
           port: parsed.port || DEFAULT_CONFIG.port,
           database: parsed.database || DEFAULT_CONFIG.database,
           username: parsed.username || DEFAULT_CONFIG.username,
-          password: parsed.password || "",
+          password: "",
         };
+        // 旧版配置文件保存了明文密码，迁移到凭据存储后重写配置文件
+        if (parsed.password) {
+          await this.saveConfig({ password: parsed.password });
+        }
         console.log("已加载 MySQL 配置:", this.config);
       } catch (error) {
         console.error("读取 MySQL 配置文件失败:", error);
@@ -77,19 +90,33 @@ class MysqlConfigManager {
   }
 
   /**
-   * 保存 MySQL 配置
+   * 保存 MySQL 配置；填写了密码时写入凭据存储，留空则沿用已保存的密码
    */
   async saveConfig(config: Partial<MysqlConfig>): Promise<void> {
     if (!this.config) {
       this.config = { ...DEFAULT_CONFIG };
     }
 
-    // 更新配置
+    // 更新配置，密码不保留在内存与配置文件中
+    const { password, ...rest } = config;
     this.config = {
       ...this.config,
-      ...config,
+      ...rest,
+      password: "",
     };
 
+    if (password) {
+      try {
+        await invoke("mysql_store_password", {
+          config: toMysqlTarget(this.config),
+          password,
+        });
+      } catch (error) {
+        console.error("保存 MySQL 密码失败:", error);
+        throw new Error("保存密码失败:" + error);
+      }
+    }
+
     try {
       const configPath = await this.getConfigPath();
       const dataDir = await appDataDir();
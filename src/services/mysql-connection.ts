@@ -1,6 +1,7 @@
+import { invoke } from "@tauri-apps/api/core";
 import Database from "@tauri-apps/plugin-sql";
 import type { MysqlConfig } from "../types/settings";
-import { mysqlConfigManager } from "./mysql-config";
+import { mysqlConfigManager, toMysqlTarget } from "./mysql-config";
 
 /**
  * MySQL连接管理器
@@ -20,11 +21,10 @@ class MySqlConnectionManager {
 
     this.initializing = true;
 
-    const databaseUrl = `mysql://${config.username}:${config.password}@${config.host}:${config.port}/${config.database}`;
-
     try {
-      // 加载数据库连接
-      this.db = await Database.load(databaseUrl);
+      // 由 Rust 用凭据存储中的密码建立连接，前端只拿到不含密码的连接名
+      const name = await invoke<string>("mysql_connect", { config: toMysqlTarget(config) });
+      this.db = Database.get(name);
 
       // 初始化数据库表
       await this.initTables();
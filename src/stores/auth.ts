@@ -11,6 +11,7 @@ const state = reactive<AuthState>({
   salt: null,
   username: null,
   displayName: null,
+  serverId: null,
 });
 
 async function ensureStore() {
@@ -31,6 +32,7 @@ async function hydrateFromStore() {
       state.salt = saved.salt;
       state.username = saved.username;
       state.displayName = saved.displayName;
+      state.serverId = saved.serverId ?? null;
     }
   } catch (error) {
     console.warn("读取登录态失败，将从空状态启动", error);
@@ -40,13 +42,14 @@ async function hydrateFromStore() {
 const ready = hydrateFromStore();
 
 // 更新登录状态并持久化到本地 Store
-async function setSession(payload: LoginSuccess & { baseUrl: string }) {
+async function setSession(payload: LoginSuccess & { baseUrl: string; serverId: string | null }) {
   const store = await ensureStore();
   state.baseUrl = payload.baseUrl;
   state.token = payload.token;
   state.salt = payload.salt;
   state.username = payload.username;
   state.displayName = payload.displayName;
+  state.serverId = payload.serverId;
   try {
     await store.set(STORAGE_KEY, { ...state });
     await store.save();
@@ -56,6 +59,18 @@ async function setSession(payload: LoginSuccess & { baseUrl: string }) {
   }
 }
 
+// 补登记服务器后回写 ID，旧版本保存的登录态没有这个字段
+async function setServerId(serverId: string) {
+  state.serverId = serverId;
+  try {
+    const store = await ensureStore();
+    await store.set(STORAGE_KEY, { ...state });
+    await store.save();
+  } catch (error) {
+    console.warn("写入服务器 ID 到 Tauri Store 失败", error);
+  }
+}
+
 // 清除本地状态与持久化存储
 async function clearSession() {
   const store = await ensureStore();
@@ -64,6 +79,7 @@ async function clearSession() {
   state.salt = null;
   state.username = null;
   state.displayName = null;
+  state.serverId = null;
   await store.delete(STORAGE_KEY);
   await store.save();
 }
@@ -73,6 +89,7 @@ export function useAuthStore() {
     state,
     ready,
     setSession,
+    setServerId,
     clearSession,
   };
 }
@@ -1,6 +1,12 @@
 // 下载与本地音乐的状态管理，集中处理批量下载、进度与本地歌曲列表
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { computed, reactive } from "vue";
+import { listenQueueEvents } from "../utils/queue-events";
+import type { LibraryChanges, LibraryTrack } from "../types/library";
+import type { AudioMetadata, MetadataResult } from "../types/metadata";
 import type { NavidromeSong } from "../types/navidrome";
+import type { QueueProgress, QueueTask } from "../types/queue";
 import type { DownloadRecord, DownloadStatus, LocalSongRecord } from "../services/library";
 import {
   listDownloadRecords,
@@ -16,8 +22,8 @@ import {
 export type DownloadTab = "local" | "downloaded" | "downloading";
 
 interface DownloadTask extends DownloadRecord {
-  controller?: AbortController;
-  resumeFn?: () => Promise<void>;
+  // Rust 传输队列中的任务 ID，只保存在内存中，刷新时按歌曲 ID 重新对应
+  queueId?: string;
 }
 
 interface DownloadState {
@@ -52,24 +58,87 @@ async function refreshLocalSongs() {
 
 async function refreshDownloads(status?: DownloadStatus) {
   const records = await listDownloadRecords(status);
-  state.downloads = records.map((item) => ({ ...item }));
+  const queueIds = new Map(state.downloads.map((item) => [item.songId, item.queueId]));
+  state.downloads = records.map((item) => ({ ...item, queueId: queueIds.get(item.songId) }));
+  // 重启后记录里的状态可能已过时，以每首歌在 Rust 队列中最新的任务为准
+  const tasks = await invoke<QueueTask[]>("queue_status");
+  const latest = new Map<string, QueueTask>();
+  for (const task of tasks) {
+    if (task.source === "Navidrome") latest.set(task.remote_path, task);
+  }
+  for (const task of latest.values()) {
+    await applyQueueTask(task);
+  }
 }
 
-async function addLocalSongFromPath(path: string, size: number) {
+// 由标签生成本地歌曲记录，缺失的字段回退到文件名与“本地文件”
+function toLocalSongRecord(path: string, size: number, metadata: AudioMetadata | null): LocalSongRecord {
   const filename = path.split(/\\|\//).pop() || "未知文件";
-  const title = filename.replace(/\.[^.]+$/, "");
-  const record: LocalSongRecord = {
+  const artist = metadata?.artists.length ? metadata.artists.join(" / ") : metadata?.album_artist;
+  return {
     id: crypto.randomUUID(),
-    title,
-    album: "本地文件",
-    artist: "本地文件",
+    title: metadata?.title || filename.replace(/\.[^.]+$/, ""),
+    album: metadata?.album || "本地文件",
+    artist: artist || "本地文件",
     path,
-    size,
+    size: metadata?.size ?? size,
   };
-  await upsertLocalSong(record);
+}
+
+async function addLocalSongFromPath(path: string, size: number) {
+  let metadata: AudioMetadata | null = null;
+  try {
+    metadata = await invoke<AudioMetadata>("read_audio_metadata", { path });
+  } catch (error) {
+    console.warn("读取音频标签失败，使用文件名作为标题", error);
+  }
+  await upsertLocalSong(toLocalSongRecord(path, size, metadata));
+  await refreshLocalSongs();
+}
+
+// 批量添加本地歌曲，标签在 Rust 侧并行读取，返回读取失败的文件
+async function addLocalSongsFromPaths(paths: string[]) {
+  const results = await invoke<MetadataResult[]>("read_audio_metadata_batch", { paths });
+  const failed: MetadataResult[] = [];
+  for (const result of results) {
+    if (!result.metadata) {
+      failed.push(result);
+      continue;
+    }
+    await upsertLocalSong(toLocalSongRecord(result.path, result.metadata.size, result.metadata));
+  }
   await refreshLocalSongs();
+  return failed;
 }
 
+// 将 Rust 扫描到的变化同步到本地音乐表，改名、移动的歌曲沿用原 ID 只更新路径
+async function applyLibraryChanges(changes: LibraryChanges) {
+  const toRecord = (track: LibraryTrack): LocalSongRecord => ({
+    id: track.id,
+    title: track.title,
+    artist: track.artist || track.album_artist || "本地文件",
+    album: track.album || "本地文件",
+    path: track.path,
+    size: track.size,
+  });
+  for (const track of [...changes.added, ...changes.updated, ...changes.moved]) {
+    await upsertLocalSong(toRecord(track));
+  }
+  if (changes.removed.length) {
+    await removeLocalSongs(changes.removed);
+  }
+  await refreshLocalSongs();
+}
+
+// 监听文件夹扫描结果，新增与删除的文件无需手动刷新
+listen<LibraryChanges>("library://changed", (event) => {
+  applyLibraryChanges(event.payload).catch((error) => {
+    console.error("同步本地音乐库失败:", error);
+  });
+}).catch((error) => {
+  console.warn("监听本地音乐库变化失败:", error);
+});
+
 function markDownload(task: DownloadTask) {
   const existingIndex = state.downloads.findIndex((item) => item.songId === task.songId);
   if (existingIndex >= 0) {
@@ -101,15 +170,73 @@ async function deleteLocalSongs(ids: string[]) {
   state.localSongs = state.localSongs.filter((item) => !ids.includes(item.id));
 }
 
-async function trackDownload(
-  song: NavidromeSong,
-  updater: (
-    signal: AbortSignal,
-    targetPath?: string,
-    updateProgress?: (percent: number) => void
-  ) => Promise<{ filePath?: string | null }>,
-  targetPath?: string
-) {
+// 把队列任务的状态同步到下载记录，已取消的任务直接移除记录
+async function applyQueueTask(task: QueueTask) {
+  if (task.source !== "Navidrome") return;
+  const target = state.downloads.find(
+    (item) => item.queueId === task.id || (!item.queueId && item.songId === task.remote_path)
+  );
+  if (!target) return;
+
+  target.queueId = task.id;
+  switch (task.status) {
+    case "Cancelled":
+      await clearDownload(target.songId);
+      return;
+    case "Done":
+      target.status = "success";
+      target.progress = 100;
+      target.filePath = task.local_path;
+      target.errorMessage = null;
+      break;
+    case "Failed":
+      target.status = "failed";
+      target.errorMessage = task.error;
+      break;
+    case "Running":
+      target.status = "downloading";
+      break;
+    default:
+      // 排队中与已暂停的任务都显示为等待
+      target.status = "pending";
+  }
+  if (task.total > 0 && task.status !== "Done") {
+    target.progress = Math.min(99, Math.round((task.transferred / task.total) * 100));
+  }
+  await persistDownload(target);
+}
+
+// 进度只更新内存中的记录，状态变化时才写入数据库
+function applyQueueProgress(progress: QueueProgress) {
+  const target = state.downloads.find((item) => item.queueId === progress.id);
+  if (!target || progress.total <= 0) return;
+  const percent = Math.min(99, Math.round((progress.transferred / progress.total) * 100));
+  if (percent === target.progress) return;
+  target.progress = percent;
+  markDownload(target);
+}
+
+function reportQueueError(error: unknown) {
+  console.error("同步下载状态失败:", error);
+}
+
+// 下载在 Rust 传输队列中执行，这里只跟随队列事件更新记录
+listenQueueEvents({
+  progress: applyQueueProgress,
+  state: (task) => {
+    if (task.status === "Queued" || task.status === "Running" || task.status === "Paused") {
+      applyQueueTask(task).catch(reportQueueError);
+    }
+  },
+  finished: (task) => {
+    applyQueueTask(task).catch(reportQueueError);
+  },
+}).catch((error) => {
+  console.warn("监听传输队列失败:", error);
+});
+
+// 把歌曲加入 Rust 下载队列，优先复制在线播放缓存，否则从 Navidrome 断点续传
+async function enqueueDownload(song: NavidromeSong, serverId: string, targetPath: string) {
   // 尝试复用已有的下载记录，方便恢复时保持路径一致
   const existing = state.downloads.find((item) => item.songId === song.id);
   const task: DownloadTask = existing
@@ -122,91 +249,31 @@ async function trackDownload(
         status: "pending",
         progress: 0,
       };
-
-  if (targetPath) {
-    task.filePath = targetPath;
-  }
-
+  task.filePath = task.filePath || targetPath;
+  task.status = "pending";
+  task.errorMessage = null;
+  // 先清空旧的队列 ID，入队时推送的事件按歌曲 ID 对应到这条记录
+  task.queueId = undefined;
   markDownload(task);
 
-  const updateProgress = (percent: number) => {
-    const normalized = Math.min(99, Math.max(0, Math.round(percent)));
-    if (normalized === task.progress) return;
-    task.progress = normalized;
-    markDownload(task);
-  };
-
-  const controller = new AbortController();
-  task.controller = controller;
-  task.status = "downloading";
-  task.progress = Math.max(task.progress || 0, 5);
-
-  // 保存恢复函数，便于后续继续下载
-  task.resumeFn = async () => {
-    const newController = new AbortController();
-    task.controller = newController;
-    task.status = "downloading";
-    await persistDownload(task);
-
-    try {
-      const result = await updater(newController.signal, task.filePath || targetPath, updateProgress);
-      if (newController.signal.aborted) {
-        task.status = "cancelled";
-        task.progress = 0;
-        await persistDownload(task);
-        return;
-      }
-
-      task.status = "success";
-      task.progress = 100;
-      task.filePath = result.filePath || task.filePath || targetPath;
-      task.resumeFn = undefined;
-      await persistDownload(task);
-    } catch (error) {
-      const hint = error instanceof Error ? error.message : String(error);
-      task.status = "failed";
-      task.progress = 0;
-      task.errorMessage = hint;
-      await persistDownload(task);
-    }
-  };
-
-  await persistDownload(task);
-
-  try {
-    const result = await updater(controller.signal, task.filePath || targetPath, updateProgress);
-    if (controller.signal.aborted) {
-      task.status = "cancelled";
-      task.progress = 0;
-      await persistDownload(task);
-      return;
-    }
-
-    task.status = "success";
-    task.progress = 100;
-    task.filePath = result.filePath || task.filePath || targetPath;
-    task.resumeFn = undefined;
-    await persistDownload(task);
-  } catch (error) {
-    const hint = error instanceof Error ? error.message : String(error);
-    task.status = "failed";
-    task.progress = 0;
-    task.errorMessage = hint;
-    await persistDownload(task);
-  }
+  const queued = await invoke<QueueTask>("navidrome_download", {
+    serverId,
+    songId: song.id,
+    localPath: task.filePath,
+  });
+  const current = state.downloads.find((item) => item.songId === song.id) ?? task;
+  current.queueId = queued.id;
+  await persistDownload(current);
 }
 
 async function cancelDownload(songId: string) {
   const target = state.downloads.find((item) => item.songId === songId);
-  target?.controller?.abort();
-  if (target) {
-    target.status = "cancelled";
-    target.progress = 0;
-    // 从数据库中彻底删除，避免刷新后又自动恢复
-    await removeDownloadRecord(songId);
-    // 从当前列表中移除，给用户即时反馈
-    state.downloads = state.downloads.filter((item) => item.songId !== songId);
+  if (!target) return;
+  if (target.queueId) {
+    await invoke("queue_cancel", { id: target.queueId });
   }
+  // 从数据库中彻底删除，避免刷新后又自动恢复
+  await clearDownload(songId);
 }
 
 async function cancelDownloads(songIds: string[]) {
@@ -218,31 +285,16 @@ async function cancelDownloads(songIds: string[]) {
 
 async function pauseDownload(songId: string) {
   const target = state.downloads.find((item) => item.songId === songId);
-  if (!target) return;
-
-  target?.controller?.abort();
-  if (target) {
-    target.status = "pending";
-    target.controller = undefined;
-    await persistDownload(target);
-  }
+  if (!target?.queueId) return;
+  await invoke("queue_pause", { id: target.queueId });
 }
 
+// 继续暂停或失败的任务，已写入的部分会从断点续传；不在队列中时返回 false，由调用方重新加入
 async function resumeDownload(songId: string) {
   const target = state.downloads.find((item) => item.songId === songId);
-  if (!target || !target.resumeFn) return;
-
-  target.status = "downloading";
-  await persistDownload(target);
-
-  try {
-    await target.resumeFn();
-  } catch (error) {
-    const hint = error instanceof Error ? error.message : String(error);
-    target.status = "failed";
-    target.errorMessage = hint;
-    await persistDownload(target);
-  }
+  if (!target?.queueId) return false;
+  await invoke("queue_resume", { id: target.queueId });
+  return true;
 }
 
 const downloadingList = computed(() =>
@@ -260,7 +312,8 @@ export function useDownloadStore() {
     refreshLocalSongs,
     refreshDownloads,
     addLocalSongFromPath,
-    trackDownload,
+    addLocalSongsFromPaths,
+    enqueueDownload,
     cancelDownload,
     cancelDownloads,
     pauseDownload,
@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import { reactive } from "vue";
 import { addFavorite, listFavorites, removeFavorite } from "../services/favorite";
 
@@ -12,11 +13,21 @@ const state = reactive<{
   refreshCounter: 0,
 });
 
+// 收藏的歌曲在播放缓存中固定，超出容量时不会被淘汰
+async function syncFavoritePins() {
+  try {
+    await invoke("cache_set_pins", { group: "favorites", songIds: [...state.favoriteIds] });
+  } catch (error) {
+    console.warn("同步收藏缓存固定项失败:", error);
+  }
+}
+
 // 加载收藏列表
 async function loadFavorites() {
   try {
     const records = await listFavorites();
     state.favoriteIds = new Set(records.map((item) => item.songId));
+    await syncFavoritePins();
   } catch (error) {
     console.error("加载收藏列表失败:", error);
   }
@@ -48,6 +59,7 @@ async function addFavoriteSong(songId: string, songData: {
   });
   state.favoriteIds.add(songId);
   state.refreshCounter++;
+  await syncFavoritePins();
 }
 
 // 移除收藏
@@ -55,6 +67,7 @@ async function removeFavoriteSong(songId: string) {
   await removeFavorite(songId);
   state.favoriteIds.delete(songId);
   state.refreshCounter++;
+  await syncFavoritePins();
 }
 
 // 切换收藏状态
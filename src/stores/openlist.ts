@@ -1,4 +1,5 @@
 import { reactive, ref } from "vue";
+import { invoke } from "@tauri-apps/api/core";
 import { Store } from "@tauri-apps/plugin-store";
 import type { OpenlistSessionState, OpenlistLoginSuccess } from "../types/openlist";
 
@@ -7,8 +8,8 @@ let backingStore: Store | null = null;
 
 const state = reactive<OpenlistSessionState>({
   baseUrl: null,
-  token: null,
   username: null,
+  keyRef: null,
 });
 
 // 标记是否已完成本地会话恢复，便于路由层基于真实登录态决策
@@ -28,8 +29,8 @@ async function hydrateFromStore() {
     const saved = await store.get<OpenlistSessionState | null>(STORAGE_KEY);
     if (saved) {
       state.baseUrl = saved.baseUrl;
-      state.token = saved.token;
       state.username = saved.username;
+      state.keyRef = saved.keyRef ?? null;
     }
   } catch (error) {
     console.warn("读取 OpenList 登录态失败，将从空状态启动", error);
@@ -40,12 +41,12 @@ async function hydrateFromStore() {
 
 const ready = hydrateFromStore();
 
-// 写入 OpenList 登录态
+// 写入 OpenList 登录态，Token 已由 Rust 存入凭据存储，这里只保存引用
 async function setSession(payload: OpenlistLoginSuccess & { baseUrl: string }) {
   const store = await ensureStore();
   state.baseUrl = payload.baseUrl;
-  state.token = payload.token;
   state.username = payload.username;
+  state.keyRef = payload.keyRef;
   try {
     await store.set(STORAGE_KEY, { ...state });
     await store.save();
@@ -54,12 +55,17 @@ async function setSession(payload: OpenlistLoginSuccess & { baseUrl: string }) {
   }
 }
 
-// 清空登录态
+// 清空登录态，同时删除凭据存储中的 Token
 async function clearSession() {
   const store = await ensureStore();
+  if (state.keyRef) {
+    await invoke("delete_credential", { keyRef: state.keyRef }).catch((error) => {
+      console.warn("删除 OpenList Token 失败", error);
+    });
+  }
   state.baseUrl = null;
-  state.token = null;
   state.username = null;
+  state.keyRef = null;
   await store.delete(STORAGE_KEY);
   await store.save();
 }
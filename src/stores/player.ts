@@ -1,8 +1,9 @@
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 import { join } from "@tauri-apps/api/path";
-import { exists, mkdir, readFile, writeFile } from "@tauri-apps/plugin-fs";
+import { exists, mkdir } from "@tauri-apps/plugin-fs";
 import { computed, reactive } from "vue";
 import type { NavidromeSong } from "../api/navidrome";
-import { buildStreamUrl, getSongById } from "../api/navidrome";
+import { getSongById } from "../api/navidrome";
 import { listDownloadRecords, listLocalSongs } from "../services/library";
 import type {
   PlayAuthContext,
@@ -18,6 +19,9 @@ import { SETTING_KEYS } from "../constants/setting-keys";
 import { recordRecentPlay } from "../services/recent-plays";
 import { emitRecentPlayUpdated } from "../utils/recent-play-events";
 import { pathConfigManager } from "../services/path-config";
+import { useAuthStore } from "./auth";
+import { useSettingsStore } from "./settings";
+import { resolveNavidromeServerId } from "../utils/navidrome-context";
 
 const audio = new Audio();
 audio.preload = "metadata";
@@ -150,8 +154,8 @@ function getNextShuffleIndex() {
   return candidates[randomIndex];
 }
 
-// 音频 MIME 类型，统一生成 Blob URL 时使用
-const AUDIO_MIME = "audio/mpeg";
+// Rust 侧注册的本地文件协议，支持 Range 请求，拖动进度无需读入整个文件
+const MEDIA_SCHEME = "musicboom";
 
 // 将当前播放信息持久化，便于刷新或重新登录后恢复
 async function persistSnapshot() {
@@ -177,42 +181,69 @@ async function persistSnapshot() {
 
 
 
+// 读取缓存目录，优先使用 path-config 中的设置，保证与下载设置保持一致
+async function resolveCacheDir(): Promise<string | undefined> {
+  let cacheDir: string | undefined;
+  try {
+    await pathConfigManager.initialize();
+    cacheDir = pathConfigManager.getConfig()?.cacheDir?.trim();
+  } catch (error) {
+    console.warn("读取路径配置失败，将回退到数据库设置", error);
+  }
+
+  if (!cacheDir) {
+    const downloadSettings = await readSetting<DownloadSettings>(SETTING_KEYS.DOWNLOAD);
+    cacheDir = downloadSettings?.cacheDir?.trim();
+  }
+  return cacheDir;
+}
+
+// 同步 musicboom:// 协议允许访问的目录，本地歌曲与下载文件按单个文件授权
+async function syncMediaRoots(files: string[], cacheDir?: string) {
+  const downloadSettings = await readSetting<DownloadSettings>(SETTING_KEYS.DOWNLOAD);
+  await invoke("set_media_roots", {
+    roots: {
+      library: files,
+      downloads: downloadSettings?.musicDir?.trim() || null,
+      cache: cacheDir || null,
+    },
+  });
+}
+
 // 计算可播放的音频地址，优先使用本地文件，返回播放源类型
-async function resolvePlayableSource(track: NavidromeSong, context: PlayAuthContext): Promise<{ url: string; source: PlaySource }> {
-  // 1. 首先检查本地音乐库
+async function resolvePlayableSource(track: NavidromeSong): Promise<{ url: string; source: PlaySource }> {
   const localSongs = await listLocalSongs();
+  const downloadRecords = await listDownloadRecords("success");
+  const cacheDir = await resolveCacheDir();
+  try {
+    await syncMediaRoots(
+      [
+        ...localSongs.map((song) => song.path),
+        ...downloadRecords.flatMap((record) => (record.filePath ? [record.filePath] : [])),
+      ],
+      cacheDir
+    );
+  } catch (error) {
+    console.warn("同步本地文件目录失败", error);
+  }
+
+  // 1. 首先检查本地音乐库
   const localSong = localSongs.find(song => song.id === track.id);
   if (localSong && await exists(localSong.path)) {
-    const localBuffer = await readFile(localSong.path);
     console.log(`使用本地音乐播放: ${track.title}`);
     state.playSource = "local";
-    return { url: URL.createObjectURL(new Blob([localBuffer], { type: AUDIO_MIME })), source: "local" };
+    return { url: convertFileSrc(localSong.path, MEDIA_SCHEME), source: "local" };
   }
 
   // 2. 检查下载记录中是否有成功下载的文件
-  const downloadRecords = await listDownloadRecords("success");
   const downloadRecord = downloadRecords.find(record => record.songId === track.id);
   if (downloadRecord?.filePath && await exists(downloadRecord.filePath)) {
-    const downloadBuffer = await readFile(downloadRecord.filePath);
     console.log(`使用下载文件播放: ${track.title}`);
     state.playSource = "downloaded";
-    return { url: URL.createObjectURL(new Blob([downloadBuffer], { type: AUDIO_MIME })), source: "downloaded" };
+    return { url: convertFileSrc(downloadRecord.filePath, MEDIA_SCHEME), source: "downloaded" };
   }
 
   // 3. 检查缓存目录
-  let cacheDir: string | undefined;
-  // 优先读取 path-config 中的缓存目录，保证与下载设置保持一致
-  try {
-    await pathConfigManager.initialize();
-    cacheDir = pathConfigManager.getConfig()?.cacheDir?.trim();
-  } catch (error) {
-    console.warn("读取路径配置失败，将回退到数据库设置", error);
-  }
-
-  if (!cacheDir) {
-    const downloadSettings = await readSetting<DownloadSettings>(SETTING_KEYS.DOWNLOAD);
-    cacheDir = downloadSettings?.cacheDir?.trim();
-  }
   let cachePath: string | null = null;
 
   if (cacheDir) {
@@ -221,10 +252,13 @@ async function resolvePlayableSource(track: NavidromeSong, context: PlayAuthCont
       cachePath = await join(cacheDir, `${track.id}.mp3`);
 
       if (await exists(cachePath)) {
-        const cachedBuffer = await readFile(cachePath);
         console.log(`使用缓存文件播放: ${track.title}`);
         state.playSource = "cached";
-        return { url: URL.createObjectURL(new Blob([cachedBuffer], { type: AUDIO_MIME })), source: "cached" };
+        // 记录播放时间，缓存超出容量时最久未播放的先被淘汰
+        invoke("cache_touch", { songId: track.id }).catch((error) => {
+          console.warn("记录缓存播放时间失败", error);
+        });
+        return { url: convertFileSrc(cachePath, MEDIA_SCHEME), source: "cached" };
       }
     } catch (error) {
       console.warn("创建或检查缓存目录失败，将直接播放流", error);
@@ -232,25 +266,29 @@ async function resolvePlayableSource(track: NavidromeSong, context: PlayAuthCont
     }
   }
 
-  // 4. 回退到在线流播放
-  const streamUrl = buildStreamUrl({ ...context, songId: track.id });
+  // 4. 回退到在线流播放，播放地址由 Rust 用服务器注册表中的凭据生成
+  const { state: authState } = useAuthStore();
+  const { state: settingsState, ready: settingsReady } = useSettingsStore();
+  await settingsReady;
+  const serverId = await resolveNavidromeServerId(authState, settingsState);
   console.log(`使用在线流播放: ${track.title}`);
   state.playSource = "online";
-  
-  if (!cachePath) return { url: streamUrl, source: "online" };
 
-  try {
-    const response = await fetch(streamUrl);
-    if (!response.ok) {
-      throw new Error(`拉取音频流失败，状态码 ${response.status}`);
-    }
-    const buffer = await response.arrayBuffer();
-    await writeFile(cachePath, new Uint8Array(buffer));
-    return { url: URL.createObjectURL(new Blob([buffer], { type: AUDIO_MIME })), source: "cached" };
-  } catch (error) {
-    console.warn("写入缓存失败，将回退为在线播放", error);
+  if (!cachePath) {
+    const streamUrl = await invoke<string>("navidrome_stream_url", {
+      serverId,
+      trackId: track.id,
+    });
     return { url: streamUrl, source: "online" };
   }
+
+  // 经 Rust 代理播放，边播边写入缓存目录，完整且大小一致时才保存为缓存文件
+  const path = await invoke<string>("stream_proxy", {
+    serverId,
+    songId: track.id,
+    size: track.size ?? null,
+  });
+  return { url: convertFileSrc(path, MEDIA_SCHEME), source: "online" };
 }
 
 // 封装统一的播放入口
@@ -271,7 +309,7 @@ async function playCurrent() {
   state.loading = true;
   state.error = null;
   try {
-    const { url } = await resolvePlayableSource(track, context);
+    const { url } = await resolvePlayableSource(track);
     // 如果在加载过程中用户已经切换到其他歌曲，则直接放弃当前请求
     if (sessionId !== playSessionId) return;
 
@@ -400,7 +438,7 @@ async function restoreFromSnapshot(context: PlayAuthContext) {
     state.progress = 0;
     state.duration = 0;
 
-    const { url } = await resolvePlayableSource(track, context);
+    const { url } = await resolvePlayableSource(track);
     audio.src = url;
     syncProgress();
 
@@ -31,6 +31,7 @@ const defaultState: SettingsState = {
     overwriteExisting: false,
     sourcePreference: "latest",
     cacheDir: "",
+    cacheLimit: 2048,
     speedLimitMode: "auto",
     speedLimit: 3072,
     tags: {
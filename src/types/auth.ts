@@ -20,4 +20,6 @@ export interface AuthState {
   salt: string | null;
   username: string | null;
   displayName: string | null;
+  // Rust 服务器注册表中的 ID，下载与在线播放据此取凭据
+  serverId: string | null;
 }
@@ -0,0 +1,43 @@
+// Rust 扫描本地音乐文件夹得到的歌曲，ID 在改名、移动后保持不变
+export interface LibraryTrack {
+  id: string;
+  path: string;
+  fingerprint: string;
+  size: number;
+  modified: number;
+  title: string;
+  artist: string | null;
+  album: string | null;
+  album_artist: string | null;
+  track_number: number | null;
+  disc_number: number | null;
+  year: number | null;
+  genre: string | null;
+  duration: number | null;
+  bitrate: number | null;
+  sample_rate: number | null;
+  bit_depth: number | null;
+  channels: number | null;
+}
+
+// library://changed 事件的载荷
+export interface LibraryChanges {
+  added: LibraryTrack[];
+  updated: LibraryTrack[];
+  moved: LibraryTrack[];
+  removed: string[];
+  // 本次无法读取或保存的文件，下次扫描时重试
+  errors: LibraryScanError[];
+}
+
+export interface LibraryScanError {
+  path: string;
+  error: string;
+}
+
+// library://progress 事件的载荷
+export interface ScanProgress {
+  phase: "walking" | "reading" | "done";
+  done: number;
+  total: number | null;
+}
@@ -0,0 +1,46 @@
+// 本地音频文件的元数据，由 Rust 的 read_audio_metadata 命令返回
+export interface EmbeddedCover {
+  mime: string;
+  size: number;
+  // 导出到缓存目录 covers 下的图片路径，未设置缓存目录时为空
+  path: string | null;
+}
+
+export interface AudioMetadata {
+  path: string;
+  size: number;
+  mime: string;
+  title: string | null;
+  artists: string[];
+  album: string | null;
+  album_artist: string | null;
+  track_number: number | null;
+  track_total: number | null;
+  disc_number: number | null;
+  disc_total: number | null;
+  year: number | null;
+  genre: string | null;
+  // 时长（秒）
+  duration: number | null;
+  // 平均比特率（kbps）
+  bitrate: number | null;
+  sample_rate: number | null;
+  bit_depth: number | null;
+  channels: number | null;
+  cover: EmbeddedCover | null;
+}
+
+// 批量读取时单个文件的结果
+export interface MetadataResult {
+  path: string;
+  metadata: AudioMetadata | null;
+  error: string | null;
+}
+
+// 本地文件中的锚点
+export interface AnchorInfo {
+  path: string;
+  anchor_id: string | null;
+  // 锚点只存在于旧版注释中，尚未迁移到专用字段
+  legacy: boolean;
+}
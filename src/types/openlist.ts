@@ -5,13 +5,14 @@ export interface OpenlistLoginPayload {
   password: string;
 }
 
+// Token 只保存在 Rust 凭据存储中，前端通过 keyRef 引用
 export interface OpenlistLoginSuccess {
-  token: string;
   username: string;
+  keyRef: string;
 }
 
 export interface OpenlistSessionState {
   baseUrl: string | null;
-  token: string | null;
   username: string | null;
+  keyRef: string | null;
 }
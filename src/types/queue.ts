@@ -0,0 +1,40 @@
+// Rust 传输队列中的任务，字段与 app_state.rs 的 QueueTask 对应
+export type QueueKind = "Upload" | "Download";
+
+// Navidrome 任务的 server 为服务器 ID，remote_path 为歌曲 ID
+export type QueueSource = "Openlist" | "Navidrome";
+
+export type QueueStatus = "Queued" | "Running" | "Paused" | "Failed" | "Done" | "Cancelled";
+
+export interface QueueAttempt {
+  attempt: number;
+  error: string;
+  at: number;
+  retry_in: number | null;
+}
+
+export interface QueueTask {
+  id: string;
+  kind: QueueKind;
+  filename: string;
+  local_path: string;
+  remote_path: string;
+  server: string;
+  source: QueueSource;
+  transferred: number;
+  total: number;
+  retries: number;
+  attempts: QueueAttempt[];
+  status: QueueStatus;
+  error: string | null;
+  as_task: boolean;
+}
+
+// queue://progress 事件的载荷
+export interface QueueProgress {
+  id: string;
+  transferred: number;
+  total: number;
+  speed: number;
+  eta: number | null;
+}
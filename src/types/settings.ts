@@ -28,11 +28,29 @@ export interface DownloadSettings {
   overwriteExisting: boolean;
   sourcePreference: "latest" | "hifi";
   cacheDir: string;
+  // 在线播放缓存的容量上限（MB），0 表示不限制
+  cacheLimit: number;
   speedLimitMode: "auto" | "manual";
   speedLimit: number;
   tags: DownloadTagOption;
 }
 
+// 文件数量与占用字节数
+export interface UsageEntry {
+  bytes: number;
+  files: number;
+}
+
+// 按类别统计的缓存占用，由 Rust 的 cache_usage 命令返回
+export interface CacheUsage {
+  stream_cache: UsageEntry;
+  pinned: UsageEntry;
+  downloads: UsageEntry;
+  covers: UsageEntry;
+  lyrics: UsageEntry;
+  budget: number;
+}
+
 export interface GeneralSettings {
   closeAction: "minimize" | "exit";
   enableNotifications: boolean;
@@ -1,3 +1,5 @@
+import { registerNavidromeServer } from "../api/navidrome";
+import { useAuthStore } from "../stores/auth";
 import type { FetchSongsOptions } from "../types/navidrome";
 import type { AuthState } from "../types/auth";
 import type { SettingsState } from "../types/settings";
@@ -18,3 +20,24 @@ export function buildNavidromeContext(authState: AuthState, settingsState: Setti
     password: settingsState.navidrome.password,
   };
 }
+
+// 取得 Rust 服务器注册表中的 ID；旧登录态没有登记过时用设置中保存的密码补登记
+export async function resolveNavidromeServerId(
+  authState: AuthState,
+  settingsState: SettingsState
+): Promise<string> {
+  if (authState.serverId) {
+    return authState.serverId;
+  }
+
+  const baseUrl = (authState.baseUrl || settingsState.navidrome.baseUrl || "").trim();
+  const username = (authState.username || settingsState.navidrome.username || "").trim();
+  const password = settingsState.navidrome.password;
+  if (!baseUrl || !username || !password) {
+    throw new Error("缺少 Navidrome 登录信息，请重新登录");
+  }
+
+  const serverId = await registerNavidromeServer({ baseUrl, username, password });
+  await useAuthStore().setServerId(serverId);
+  return serverId;
+}
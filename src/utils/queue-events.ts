@@ -0,0 +1,21 @@
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { QueueProgress, QueueTask } from "../types/queue";
+
+export interface QueueEventHandlers {
+  // 节流后的字节进度、速度与剩余时间
+  progress?: (progress: QueueProgress) => void;
+  // 任务状态变化，载荷为最新的任务快照
+  state?: (task: QueueTask) => void;
+  // 任务完成、失败或取消
+  finished?: (task: QueueTask) => void;
+}
+
+// 订阅 Rust 传输队列推送的事件，返回取消全部订阅的函数
+export async function listenQueueEvents(handlers: QueueEventHandlers): Promise<UnlistenFn> {
+  const unlisteners = await Promise.all([
+    listen<QueueProgress>("queue://progress", (event) => handlers.progress?.(event.payload)),
+    listen<QueueTask>("queue://state", (event) => handlers.state?.(event.payload)),
+    listen<QueueTask>("queue://finished", (event) => handlers.finished?.(event.payload)),
+  ]);
+  return () => unlisteners.forEach((unlisten) => unlisten());
+}